@@ -0,0 +1,587 @@
+#![allow(clippy::useless_conversion)] // these are needed to support e.g. smartstring
+use crate::position::{Position, Positioned, Strand};
+use crate::skip::Skip;
+use crate::string::String;
+
+use rust_htslib::bam::{self, record::Cigar, Read};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+enum AlignmentReader {
+    // with an index (.bai, .csi or .crai) we can skip ahead to query intervals.
+    Indexed(bam::IndexedReader),
+    Stream(bam::Reader),
+}
+
+impl AlignmentReader {
+    fn header(&self) -> &bam::HeaderView {
+        match self {
+            AlignmentReader::Indexed(r) => r.header(),
+            AlignmentReader::Stream(r) => r.header(),
+        }
+    }
+
+    fn read(&mut self, record: &mut bam::Record) -> Option<rust_htslib::errors::Result<()>> {
+        match self {
+            AlignmentReader::Indexed(r) => r.read(record),
+            AlignmentReader::Stream(r) => r.read(record),
+        }
+    }
+
+    fn set_reference<P: AsRef<Path>>(&mut self, path: P) -> rust_htslib::errors::Result<()> {
+        match self {
+            AlignmentReader::Indexed(r) => r.set_reference(path),
+            AlignmentReader::Stream(r) => r.set_reference(path),
+        }
+    }
+}
+
+/// BedderBam reads SAM, BAM or CRAM alignments as `Position::Alignment`.
+/// Unmapped reads are not reported. When the file has an index, `next_position` with a query
+/// skips ahead using the index.
+pub struct BedderBam {
+    reader: AlignmentReader,
+    record_number: u64,
+    chrom_names: Vec<Arc<str>>,
+    // tid of the region fetched by the last skip. reading continues on later chromosomes.
+    fetched_tid: Option<i32>,
+    // (tid, pos) of the last reported alignment and the number of alignments reported at that
+    // position so that alignments are not reported twice after a skip.
+    last: Option<(i32, i64)>,
+    n_at_last: usize,
+    // alignments at `last` that a fetched region will return again.
+    skip_at_last: usize,
+    path: String,
+
+    filter: AlignmentFilter,
+    split: bool,
+    // with split, aligned blocks (and their tid) that are not yet reported, sorted by start.
+    blocks: VecDeque<(i32, Position)>,
+    done: bool,
+}
+
+/// AlignmentFilter decides which alignments are reported. Unmapped reads are never reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AlignmentFilter {
+    /// minimum mapping quality.
+    pub min_mapq: u8,
+    /// all of these SAM flag bits must be set.
+    pub require_flags: u16,
+    /// none of these SAM flag bits may be set.
+    pub exclude_flags: u16,
+}
+
+impl AlignmentFilter {
+    pub const SECONDARY: u16 = 0x100;
+    pub const QC_FAIL: u16 = 0x200;
+    pub const DUPLICATE: u16 = 0x400;
+    pub const SUPPLEMENTARY: u16 = 0x800;
+
+    #[inline]
+    pub fn accepts(&self, record: &bam::Record) -> bool {
+        let flags = record.flags();
+        record.mapq() >= self.min_mapq
+            && flags & self.require_flags == self.require_flags
+            && flags & self.exclude_flags == 0
+    }
+}
+
+impl BedderBam {
+    /// Open alignments from `p` (or stdin for `-`). `reference` is the FASTA used to decode CRAM;
+    /// without it, htslib falls back to the REF_PATH and REF_CACHE environment variables.
+    pub fn from_path(p: &str, reference: Option<&Path>) -> io::Result<BedderBam> {
+        let stdin = p == "-" || p == "stdin" || p == "/dev/stdin";
+        let mut reader = if stdin {
+            AlignmentReader::Stream(bam::Reader::from_stdin().map_err(io::Error::other)?)
+        } else {
+            match bam::IndexedReader::from_path(p) {
+                Ok(r) => AlignmentReader::Indexed(r),
+                Err(e) => {
+                    log::info!("no index for {}: {}. index skipping is disabled", p, e);
+                    AlignmentReader::Stream(bam::Reader::from_path(p).map_err(io::Error::other)?)
+                }
+            }
+        };
+        if let Some(reference) = reference {
+            reader.set_reference(reference).map_err(io::Error::other)?;
+        }
+        if let AlignmentReader::Indexed(r) = &mut reader {
+            // an indexed reader only reads fetched regions.
+            r.fetch(bam::FetchDefinition::All)
+                .map_err(io::Error::other)?;
+        }
+
+        let h = reader.header();
+        let mut chrom_names = Vec::with_capacity(h.target_count() as usize);
+        for tid in 0..h.target_count() {
+            let name = std::str::from_utf8(h.tid2name(tid)).map_err(io::Error::other)?;
+            chrom_names.push(Arc::<str>::from(name));
+        }
+        Ok(BedderBam {
+            reader,
+            record_number: 0,
+            chrom_names,
+            fetched_tid: None,
+            last: None,
+            n_at_last: 0,
+            skip_at_last: 0,
+            path: String::from(if stdin { "stdin" } else { p }),
+            filter: AlignmentFilter::default(),
+            split: false,
+            blocks: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Only report alignments that pass `filter`.
+    pub fn with_filter(mut self, filter: AlignmentFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Report each aligned block of an alignment separately, splitting on `N` (reference skip)
+    /// CIGAR operations as `bedtools -split` does. Deletions do not split blocks.
+    pub fn with_split(mut self, split: bool) -> Self {
+        self.split = split;
+        self
+    }
+
+    /// The header of the alignment file.
+    pub fn header(&self) -> &bam::HeaderView {
+        self.reader.header()
+    }
+
+    // the next mapped alignment that passes the filter.
+    fn next_alignment(&mut self) -> Option<io::Result<BedderAlignment>> {
+        loop {
+            let mut record = bam::Record::new();
+            match self.reader.read(&mut record) {
+                None => match self.fetch_next_chromosome() {
+                    Ok(true) => continue,
+                    Ok(false) => return None,
+                    Err(e) => return Some(Err(e)),
+                },
+                Some(Err(e)) => {
+                    log::error!(
+                        "error reading alignment: {} at record number: {}",
+                        e,
+                        self.record_number
+                    );
+                    return Some(Err(io::Error::other(e)));
+                }
+                Some(Ok(())) => {
+                    self.record_number += 1;
+                    if record.is_unmapped() || record.tid() < 0 || !self.filter.accepts(&record) {
+                        continue;
+                    }
+                    let key = (record.tid(), record.pos());
+                    // a fetched region can start with alignments that were already reported.
+                    // alignments at the same position are read in the same order each time so
+                    // the first `skip_at_last` of them were reported.
+                    if self.fetched_tid.is_some() {
+                        match self.last.map(|last| key.cmp(&last)) {
+                            Some(Ordering::Less) => continue,
+                            Some(Ordering::Equal) if self.skip_at_last > 0 => {
+                                self.skip_at_last -= 1;
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+                    if self.last == Some(key) {
+                        self.n_at_last += 1;
+                    } else {
+                        self.last = Some(key);
+                        self.n_at_last = 1;
+                        self.skip_at_last = 0;
+                    }
+                    let Some(chrom) = self.chrom_names.get(record.tid() as usize) else {
+                        return Some(Err(io::Error::other(format!(
+                            "alignment tid {} not found in header of {}",
+                            record.tid(),
+                            self.path
+                        ))));
+                    };
+                    let chrom = Arc::clone(chrom);
+                    return Some(Ok(BedderAlignment::new(record, chrom)));
+                }
+            }
+        }
+    }
+
+    // after a fetched region is exhausted, fetch the next chromosome.
+    // returns false when there are no more chromosomes.
+    fn fetch_next_chromosome(&mut self) -> io::Result<bool> {
+        let (AlignmentReader::Indexed(r), Some(tid)) = (&mut self.reader, self.fetched_tid) else {
+            return Ok(false);
+        };
+        let tid = tid + 1;
+        if tid as usize >= self.chrom_names.len() {
+            self.fetched_tid = None;
+            return Ok(false);
+        }
+        r.fetch(bam::FetchDefinition::CompleteTid(tid))
+            .map_err(io::Error::other)?;
+        self.fetched_tid = Some(tid);
+        Ok(true)
+    }
+}
+
+impl Skip for BedderBam {
+    fn skip_to(&mut self, chrom: &str, pos0: u64) -> io::Result<()> {
+        let AlignmentReader::Indexed(r) = &mut self.reader else {
+            return Ok(());
+        };
+        let Some(tid) = r.header().tid(chrom.as_bytes()) else {
+            log::info!("chromosome {} not found in {}", chrom, self.path);
+            return Ok(());
+        };
+        r.fetch(bam::FetchDefinition::Region(
+            tid as i32,
+            pos0 as i64,
+            i64::MAX,
+        ))
+        .map_err(io::Error::other)?;
+        self.fetched_tid = Some(tid as i32);
+        self.skip_at_last = self.n_at_last;
+        Ok(())
+    }
+}
+
+/// An aligned read. The span is the aligned part of the read on the reference, or one aligned
+/// block when alignments are split.
+#[derive(Debug, Clone)]
+pub struct BedderAlignment {
+    pub record: bam::Record,
+    pub chrom: Arc<str>,
+    start: u64,
+    stop: u64,
+}
+
+impl BedderAlignment {
+    pub fn new(record: bam::Record, chrom: Arc<str>) -> Self {
+        let start = record.pos() as u64;
+        let stop = record.cigar().end_pos().max(record.pos() + 1) as u64;
+        Self {
+            record,
+            chrom,
+            start,
+            stop,
+        }
+    }
+
+    /// The read name.
+    pub fn qname(&self) -> Option<&str> {
+        std::str::from_utf8(self.record.qname()).ok()
+    }
+
+    /// The aligned blocks of the read on the reference as (start, stop). Blocks are separated by
+    /// `N` (reference skip) CIGAR operations, as in spliced alignments.
+    pub fn blocks(&self) -> Vec<(u64, u64)> {
+        let mut blocks = vec![];
+        let mut start = self.record.pos() as u64;
+        let mut pos = start;
+        for op in self.record.cigar().iter() {
+            match op {
+                Cigar::Match(l) | Cigar::Equal(l) | Cigar::Diff(l) | Cigar::Del(l) => {
+                    pos += *l as u64
+                }
+                Cigar::RefSkip(l) => {
+                    if pos > start {
+                        blocks.push((start, pos));
+                    }
+                    pos += *l as u64;
+                    start = pos;
+                }
+                Cigar::Ins(_) | Cigar::SoftClip(_) | Cigar::HardClip(_) | Cigar::Pad(_) => {}
+            }
+        }
+        if pos > start {
+            blocks.push((start, pos));
+        }
+        if blocks.is_empty() {
+            blocks.push((self.start, self.stop));
+        }
+        blocks
+    }
+
+    // a copy of this alignment reporting only start..stop.
+    fn with_span(&self, start: u64, stop: u64) -> Self {
+        Self {
+            record: self.record.clone(),
+            chrom: Arc::clone(&self.chrom),
+            start,
+            stop,
+        }
+    }
+}
+
+impl Positioned for BedderAlignment {
+    #[inline]
+    fn chrom(&self) -> &str {
+        self.chrom.as_ref()
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start
+    }
+
+    #[inline]
+    fn stop(&self) -> u64 {
+        self.stop
+    }
+
+    // the alignment itself is unchanged; only the reported span.
+    fn set_start(&mut self, start: u64) {
+        self.start = start;
+    }
+
+    fn set_stop(&mut self, stop: u64) {
+        self.stop = stop;
+    }
+
+    #[inline]
+    fn strand(&self) -> Strand {
+        if self.record.is_reverse() {
+            Strand::Reverse
+        } else {
+            Strand::Forward
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Positioned> {
+        Box::new(self.clone())
+    }
+}
+
+impl crate::position::PositionedIterator for BedderBam {
+    fn next_position(
+        &mut self,
+        q: Option<&crate::position::Position>,
+    ) -> Option<std::result::Result<Position, std::io::Error>> {
+        if let Some(q) = q {
+            if let Err(e) = self.skip_to(q.chrom(), q.start().saturating_sub(1)) {
+                return Some(Err(e));
+            }
+        }
+
+        if !self.split {
+            return self
+                .next_alignment()
+                .map(|a| a.map(|a| Position::Alignment(Box::new(a))));
+        }
+
+        // blocks of later alignments start at or after the start of the last alignment so
+        // anything before that can be reported.
+        loop {
+            if let Some((tid, block)) = self.blocks.front() {
+                let ready = self.done
+                    || self
+                        .last
+                        .is_some_and(|last| (*tid, block.start() as i64) <= last);
+                if ready {
+                    return self.blocks.pop_front().map(|(_, b)| Ok(b));
+                }
+            }
+            if self.done {
+                return None;
+            }
+            match self.next_alignment() {
+                Some(Ok(a)) => {
+                    let tid = a.record.tid();
+                    for (start, stop) in a.blocks() {
+                        let block = Position::Alignment(Box::new(a.with_span(start, stop)));
+                        let i = self
+                            .blocks
+                            .partition_point(|(t, b)| (*t, b.start()) <= (tid, block.start()));
+                        self.blocks.insert(i, (tid, block));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => self.done = true,
+            }
+        }
+    }
+
+    fn name(&self) -> String {
+        String::from(format!("BAM|{}:record #:{}", self.path, self.record_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::PositionedIterator;
+
+    fn alignments(path: &str) -> Vec<(String, u64, u64, Strand, Option<String>)> {
+        read_all(BedderBam::from_path(path, None).expect("error opening alignments"))
+    }
+
+    fn read_all(mut bam: BedderBam) -> Vec<(String, u64, u64, Strand, Option<String>)> {
+        let mut result = vec![];
+        while let Some(p) = bam.next_position(None) {
+            let p = p.expect("error reading alignment");
+            result.push((
+                String::from(p.chrom()),
+                p.start(),
+                p.stop(),
+                p.strand(),
+                p.name().map(String::from),
+            ));
+        }
+        result
+    }
+
+    #[test]
+    fn test_read_bam_and_sam() {
+        let expected = vec![
+            (
+                String::from("chr1"),
+                999900,
+                1000000,
+                Strand::Reverse,
+                Some(String::from("A")),
+            ),
+            (
+                String::from("chr1"),
+                999913,
+                1000013,
+                Strand::Forward,
+                Some(String::from("B")),
+            ),
+        ];
+        assert_eq!(alignments("tests/test.bam"), expected);
+        assert_eq!(alignments("tests/test.sam"), expected);
+    }
+
+    const SPLICED_SAM: &str = "@HD\tVN:1.6\tSO:coordinate
+@SQ\tSN:chr1\tLN:10000
+r1\t0\tchr1\t101\t60\t50M100N45M2D3M\t*\t0\t0\t*\t*
+r2\t0\tchr1\t201\t60\t20M\t*\t0\t0\t*\t*
+dup\t1024\tchr1\t301\t60\t10M\t*\t0\t0\t*\t*
+lowq\t0\tchr1\t401\t5\t10M\t*\t0\t0\t*\t*
+sec\t256\tchr1\t501\t60\t10M\t*\t0\t0\t*\t*
+";
+
+    fn spliced_bam() -> BedderBam {
+        let mut f = tempfile::Builder::new().suffix(".sam").tempfile().unwrap();
+        std::io::Write::write_all(&mut f, SPLICED_SAM.as_bytes()).unwrap();
+        BedderBam::from_path(f.path().to_str().unwrap(), None).unwrap()
+    }
+
+    fn spans(r: &[(String, u64, u64, Strand, Option<String>)]) -> Vec<(u64, u64, &str)> {
+        r.iter()
+            .map(|(_, s, e, _, n)| (*s, *e, n.as_deref().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_alignment_filter() {
+        let all = read_all(spliced_bam());
+        assert_eq!(all.len(), 5);
+
+        let filter = AlignmentFilter {
+            min_mapq: 10,
+            require_flags: 0,
+            exclude_flags: AlignmentFilter::DUPLICATE | AlignmentFilter::SECONDARY,
+        };
+        let r = read_all(spliced_bam().with_filter(filter));
+        assert_eq!(spans(&r), vec![(100, 300, "r1"), (200, 220, "r2")]);
+
+        let filter = AlignmentFilter {
+            require_flags: AlignmentFilter::DUPLICATE,
+            ..Default::default()
+        };
+        let r = read_all(spliced_bam().with_filter(filter));
+        assert_eq!(spans(&r), vec![(300, 310, "dup")]);
+    }
+
+    #[test]
+    fn test_split_alignments() {
+        let filter = AlignmentFilter {
+            min_mapq: 10,
+            ..Default::default()
+        };
+        let r = read_all(spliced_bam().with_filter(filter).with_split(true));
+        // the deletion does not split the second block of r1; blocks are sorted.
+        assert_eq!(
+            spans(&r),
+            vec![
+                (100, 150, "r1"),
+                (200, 220, "r2"),
+                (250, 300, "r1"),
+                (300, 310, "dup"),
+                (500, 510, "sec"),
+            ]
+        );
+    }
+
+    const STACKED_SAM: &str = "@HD\tVN:1.6\tSO:coordinate
+@SQ\tSN:chr1\tLN:10000
+s1\t0\tchr1\t101\t60\t50M\t*\t0\t0\t*\t*
+s2\t0\tchr1\t101\t60\t50M\t*\t0\t0\t*\t*
+s3\t0\tchr1\t101\t60\t50M\t*\t0\t0\t*\t*
+far\t0\tchr1\t5001\t60\t50M\t*\t0\t0\t*\t*
+";
+
+    // an indexed BAM of STACKED_SAM.
+    fn indexed_bam(dir: &tempfile::TempDir) -> BedderBam {
+        let sam = dir.path().join("stacked.sam");
+        let path = dir.path().join("stacked.bam");
+        std::fs::write(&sam, STACKED_SAM).unwrap();
+        let mut reader = bam::Reader::from_path(&sam).unwrap();
+        let header = bam::Header::from_template(reader.header());
+        let mut writer = bam::Writer::from_path(&path, &header, bam::Format::Bam).unwrap();
+        for r in reader.records() {
+            writer.write(&r.unwrap()).unwrap();
+        }
+        drop(writer);
+        bam::index::build(&path, None, bam::index::Type::Bai, 1).unwrap();
+        BedderBam::from_path(path.to_str().unwrap(), None).unwrap()
+    }
+
+    fn query(start: u64, stop: u64) -> Position {
+        Position::Interval(crate::interval::Interval {
+            chrom: String::from("chr1"),
+            start,
+            stop,
+            ..Default::default()
+        })
+    }
+
+    fn next_name(bam: &mut BedderBam, q: Option<&Position>) -> Option<String> {
+        bam.next_position(q)
+            .map(|p| p.unwrap().name().map(String::from).unwrap())
+    }
+
+    #[test]
+    fn test_skip_does_not_repeat_alignments() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bam = indexed_bam(&dir);
+        assert!(matches!(bam.reader, AlignmentReader::Indexed(_)));
+        assert_eq!(next_name(&mut bam, None).as_deref(), Some("s1"));
+        assert_eq!(next_name(&mut bam, None).as_deref(), Some("s2"));
+        // the fetched region starts at s1, s2 and s3 but only s3 is new.
+        assert_eq!(
+            next_name(&mut bam, Some(&query(120, 130))).as_deref(),
+            Some("s3")
+        );
+        assert_eq!(
+            next_name(&mut bam, Some(&query(120, 130))).as_deref(),
+            Some("far")
+        );
+        assert_eq!(next_name(&mut bam, None), None);
+
+        let mut bam = indexed_bam(&dir);
+        assert_eq!(next_name(&mut bam, None).as_deref(), Some("s1"));
+        assert_eq!(
+            next_name(&mut bam, Some(&query(5010, 5020))).as_deref(),
+            Some("far")
+        );
+        assert_eq!(next_name(&mut bam, None), None);
+    }
+}
@@ -0,0 +1,288 @@
+#![allow(clippy::useless_conversion)] // these are needed to support e.g. smartstring
+use crate::bedder_bed::BedRecord;
+use crate::chrom_ordering::Chromosome;
+use crate::position::Position;
+use crate::skip::Skip;
+use crate::string::String;
+use bigtools::utils::reopen::ReopenableFile;
+use bigtools::{BigBedRead, BigWigRead};
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::io;
+
+// bases requested from the index at a time when reading sequentially. Kept small so that
+// base-level bigWigs do not buffer too many records.
+const CHUNK_SIZE: u32 = 1 << 16;
+
+enum BbiReader {
+    BigWig(BigWigRead<ReopenableFile>),
+    BigBed(BigBedRead<ReopenableFile>),
+}
+
+/// Reads bigWig or bigBed files as BED records. bigWig values are the BED score (column 5)
+/// so they can be summarized by `map` as with a bedGraph. bigBed records are expected to
+/// follow BED column order after the first 3 columns.
+///
+/// Records are read from the R-tree index a chunk at a time so `Skip` jumps directly to the
+/// requested region.
+pub struct BedderBbi {
+    reader: BbiReader,
+    path: String,
+    /// chromosome names and lengths in the order they are read.
+    chroms: Vec<(String, u32)>,
+    /// index into `chroms` of the chromosome being read.
+    chrom_index: usize,
+    /// records that start before this position on the current chromosome have been read.
+    frontier: u32,
+    /// the start of the earliest record to keep from the next chunk. This is before
+    /// `frontier` after a skip so that records overlapping the skip position are reported.
+    min_start: u32,
+    buffer: VecDeque<Position>,
+}
+
+impl BedderBbi {
+    pub fn from_path(path: &str) -> io::Result<Self> {
+        let reader = match BigWigRead::open_file(path) {
+            Ok(r) => BbiReader::BigWig(r),
+            Err(_) => BbiReader::BigBed(BigBedRead::open_file(path).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("error opening {} as bigWig or bigBed: {}", path, e),
+                )
+            })?),
+        };
+        let chroms = match &reader {
+            BbiReader::BigWig(r) => r.chroms(),
+            BbiReader::BigBed(r) => r.chroms(),
+        }
+        .iter()
+        .map(|c| (String::from(c.name.as_str()), c.length))
+        .collect();
+        Ok(BedderBbi {
+            reader,
+            path: String::from(path),
+            chroms,
+            chrom_index: 0,
+            frontier: 0,
+            min_start: 0,
+            buffer: VecDeque::new(),
+        })
+    }
+
+    /// Read chromosomes in the order of the genome file rather than the order in the bbi file
+    /// (which is sorted by name). Chromosomes that are not in `chrom_order` are not read.
+    pub fn with_chromosome_order(mut self, chrom_order: &HashMap<String, Chromosome>) -> Self {
+        self.chroms.retain(|(name, _)| {
+            let found = chrom_order.contains_key(name);
+            if !found {
+                log::info!(
+                    "chromosome {} in {} is not in the genome file",
+                    name,
+                    self.path
+                );
+            }
+            found
+        });
+        self.chroms.sort_by_key(|(name, _)| chrom_order[name].index);
+        self
+    }
+
+    pub fn is_bigwig(&self) -> bool {
+        matches!(self.reader, BbiReader::BigWig(_))
+    }
+
+    // read the next chunk of records into the buffer. returns false when all chromosomes are read.
+    fn fill_buffer(&mut self) -> io::Result<bool> {
+        while self.buffer.is_empty() {
+            let Some((chrom, length)) = self.chroms.get(self.chrom_index) else {
+                return Ok(false);
+            };
+            if self.frontier >= *length {
+                self.chrom_index += 1;
+                self.frontier = 0;
+                self.min_start = 0;
+                continue;
+            }
+            let start = self.frontier;
+            let end = start.saturating_add(CHUNK_SIZE).min(*length);
+            let min_start = self.min_start;
+            let error = |e: bigtools::BBIReadError| {
+                io::Error::other(format!(
+                    "error reading {}:{}-{} from {}: {}",
+                    chrom, start, end, self.path, e
+                ))
+            };
+            // records that start before `min_start` were read from an earlier chunk.
+            match &mut self.reader {
+                BbiReader::BigWig(r) => {
+                    for v in r.get_interval(chrom, start, end).map_err(error)? {
+                        let v = v.map_err(error)?;
+                        if v.start >= min_start {
+                            self.buffer.push_back(Position::Bed(BedRecord::new(
+                                chrom,
+                                v.start as u64,
+                                v.end as u64,
+                                None,
+                                Some(v.value as f64),
+                                vec![],
+                            )));
+                        }
+                    }
+                }
+                BbiReader::BigBed(r) => {
+                    for b in r.get_interval(chrom, start, end).map_err(error)? {
+                        let b = b.map_err(error)?;
+                        if b.start >= min_start {
+                            self.buffer
+                                .push_back(bigbed_record(chrom, b.start, b.end, &b.rest));
+                        }
+                    }
+                }
+            }
+            self.frontier = end;
+            self.min_start = end;
+        }
+        Ok(true)
+    }
+}
+
+// a bigBed entry as BED. `rest` holds the tab-delimited columns after the end.
+fn bigbed_record(chrom: &str, start: u32, end: u32, rest: &str) -> Position {
+    let mut fields = rest.split('\t').filter(|_| !rest.is_empty());
+    let name = fields.next();
+    let score = fields.next().and_then(|s| s.parse::<f64>().ok());
+    Position::Bed(BedRecord::new(
+        chrom,
+        start as u64,
+        end as u64,
+        name,
+        score,
+        fields.map(String::from).collect(),
+    ))
+}
+
+impl Skip for BedderBbi {
+    /// Move to `pos0` on `chrom` if it is after what was already read. Records overlapping
+    /// `pos0` that were not yet read are still reported.
+    fn skip_to(&mut self, chrom: &str, pos0: u64) -> io::Result<()> {
+        let Some(index) = self.chroms.iter().position(|(name, _)| name == chrom) else {
+            log::info!("chromosome {} not found in {}", chrom, self.path);
+            return Ok(());
+        };
+        let pos0 = pos0.min(u32::MAX as u64) as u32;
+        if index < self.chrom_index || (index == self.chrom_index && pos0 <= self.frontier) {
+            return Ok(());
+        }
+        if index > self.chrom_index {
+            self.buffer.clear();
+            self.chrom_index = index;
+            self.min_start = 0;
+        } else {
+            self.buffer.retain(|p| p.stop() > pos0 as u64);
+            self.min_start = self.frontier;
+        }
+        self.frontier = pos0;
+        Ok(())
+    }
+}
+
+impl crate::position::PositionedIterator for BedderBbi {
+    fn next_position(&mut self, q: Option<&Position>) -> Option<io::Result<Position>> {
+        if let Some(q) = q {
+            if let Err(e) = self.skip_to(q.chrom(), q.start().saturating_sub(1)) {
+                return Some(Err(e));
+            }
+        }
+        match self.fill_buffer() {
+            Ok(true) => self.buffer.pop_front().map(Ok),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn name(&self) -> String {
+        let kind = if self.is_bigwig() { "bigWig" } else { "bigBed" };
+        let chrom = self.chroms.get(self.chrom_index).map_or("", |(c, _)| c);
+        String::from(format!(
+            "{}|{}:{}:{}",
+            kind, self.path, chrom, self.frontier
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::PositionedIterator;
+
+    fn read_all(mut r: BedderBbi, q: Option<Position>) -> Vec<(String, u64, u64, Option<f64>)> {
+        let mut result = Vec::new();
+        let mut q = q;
+        while let Some(p) = r.next_position(q.take().as_ref()) {
+            let p = p.expect("error reading bbi");
+            let score = p.column_as_f64(5);
+            result.push((String::from(p.chrom()), p.start(), p.stop(), score));
+        }
+        result
+    }
+
+    fn genome() -> HashMap<String, Chromosome> {
+        ["chr1", "chr2", "chr10"]
+            .iter()
+            .enumerate()
+            .map(|(index, c)| {
+                (
+                    String::from(*c),
+                    Chromosome {
+                        index,
+                        length: None,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_bigwig() {
+        let r = BedderBbi::from_path("tests/test.bw").expect("error opening bigWig");
+        assert!(r.is_bigwig());
+        let values = read_all(r.with_chromosome_order(&genome()), None);
+        let chroms: Vec<&str> = values.iter().map(|v| v.0.as_str()).collect();
+        assert_eq!(
+            chroms,
+            vec!["chr1", "chr1", "chr1", "chr1", "chr2", "chr10"]
+        );
+        assert_eq!(values[1], (String::from("chr1"), 200, 300, Some(3.0)));
+        assert_eq!(
+            values[3],
+            (String::from("chr1"), 2000000, 2000100, Some(4.0))
+        );
+    }
+
+    #[test]
+    fn test_skip_bigwig() {
+        let r = BedderBbi::from_path("tests/test.bw")
+            .expect("error opening bigWig")
+            .with_chromosome_order(&genome());
+        // the record at 200-300 overlaps the skip position so it is still reported.
+        let q = Position::Bed(BedRecord::new("chr1", 251, 260, None, None, vec![]));
+        let values = read_all(r, Some(q));
+        assert_eq!(values[0], (String::from("chr1"), 200, 300, Some(3.0)));
+        assert_eq!(values.len(), 5);
+    }
+
+    #[test]
+    fn test_read_bigbed() {
+        let r = BedderBbi::from_path("tests/test.bb").expect("error opening bigBed");
+        assert!(!r.is_bigwig());
+        let mut r = r.with_chromosome_order(&genome());
+        let p = r.next_position(None).unwrap().unwrap();
+        assert_eq!((p.start(), p.stop()), (100, 300));
+        assert_eq!(p.name(), Some("gene1"));
+        assert_eq!(p.column_as_f64(5), Some(5.0));
+        assert_eq!(p.strand(), crate::position::Strand::Forward);
+        let rest = read_all(r, None);
+        assert_eq!(rest.len(), 3);
+        assert_eq!(rest[2].0, "chr10");
+    }
+}
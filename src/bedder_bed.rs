@@ -1,6 +1,6 @@
 #![allow(clippy::useless_conversion)] // these are needed to support e.g. smartstring
 
-use crate::position::{Position, Positioned};
+use crate::position::{Position, Positioned, Strand};
 use crate::skip::Skip;
 use crate::string::String;
 pub use simplebed;
@@ -37,6 +37,69 @@ impl BedRecord {
     pub fn push_field(&mut self, field: BedValue) {
         self.0.push_field(field);
     }
+
+    /// The blocks (e.g. exons) of a BED12 record as sorted (start, stop) in genome coordinates,
+    /// from blockCount, blockSizes and blockStarts (columns 10-12).
+    /// None if the record is not BED12 or the block columns are invalid.
+    pub fn blocks(&self) -> Option<Vec<(u64, u64)>> {
+        let fields = self.0.other_fields();
+        if fields.len() < 7 {
+            return None;
+        }
+        let count: usize = fields[4].to_string().parse().ok()?;
+        let parse = |v: &BedValue| -> Option<Vec<u64>> {
+            v.to_string()
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.trim().parse().ok())
+                .collect()
+        };
+        let (sizes, starts) = (parse(&fields[5])?, parse(&fields[6])?);
+        if count == 0 || sizes.len() != count || starts.len() != count {
+            return None;
+        }
+        let start = self.start();
+        let mut blocks: Vec<(u64, u64)> = starts
+            .iter()
+            .zip(sizes.iter())
+            .map(|(s, l)| (start + s, start + s + l))
+            .collect();
+        blocks.sort_unstable();
+        Some(blocks)
+    }
+
+    /// Replace the BED12 block columns so that the record has `blocks` (sorted, in genome
+    /// coordinates, within start..stop). thickStart and thickEnd are clipped to the record.
+    /// Records that are not BED12 are unchanged.
+    pub fn set_blocks(&mut self, blocks: &[(u64, u64)]) {
+        if self.0.other_fields().len() < 7 {
+            return;
+        }
+        let (start, stop) = (self.start(), self.stop());
+        let join = |f: &dyn Fn(&(u64, u64)) -> u64| -> BedValue {
+            BedValue::String(blocks.iter().map(|b| format!("{},", f(b))).collect())
+        };
+        let clip = |v: &BedValue| -> BedValue {
+            match v.to_string().parse::<u64>() {
+                Ok(t) => BedValue::Integer(t.clamp(start, stop) as i64),
+                Err(_) => v.clone(),
+            }
+        };
+        let mut fields = self.0.other_fields().to_vec();
+        fields[1] = clip(&fields[1]);
+        fields[2] = clip(&fields[2]);
+        fields[4] = BedValue::Integer(blocks.len() as i64);
+        fields[5] = join(&|(s, e)| e - s);
+        fields[6] = join(&|(s, _)| s - start);
+        self.0 = SimpleBedRecord::new(
+            self.0.chrom().to_string(),
+            start,
+            stop,
+            self.0.name().map(|s| s.to_string()),
+            self.0.score(),
+            fields,
+        );
+    }
 }
 
 impl crate::position::Positioned for BedRecord {
@@ -65,6 +128,15 @@ impl crate::position::Positioned for BedRecord {
         self.0.set_end(stop);
     }
 
+    /// strand is BED column 6, which is the first of the `other_fields`.
+    #[inline]
+    fn strand(&self) -> Strand {
+        match self.0.other_fields().first() {
+            Some(BedValue::String(s)) => Strand::from_str_lossy(s),
+            _ => Strand::Unknown,
+        }
+    }
+
     fn clone_box(&self) -> Box<dyn Positioned> {
         Box::new(self.clone())
     }
@@ -95,6 +167,8 @@ where
     line_number: u64,
     query_iter: Option<Box<dyn Iterator<Item = Result<SimpleBedRecord, BedError>> + 'a>>,
     path: Option<String>,
+    /// use the index to skip to query intervals. Disabled for readers that can not seek.
+    index_skipping: bool,
 }
 
 impl<'a, R> BedderBed<'a, R>
@@ -112,8 +186,16 @@ where
             line_number: 0,
             query_iter: None,
             path: Some(path_string.into()),
+            index_skipping: true,
         }
     }
+
+    /// Enable or disable skipping to query intervals with the index. Streams such as stdin
+    /// must be read without skipping.
+    pub fn with_index_skipping(mut self, index_skipping: bool) -> Self {
+        self.index_skipping = index_skipping;
+        self
+    }
 }
 
 impl<'a, R> Skip for BedderBed<'a, R>
@@ -153,7 +235,7 @@ where
         query: Option<&crate::position::Position>,
     ) -> Option<std::result::Result<Position, std::io::Error>> {
         // If we have a query, set up the query iterator
-        if let Some(query) = query {
+        if let Some(query) = query.filter(|_| self.index_skipping) {
             self.query_iter = None;
             log::info!(
                 "skipping to query: {}:{} for {}",
@@ -234,6 +316,31 @@ mod tests {
     use hashbrown::HashMap;
     use std::io::Cursor;
 
+    #[test]
+    fn test_bed12_blocks() {
+        let fields = ["+", "100", "400", "0", "2", "100,50,", "200,0,"];
+        let mut b = BedRecord::new(
+            "chr1",
+            100,
+            400,
+            Some("tx"),
+            None,
+            fields.iter().map(|f| String::from(*f)).collect(),
+        );
+        // blocks are sorted even when blockStarts are not.
+        assert_eq!(b.blocks(), Some(vec![(100, 150), (300, 400)]));
+
+        b.set_start(310);
+        b.set_stop(320);
+        b.set_blocks(&[(310, 320)]);
+        assert_eq!(b.blocks(), Some(vec![(310, 320)]));
+        assert_eq!(b.0.other_fields()[1].to_string(), "310");
+        assert_eq!(b.0.name(), Some("tx"));
+
+        let bed6 = BedRecord::new("chr1", 1, 10, None, None, vec![String::from("+")]);
+        assert_eq!(bed6.blocks(), None);
+    }
+
     #[test]
     fn test_bed_read() {
         // write a test for bed from a string using BufRead
@@ -0,0 +1,423 @@
+#![allow(clippy::useless_conversion)] // these are needed to support e.g. smartstring
+use crate::bedder_bed::BedRecord;
+use crate::chrom_ordering::Chromosome;
+use crate::position::Position;
+use crate::skip::Skip;
+use crate::string::String;
+use d4::ptab::{Encoder, PTablePartitionWriter};
+use d4::ssio::D4TrackReader;
+use d4::stab::SecondaryTablePartWriter;
+use d4::{Chrom, D4FileBuilder, D4FileWriter, Dictionary};
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+// bases read from the file at a time.
+const CHUNK_SIZE: u32 = 1 << 16;
+
+fn d4_error(path: &str, e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("D4 error in {}: {}", path, e))
+}
+
+/// Reads the first track of a D4 file as runs of equal depth. Each run is a BED record with
+/// the depth as the score (column 5), as in a bedGraph, including runs with zero depth.
+pub struct BedderD4 {
+    reader: D4TrackReader<File>,
+    path: String,
+    /// chromosome names and lengths in the order they are read.
+    chroms: Vec<(String, u32)>,
+    /// index into `chroms` of the chromosome being read.
+    chrom_index: usize,
+    /// bases before this position on the current chromosome have been read.
+    frontier: u32,
+    /// the (start, stop, depth) of the run that is being extended.
+    run: Option<(u32, u32, i32)>,
+    buffer: VecDeque<Position>,
+}
+
+impl BedderD4 {
+    pub fn from_path(path: &str) -> io::Result<Self> {
+        let reader =
+            D4TrackReader::from_reader(File::open(path)?, None).map_err(|e| d4_error(path, e))?;
+        let chroms = reader
+            .chrom_list()
+            .iter()
+            .map(|c| (String::from(c.name.as_str()), c.size as u32))
+            .collect();
+        Ok(BedderD4 {
+            reader,
+            path: String::from(path),
+            chroms,
+            chrom_index: 0,
+            frontier: 0,
+            run: None,
+            buffer: VecDeque::new(),
+        })
+    }
+
+    /// Read chromosomes in the order of the genome file. Chromosomes that are not in
+    /// `chrom_order` are not read.
+    pub fn with_chromosome_order(mut self, chrom_order: &HashMap<String, Chromosome>) -> Self {
+        self.chroms.retain(|(name, _)| {
+            let found = chrom_order.contains_key(name);
+            if !found {
+                log::info!(
+                    "chromosome {} in {} is not in the genome file",
+                    name,
+                    self.path
+                );
+            }
+            found
+        });
+        self.chroms.sort_by_key(|(name, _)| chrom_order[name].index);
+        self
+    }
+
+    fn push_run(&mut self) {
+        if let Some(run) = self.run.take() {
+            let record = run_record(&self.chroms[self.chrom_index].0, run);
+            self.buffer.push_back(record);
+        }
+    }
+
+    // read chunks until a run is complete. returns false when all chromosomes are read.
+    fn fill_buffer(&mut self) -> io::Result<bool> {
+        while self.buffer.is_empty() {
+            let Some((chrom, length)) = self.chroms.get(self.chrom_index) else {
+                return Ok(false);
+            };
+            if self.frontier >= *length {
+                self.push_run();
+                self.chrom_index += 1;
+                self.frontier = 0;
+                continue;
+            }
+            let start = self.frontier;
+            let end = start.saturating_add(CHUNK_SIZE).min(*length);
+            let view = self
+                .reader
+                .get_view(chrom, start, end)
+                .map_err(|e| d4_error(&self.path, e))?;
+            // extend the current run while the depth is unchanged rather than collecting the
+            // depth of each base.
+            for value in view {
+                let (pos, depth) = value.map_err(|e| d4_error(&self.path, e))?;
+                match &mut self.run {
+                    Some((_, stop, d)) if *d == depth && *stop == pos => *stop = pos + 1,
+                    run => {
+                        if let Some(done) = run.replace((pos, pos + 1, depth)) {
+                            self.buffer.push_back(run_record(chrom, done));
+                        }
+                    }
+                }
+            }
+            self.frontier = end;
+        }
+        Ok(true)
+    }
+}
+
+// a run of depth as a bedGraph-like BED record.
+fn run_record(chrom: &str, (start, stop, depth): (u32, u32, i32)) -> Position {
+    Position::Bed(BedRecord::new(
+        chrom,
+        start as u64,
+        stop as u64,
+        None,
+        Some(depth as f64),
+        vec![],
+    ))
+}
+
+impl Skip for BedderD4 {
+    /// Move to `pos0` on `chrom` if it is after what was already read. The run that
+    /// contains `pos0` is reported from `pos0`.
+    fn skip_to(&mut self, chrom: &str, pos0: u64) -> io::Result<()> {
+        let Some(index) = self.chroms.iter().position(|(name, _)| name == chrom) else {
+            log::info!("chromosome {} not found in {}", chrom, self.path);
+            return Ok(());
+        };
+        let pos0 = pos0.min(u32::MAX as u64) as u32;
+        if index < self.chrom_index || (index == self.chrom_index && pos0 <= self.frontier) {
+            return Ok(());
+        }
+        if index > self.chrom_index {
+            self.buffer.clear();
+            self.chrom_index = index;
+        } else {
+            self.buffer.retain(|p| p.stop() > pos0 as u64);
+        }
+        // the current run ends at the frontier, before pos0.
+        self.run = None;
+        self.frontier = pos0;
+        Ok(())
+    }
+}
+
+impl crate::position::PositionedIterator for BedderD4 {
+    fn next_position(&mut self, q: Option<&Position>) -> Option<io::Result<Position>> {
+        if let Some(q) = q {
+            if let Err(e) = self.skip_to(q.chrom(), q.start().saturating_sub(1)) {
+                return Some(Err(e));
+            }
+        }
+        match self.fill_buffer() {
+            Ok(true) => self.buffer.pop_front().map(Ok),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn name(&self) -> String {
+        let chrom = self.chroms.get(self.chrom_index).map_or("", |(c, _)| c);
+        String::from(format!("D4|{}:{}:{}", self.path, chrom, self.frontier))
+    }
+}
+
+// bases per partition of the D4 primary table.
+const PART_SIZE: usize = 10_000_000;
+
+// encodes the runs of the chromosome with the given index into its partitions, after finishing
+// the partitions of earlier chromosomes. `None` finishes the remaining partitions. It is a
+// closure because the d4 partition writer types are not named by its API.
+type PartWriter = Box<dyn FnMut(Option<usize>, &[(u32, u32, i32)]) -> io::Result<()>>;
+
+/// Writes depths to a single-track D4 file. Runs must arrive in genome file order; the runs of
+/// a chromosome are kept until the next chromosome starts and are then written to its
+/// partitions.
+pub struct D4Writer {
+    /// index of each chromosome in genome file order.
+    order: HashMap<String, usize>,
+    /// the index of the chromosome being written.
+    chrom: Option<usize>,
+    /// (start, stop, depth) runs of the chromosome being written. Runs with zero depth are
+    /// not stored.
+    runs: Vec<(u32, u32, i32)>,
+    write_parts: PartWriter,
+    // kept until the partitions are written.
+    _writer: D4FileWriter,
+}
+
+impl D4Writer {
+    /// D4 output requires a length for every chromosome in `chrom_order`.
+    pub fn new(path: &Path, chrom_order: &HashMap<String, Chromosome>) -> io::Result<Self> {
+        let mut chroms = chrom_order
+            .iter()
+            .map(|(name, c)| match c.length {
+                Some(length) => Ok((name.clone(), c.index, length as u32)),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "D4 output requires chromosome lengths in the genome file; none for {}",
+                        name
+                    ),
+                )),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        chroms.sort_by_key(|(_, index, _)| *index);
+
+        let path_str = path.to_string_lossy().to_string();
+        let mut builder = D4FileBuilder::new(path);
+        builder.append_chrom(chroms.iter().map(|(name, _, length)| Chrom {
+            name: name.to_string(),
+            size: *length as usize,
+        }));
+        // depths up to 63 are stored in the primary table, others in the secondary table.
+        builder.set_dictionary(
+            Dictionary::new_simple_range_dict(0, 64).map_err(|e| d4_error(&path_str, e))?,
+        );
+        let mut writer: D4FileWriter = builder.create().map_err(|e| d4_error(&path_str, e))?;
+        let mut parts: VecDeque<_> = writer
+            .parallel_parts(Some(PART_SIZE))
+            .map_err(|e| d4_error(&path_str, e))?
+            .into_iter()
+            .collect();
+
+        let order: HashMap<String, usize> = chroms
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, _, _))| (name, i))
+            .collect();
+        let part_order = order.clone();
+        let write_parts: PartWriter = Box::new(move |index, runs| {
+            while let Some((primary, _)) = parts.front() {
+                let part_index = part_order[primary.region().0];
+                if index.is_some_and(|i| part_index > i) {
+                    break;
+                }
+                let (mut primary, mut secondary) = parts.pop_front().unwrap();
+                if index == Some(part_index) {
+                    let (_, begin, end) = primary.region();
+                    let first = runs.partition_point(|(_, stop, _)| *stop <= begin);
+                    let mut encoder = primary.make_encoder();
+                    for &(start, stop, depth) in runs[first..].iter().take_while(|r| r.0 < end) {
+                        for pos in start.max(begin)..stop.min(end) {
+                            if !encoder.encode(pos as usize, depth) {
+                                secondary
+                                    .encode(pos, depth)
+                                    .map_err(|e| d4_error(&path_str, e))?;
+                            }
+                        }
+                    }
+                }
+                secondary.flush().map_err(|e| d4_error(&path_str, e))?;
+                secondary.finish().map_err(|e| d4_error(&path_str, e))?;
+            }
+            Ok(())
+        });
+        Ok(D4Writer {
+            order,
+            chrom: None,
+            runs: Vec::new(),
+            write_parts,
+            _writer: writer,
+        })
+    }
+
+    /// Add a run of `depth`. Chromosomes must be in genome file order and runs on a chromosome
+    /// must be sorted and must not overlap.
+    pub fn write(&mut self, chrom: &str, start: u64, stop: u64, depth: u64) -> io::Result<()> {
+        let Some(&index) = self.order.get(chrom) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("chromosome {} is not in the genome file", chrom),
+            ));
+        };
+        if self.chrom != Some(index) {
+            if self.chrom.is_some_and(|c| c > index) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "D4 output must be sorted by chromosome in genome file order; got {} after a later chromosome",
+                        chrom
+                    ),
+                ));
+            }
+            self.write_chrom()?;
+            self.chrom = Some(index);
+        }
+        if depth == 0 {
+            return Ok(());
+        }
+        let depth = i32::try_from(depth).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("depth {} is too large for D4", depth),
+            )
+        })?;
+        self.runs.push((start as u32, stop as u32, depth));
+        Ok(())
+    }
+
+    // write the partitions of the current chromosome.
+    fn write_chrom(&mut self) -> io::Result<()> {
+        if self.chrom.is_some() {
+            (self.write_parts)(self.chrom, &self.runs)?;
+            self.runs.clear();
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_chrom()?;
+        (self.write_parts)(None, &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::PositionedIterator;
+
+    #[test]
+    fn test_d4_round_trip() {
+        let genome =
+            crate::chrom_ordering::parse_genome("chr2\t100\nchr1\t200\n".as_bytes()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("depth.d4");
+        let mut w = D4Writer::new(&path, &genome).unwrap();
+        w.write("chr2", 10, 20, 3).unwrap();
+        w.write("chr1", 0, 50, 1).unwrap();
+        w.write("chr1", 50, 60, 100).unwrap();
+        w.finish().unwrap();
+
+        let mut r = BedderD4::from_path(path.to_str().unwrap())
+            .unwrap()
+            .with_chromosome_order(&genome);
+        let mut runs = Vec::new();
+        while let Some(p) = r.next_position(None) {
+            let p = p.unwrap();
+            runs.push((
+                p.chrom().to_string(),
+                p.start(),
+                p.stop(),
+                p.column_as_f64(5),
+            ));
+        }
+        let expected = [
+            ("chr2", 0, 10, 0.0),
+            ("chr2", 10, 20, 3.0),
+            ("chr2", 20, 100, 0.0),
+            ("chr1", 0, 50, 1.0),
+            ("chr1", 50, 60, 100.0),
+            ("chr1", 60, 200, 0.0),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(c, s, e, v)| (c.to_string(), *s, *e, Some(*v)))
+            .collect();
+        assert_eq!(runs, expected);
+    }
+
+    #[test]
+    fn test_d4_skip_to() {
+        let genome =
+            crate::chrom_ordering::parse_genome("chr1\t200000\nchr2\t100\n".as_bytes()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("depth.d4");
+        let mut w = D4Writer::new(&path, &genome).unwrap();
+        w.write("chr1", 0, 100, 2).unwrap();
+        w.write("chr1", 150_000, 150_100, 5).unwrap();
+        w.write("chr2", 10, 20, 3).unwrap();
+        // chromosomes must be in genome file order.
+        assert!(w.write("chr1", 0, 10, 1).is_err());
+        w.finish().unwrap();
+
+        let mut r = BedderD4::from_path(path.to_str().unwrap())
+            .unwrap()
+            .with_chromosome_order(&genome);
+        let mut next = |q: Option<Position>| {
+            let p = r.next_position(q.as_ref()).unwrap().unwrap();
+            (
+                p.chrom().to_string(),
+                p.start(),
+                p.stop(),
+                p.column_as_f64(5),
+            )
+        };
+        let query = |chrom: &str, start: u64, stop: u64| {
+            Some(Position::Bed(BedRecord::new(
+                chrom,
+                start,
+                stop,
+                None,
+                None,
+                vec![],
+            )))
+        };
+        assert_eq!(next(None), ("chr1".to_string(), 0, 100, Some(2.0)));
+        // the query is after the first chunk, so the run that contains it is read from the
+        // start of the query.
+        assert_eq!(
+            next(query("chr1", 150_051, 150_060)),
+            ("chr1".to_string(), 150_050, 150_100, Some(5.0))
+        );
+        assert_eq!(
+            next(query("chr2", 51, 60)),
+            ("chr2".to_string(), 50, 100, Some(0.0))
+        );
+        assert!(r.next_position(None).is_none());
+    }
+}
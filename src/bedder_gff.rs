@@ -0,0 +1,403 @@
+#![allow(clippy::useless_conversion)] // these are needed to support e.g. smartstring
+use crate::position::{Position, Positioned, Strand};
+use crate::string::String;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+/// A GFF3 or GTF feature. Coordinates are converted to 0-based, half-open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GffRecord {
+    chrom: String,
+    pub source: String,
+    pub feature_type: String,
+    start: u64,
+    stop: u64,
+    pub score: Option<f64>,
+    strand: Strand,
+    pub phase: Option<u8>,
+    /// attributes from column 9 in file order. Values are percent-decoded for GFF3 and
+    /// unquoted for GTF. Multi-valued GFF3 attributes (e.g. `Parent=a,b`) are kept as one value.
+    pub attributes: Vec<(String, String)>,
+}
+
+// attributes used, in order, as the name of a feature.
+const NAME_ATTRIBUTES: [&str; 4] = ["Name", "gene_name", "ID", "gene_id"];
+
+impl GffRecord {
+    /// Parse a (non-comment) GFF3 or GTF line.
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} in GFF/GTF line: {}", msg, line),
+            )
+        };
+        let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+        if fields.len() < 8 {
+            return Err(invalid("expected at least 8 tab-separated columns"));
+        }
+        let start: u64 = fields[3].parse().map_err(|_| invalid("invalid start"))?;
+        let stop: u64 = fields[4].parse().map_err(|_| invalid("invalid end"))?;
+        if start == 0 || stop < start {
+            return Err(invalid("invalid coordinates"));
+        }
+        let score = match fields[5] {
+            "." => None,
+            s => Some(s.parse().map_err(|_| invalid("invalid score"))?),
+        };
+        let phase = match fields[7] {
+            "." => None,
+            s => Some(s.parse().map_err(|_| invalid("invalid phase"))?),
+        };
+        Ok(GffRecord {
+            chrom: String::from(fields[0]),
+            source: String::from(fields[1]),
+            feature_type: String::from(fields[2]),
+            start: start - 1,
+            stop,
+            score,
+            strand: Strand::from_str_lossy(fields[6]),
+            phase,
+            attributes: fields.get(8).map_or_else(Vec::new, |a| parse_attributes(a)),
+        })
+    }
+
+    /// The value of the first attribute named `key`.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The first of the Name, gene_name, ID or gene_id attributes.
+    pub fn name(&self) -> Option<&str> {
+        NAME_ATTRIBUTES.iter().find_map(|k| self.attribute(k))
+    }
+}
+
+// GFF3 attributes are `key=value;...`; GTF attributes are `key "value"; ...`.
+fn parse_attributes(s: &str) -> Vec<(String, String)> {
+    if s == "." {
+        return vec![];
+    }
+    s.split(';')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let eq = t.find('=');
+            let ws = t.find(char::is_whitespace);
+            match eq {
+                Some(eq) if ws.is_none_or(|ws| eq < ws) => {
+                    (String::from(&t[..eq]), percent_decode(&t[eq + 1..]))
+                }
+                _ => {
+                    let (k, v) = t.split_once(char::is_whitespace).unwrap_or((t, ""));
+                    (String::from(k), String::from(v.trim().trim_matches('"')))
+                }
+            }
+        })
+        .collect()
+}
+
+// decode %XX escapes (e.g. %3B for ';') in GFF3 values.
+fn percent_decode(s: &str) -> String {
+    if !s.contains('%') {
+        return String::from(s);
+    }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from(std::string::String::from_utf8_lossy(&out).as_ref())
+}
+
+impl Positioned for GffRecord {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start
+    }
+
+    #[inline]
+    fn stop(&self) -> u64 {
+        self.stop
+    }
+
+    fn set_start(&mut self, start: u64) {
+        self.start = start;
+    }
+
+    fn set_stop(&mut self, stop: u64) {
+        self.stop = stop;
+    }
+
+    #[inline]
+    fn strand(&self) -> Strand {
+        self.strand
+    }
+
+    fn clone_box(&self) -> Box<dyn Positioned> {
+        Box::new(self.clone())
+    }
+}
+
+/// A condition on a GFF/GTF attribute.
+#[derive(Debug, Clone)]
+pub enum AttributeCondition {
+    /// `key=value`: the attribute is present with exactly this value.
+    Equals(String, String),
+    /// `key~regex`: the attribute is present and its value matches the regular expression.
+    Matches(String, regex::Regex),
+}
+
+impl AttributeCondition {
+    #[inline]
+    pub fn accepts(&self, record: &GffRecord) -> bool {
+        match self {
+            AttributeCondition::Equals(key, value) => record.attribute(key) == Some(value.as_str()),
+            AttributeCondition::Matches(key, re) => {
+                record.attribute(key).is_some_and(|v| re.is_match(v))
+            }
+        }
+    }
+}
+
+impl FromStr for AttributeCondition {
+    type Err = std::string::String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the first of '=' or '~' separates the key from the value.
+        let Some(i) = s.find(['=', '~']) else {
+            return Err(format!(
+                "invalid attribute condition '{}'; expected key=value or key~regex",
+                s
+            ));
+        };
+        let (key, value) = (&s[..i], &s[i + 1..]);
+        if key.is_empty() {
+            return Err(format!("attribute condition '{}' has an empty key", s));
+        }
+        if s.as_bytes()[i] == b'=' {
+            return Ok(AttributeCondition::Equals(
+                String::from(key),
+                String::from(value),
+            ));
+        }
+        let re = regex::Regex::new(value)
+            .map_err(|e| format!("invalid regular expression in '{}': {}", s, e))?;
+        Ok(AttributeCondition::Matches(String::from(key), re))
+    }
+}
+
+/// GffFilter decides which features are reported. An empty filter accepts everything.
+#[derive(Debug, Clone, Default)]
+pub struct GffFilter {
+    /// when not empty, only features with one of these types (column 3) are reported.
+    pub feature_types: Vec<String>,
+    /// all of these conditions must hold.
+    pub attributes: Vec<AttributeCondition>,
+}
+
+impl GffFilter {
+    pub fn is_empty(&self) -> bool {
+        self.feature_types.is_empty() && self.attributes.is_empty()
+    }
+
+    #[inline]
+    pub fn accepts_feature_type(&self, feature_type: &str) -> bool {
+        self.feature_types.is_empty() || self.feature_types.iter().any(|t| t == feature_type)
+    }
+
+    #[inline]
+    pub fn accepts(&self, record: &GffRecord) -> bool {
+        self.accepts_feature_type(&record.feature_type)
+            && self.attributes.iter().all(|c| c.accepts(record))
+    }
+}
+
+/// BedderGff reads GFF3 or GTF features as `Position::Gff`. Comment lines are skipped and
+/// reading stops at a `##FASTA` section.
+pub struct BedderGff {
+    reader: Box<dyn BufRead>,
+    line_number: u64,
+    line: std::string::String,
+    path: String,
+    filter: GffFilter,
+    done: bool,
+}
+
+impl BedderGff {
+    pub fn new(reader: Box<dyn BufRead>, path: &str) -> Self {
+        BedderGff {
+            reader,
+            line_number: 0,
+            line: std::string::String::new(),
+            path: String::from(path),
+            filter: GffFilter::default(),
+            done: false,
+        }
+    }
+
+    /// Only report features that pass `filter`. Other lines are dropped as they are read, before
+    /// their attributes are parsed when the feature type does not match.
+    pub fn with_filter(mut self, filter: GffFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl crate::position::PositionedIterator for BedderGff {
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        while !self.done {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line_number += 1;
+                    let line = self.line.trim_end();
+                    if line.starts_with("##FASTA") {
+                        self.done = true;
+                        continue;
+                    }
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    if !self.filter.feature_types.is_empty()
+                        && line
+                            .split('\t')
+                            .nth(2)
+                            .is_some_and(|t| !self.filter.accepts_feature_type(t))
+                    {
+                        continue;
+                    }
+                    match GffRecord::parse(line) {
+                        Ok(r) if self.filter.accepts(&r) => {
+                            return Some(Ok(Position::Gff(Box::new(r))))
+                        }
+                        Ok(_) => continue,
+                        Err(e) => {
+                            return Some(Err(io::Error::new(
+                                e.kind(),
+                                format!("{}:{}: {}", self.path, self.line_number, e),
+                            )))
+                        }
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+
+    fn name(&self) -> String {
+        String::from(format!("GFF|{}:{}", self.path, self.line_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::PositionedIterator;
+
+    #[test]
+    fn test_parse_gff3() {
+        let r = GffRecord::parse(
+            "chr1\tensembl\texon\t101\t200\t.\t-\t0\tID=exon1;Parent=tx1,tx2;Name=a%3Bb",
+        )
+        .unwrap();
+        assert_eq!((r.chrom(), r.start(), r.stop()), ("chr1", 100, 200));
+        assert_eq!(r.feature_type, "exon");
+        assert_eq!(r.source, "ensembl");
+        assert_eq!(r.strand(), Strand::Reverse);
+        assert_eq!(r.phase, Some(0));
+        assert_eq!(r.score, None);
+        assert_eq!(r.attribute("Parent"), Some("tx1,tx2"));
+        assert_eq!(r.name(), Some("a;b"));
+    }
+
+    #[test]
+    fn test_parse_gtf() {
+        let r = GffRecord::parse(
+            "chr2\tHAVANA\tgene\t1\t10\t5.5\t+\t.\tgene_id \"ENSG1\"; gene_name \"TP53\"; tag \"basic\";",
+        )
+        .unwrap();
+        assert_eq!((r.start(), r.stop()), (0, 10));
+        assert_eq!(r.score, Some(5.5));
+        assert_eq!(r.phase, None);
+        assert_eq!(r.attribute("gene_id"), Some("ENSG1"));
+        assert_eq!(r.name(), Some("TP53"));
+        assert_eq!(r.attributes.len(), 3);
+
+        assert!(GffRecord::parse("chr1\tsrc\tgene\t0\t10\t.\t+\t.").is_err());
+        assert!(GffRecord::parse("chr1\t100\t200").is_err());
+    }
+
+    #[test]
+    fn test_read_gff() {
+        let data = "##gff-version 3\nchr1\t.\tgene\t1\t100\t.\t+\t.\tID=g1\n#comment\n\nchr1\t.\texon\t10\t20\t.\t+\t.\tParent=g1\n##FASTA\n>chr1\nACGT\n";
+        let mut gff = BedderGff::new(Box::new(std::io::Cursor::new(data)), "memory");
+        let mut found = vec![];
+        while let Some(p) = gff.next_position(None) {
+            let p = p.unwrap();
+            found.push((p.start(), p.stop(), p.name().map(String::from)));
+        }
+        assert_eq!(
+            found,
+            vec![(0, 100, Some(String::from("g1"))), (9, 20, None)]
+        );
+    }
+
+    #[test]
+    fn test_filter_gff() {
+        let data =
+            "chr1\t.\tgene\t1\t100\t.\t+\t.\tgene_id \"g1\"; gene_biotype \"protein_coding\";
+chr1\t.\texon\t10\t20\t.\t+\t.\tgene_id \"g1\"; gene_biotype \"protein_coding\";
+chr1\t.\tgene\t200\t300\t.\t+\t.\tgene_id \"g2\"; gene_biotype \"lncRNA\";
+chr1\t.\tgene\t400\t500\t.\t+\t.\tgene_id \"g3\"; gene_biotype \"protein_coding\";
+";
+        let ids = |filter: GffFilter| {
+            let mut gff =
+                BedderGff::new(Box::new(std::io::Cursor::new(data)), "memory").with_filter(filter);
+            let mut ids = vec![];
+            while let Some(p) = gff.next_position(None) {
+                ids.push(String::from(p.unwrap().attribute("gene_id").unwrap()));
+            }
+            ids
+        };
+        assert_eq!(ids(GffFilter::default()).len(), 4);
+
+        let filter = GffFilter {
+            feature_types: vec![String::from("gene")],
+            attributes: vec!["gene_biotype=protein_coding".parse().unwrap()],
+        };
+        assert_eq!(ids(filter), vec!["g1", "g3"]);
+
+        let filter = GffFilter {
+            feature_types: vec![],
+            attributes: vec!["gene_id~^g[12]$".parse().unwrap()],
+        };
+        assert_eq!(ids(filter), vec!["g1", "g1", "g2"]);
+
+        assert!("gene_id".parse::<AttributeCondition>().is_err());
+        assert!("=x".parse::<AttributeCondition>().is_err());
+        assert!("gene_id~(".parse::<AttributeCondition>().is_err());
+    }
+}
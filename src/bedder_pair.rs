@@ -0,0 +1,964 @@
+#![allow(clippy::useless_conversion)] // these are needed to support e.g. smartstring
+use crate::chrom_ordering::Chromosome;
+use crate::intersection::IntersectionIterator;
+use crate::position::{Position, Positioned, PositionedIterator, Strand};
+use crate::report_options::StrandMode;
+use crate::string::String;
+use clap::ValueEnum;
+use hashbrown::HashMap;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// One of the two ends of a pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairSide {
+    /// chrom1/start1/end1 of BEDPE or the query of PAF.
+    First,
+    /// chrom2/start2/end2 of BEDPE or the target of PAF.
+    Second,
+}
+
+impl fmt::Display for PairSide {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PairSide::First => write!(f, "first"),
+            PairSide::Second => write!(f, "second"),
+        }
+    }
+}
+
+/// Formats with two intervals per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairFormat {
+    /// chrom1 start1 end1 chrom2 start2 end2 [name score strand1 strand2 ...]
+    Bedpe,
+    /// minimap2 pairwise alignment format. The query is the first end and the target the
+    /// second.
+    Paf,
+}
+
+impl PairFormat {
+    /// PAF lines have at least 12 columns with integer lengths and coordinates and a
+    /// relative strand in column 5. Anything else is read as BEDPE.
+    pub fn detect(line: &str) -> Self {
+        let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+        let is_int = |i: usize| fields[i].parse::<u64>().is_ok();
+        if fields.len() >= 12
+            && [1, 2, 3, 6, 7, 8].iter().all(|&i| is_int(i))
+            && matches!(fields[4], "+" | "-")
+        {
+            PairFormat::Paf
+        } else {
+            PairFormat::Bedpe
+        }
+    }
+}
+
+/// One end of a pair. Coordinates are 0-based, half-open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairInterval {
+    pub chrom: String,
+    pub start: u64,
+    pub stop: u64,
+    pub strand: Strand,
+}
+
+/// A BEDPE or PAF record. Either end may be absent (BEDPE chrom `.` and start `-1`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairRecord {
+    ends: [Option<PairInterval>; 2],
+    /// the BEDPE name (column 7) or the PAF query name.
+    pub name: Option<String>,
+    /// the BEDPE score (column 8) or the PAF mapping quality.
+    pub score: Option<f64>,
+    line: String,
+    format: PairFormat,
+}
+
+impl PairRecord {
+    /// Parse a (non-comment) line in the given format.
+    pub fn parse(line: &str, format: PairFormat) -> io::Result<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} in {:?} line: {}", msg, format, line),
+            )
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        let optional = |i: usize| fields.get(i).copied().filter(|s| *s != ".");
+        let score = |i: usize| -> io::Result<Option<f64>> {
+            optional(i)
+                .map(|s| s.parse().map_err(|_| invalid("invalid score")))
+                .transpose()
+        };
+        let (ends, name, score) = match format {
+            PairFormat::Bedpe => {
+                if fields.len() < 6 {
+                    return Err(invalid("expected at least 6 tab-separated columns"));
+                }
+                let first = parse_end(&fields[0..3], optional(8), &invalid)?;
+                let second = parse_end(&fields[3..6], optional(9), &invalid)?;
+                ([first, second], optional(6), score(7)?)
+            }
+            PairFormat::Paf => {
+                if fields.len() < 12 {
+                    return Err(invalid("expected at least 12 tab-separated columns"));
+                }
+                if !matches!(fields[4], "+" | "-") {
+                    return Err(invalid("invalid strand"));
+                }
+                let first = parse_end(&[fields[0], fields[2], fields[3]], Some("+"), &invalid)?;
+                let second = parse_end(
+                    &[fields[5], fields[7], fields[8]],
+                    Some(fields[4]),
+                    &invalid,
+                )?;
+                ([first, second], Some(fields[0]), score(11)?)
+            }
+        };
+        Ok(PairRecord {
+            ends,
+            name: name.map(String::from),
+            score,
+            line: String::from(line),
+            format,
+        })
+    }
+
+    /// The given end, or None if it is absent.
+    pub fn end(&self, side: PairSide) -> Option<&PairInterval> {
+        match side {
+            PairSide::First => self.ends[0].as_ref(),
+            PairSide::Second => self.ends[1].as_ref(),
+        }
+    }
+
+    /// The original line, without the line ending.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+}
+
+// chrom, start, stop. An end with chrom `.` or start `-1` is absent.
+fn parse_end(
+    fields: &[&str],
+    strand: Option<&str>,
+    invalid: &dyn Fn(&str) -> io::Error,
+) -> io::Result<Option<PairInterval>> {
+    let [chrom, start, stop] = fields else {
+        return Ok(None);
+    };
+    if *chrom == "." || *start == "-1" {
+        return Ok(None);
+    }
+    let start: u64 = start.parse().map_err(|_| invalid("invalid start"))?;
+    let stop: u64 = stop.parse().map_err(|_| invalid("invalid end"))?;
+    if stop < start {
+        return Err(invalid("invalid coordinates"));
+    }
+    Ok(Some(PairInterval {
+        chrom: String::from(*chrom),
+        start,
+        stop,
+        strand: strand.map_or(Strand::Unknown, Strand::from_str_lossy),
+    }))
+}
+
+/// Reads pairs from a BEDPE or PAF file. The format is detected from the first record.
+/// Blank lines and `#`, `track` and `browser` header lines are skipped.
+pub struct PairRecords<R: BufRead> {
+    lines: io::Lines<R>,
+    format: Option<PairFormat>,
+}
+
+/// The pairs of a BEDPE or PAF file, in input order.
+pub fn read_pairs<R: BufRead>(reader: R) -> PairRecords<R> {
+    PairRecords {
+        lines: reader.lines(),
+        format: None,
+    }
+}
+
+impl<R: BufRead> Iterator for PairRecords<R> {
+    type Item = io::Result<PairRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            if line.trim().is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+            let format = *self.format.get_or_insert_with(|| PairFormat::detect(&line));
+            return Some(PairRecord::parse(&line, format));
+        }
+    }
+}
+
+// the sort key (chromosome index, start, stop, pair index) of the `side` end of a pair, if it is
+// present and its chromosome is in the genome.
+fn end_key(
+    pair: &PairRecord,
+    index: usize,
+    side: PairSide,
+    chrom_order: &HashMap<String, Chromosome>,
+) -> Option<SortKey> {
+    let end = pair.end(side)?;
+    let Some(chrom) = chrom_order.get(&end.chrom) else {
+        log::info!(
+            "chromosome {} of pair {} is not in the genome file",
+            end.chrom,
+            index + 1
+        );
+        return None;
+    };
+    Some([chrom.index as u64, end.start, end.stop, index as u64])
+}
+
+/// One end of a pair as a Positioned. `index` is the position of the pair in the input.
+#[derive(Debug, Clone)]
+pub struct PairEnd {
+    pub pair: Arc<PairRecord>,
+    pub index: usize,
+    pub side: PairSide,
+    start: u64,
+    stop: u64,
+}
+
+impl PairEnd {
+    // the `side` end of `pair` if it is present and its chromosome is in the genome, with the
+    // index of the chromosome.
+    fn new(
+        pair: Arc<PairRecord>,
+        index: usize,
+        side: PairSide,
+        chrom_order: &HashMap<String, Chromosome>,
+    ) -> Option<(usize, Self)> {
+        let [chrom_index, start, stop, _] = end_key(&pair, index, side, chrom_order)?;
+        Some((
+            chrom_index as usize,
+            PairEnd {
+                pair,
+                index,
+                side,
+                start,
+                stop,
+            },
+        ))
+    }
+
+    fn interval(&self) -> &PairInterval {
+        self.pair
+            .end(self.side)
+            .expect("PairEnd is only created for ends that are present")
+    }
+}
+
+impl Positioned for PairEnd {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.interval().chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start
+    }
+
+    #[inline]
+    fn stop(&self) -> u64 {
+        self.stop
+    }
+
+    #[inline]
+    fn set_start(&mut self, start: u64) {
+        self.start = start;
+    }
+
+    #[inline]
+    fn set_stop(&mut self, stop: u64) {
+        self.stop = stop;
+    }
+
+    fn strand(&self) -> Strand {
+        self.interval().strand
+    }
+
+    fn clone_box(&self) -> Box<dyn Positioned> {
+        Box::new(self.clone())
+    }
+}
+
+// pairs read by a PairReader, with their index, until they are taken by the caller.
+type ReadPairs = Rc<RefCell<VecDeque<(usize, Arc<PairRecord>)>>>;
+
+/// Streams one end of each pair from a file that is sorted by that end (usually the first end,
+/// as pairs and contacts files are). Ends on chromosomes that are not in the genome file are not
+/// reported.
+pub struct PairReader<R: BufRead> {
+    pairs: PairRecords<R>,
+    side: PairSide,
+    chrom_order: HashMap<String, Chromosome>,
+    n: usize,
+    last: Option<(usize, u64)>,
+    read: Option<ReadPairs>,
+}
+
+impl<R: BufRead> PairReader<R> {
+    pub fn new(reader: R, side: PairSide, chrom_order: &HashMap<String, Chromosome>) -> Self {
+        PairReader {
+            pairs: read_pairs(reader),
+            side,
+            chrom_order: chrom_order.clone(),
+            n: 0,
+            last: None,
+            read: None,
+        }
+    }
+
+    // also push every pair that is read, including those without a reported end, to `read`.
+    fn with_read_pairs(mut self, read: ReadPairs) -> Self {
+        self.read = Some(read);
+        self
+    }
+}
+
+impl<R: BufRead> PositionedIterator for PairReader<R> {
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        loop {
+            let pair = match self.pairs.next()? {
+                Ok(pair) => Arc::new(pair),
+                Err(e) => return Some(Err(e)),
+            };
+            let index = self.n;
+            self.n += 1;
+            if let Some(read) = &self.read {
+                read.borrow_mut().push_back((index, Arc::clone(&pair)));
+            }
+            let Some((chrom_index, end)) = PairEnd::new(pair, index, self.side, &self.chrom_order)
+            else {
+                continue;
+            };
+            let key = (chrom_index, end.start);
+            if self.last.is_some_and(|last| key < last) {
+                return Some(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "pairs must be sorted by their {} end in the genome file order: {}",
+                        self.side,
+                        end.pair.line()
+                    ),
+                )));
+            }
+            self.last = Some(key);
+            return Some(Ok(Position::Pair(end)));
+        }
+    }
+
+    fn name(&self) -> String {
+        String::from(format!("pairs|{} end:{}", self.side, self.n))
+    }
+}
+
+// records held in memory before they are sorted and written to a temporary file.
+const SORT_CHUNK: usize = 1 << 20;
+
+type SortKey = [u64; 4];
+
+/// Sorts records of a key and a line of text with bounded memory. Each chunk of records is
+/// sorted and written to a temporary file and the files are merged as they are read back.
+struct ExternalSort {
+    chunk: Vec<(SortKey, std::string::String)>,
+    chunk_size: usize,
+    runs: Vec<File>,
+}
+
+impl ExternalSort {
+    fn new(chunk_size: usize) -> Self {
+        ExternalSort {
+            chunk: Vec::new(),
+            chunk_size,
+            runs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, key: SortKey, text: std::string::String) -> io::Result<()> {
+        self.chunk.push((key, text));
+        if self.chunk.len() >= self.chunk_size {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.chunk.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut w = BufWriter::new(tempfile::tempfile()?);
+        for ([k0, k1, k2, k3], text) in self.chunk.drain(..) {
+            writeln!(w, "{}\t{}\t{}\t{}\t{}", k0, k1, k2, k3, text)?;
+        }
+        let mut f = w.into_inner().map_err(|e| e.into_error())?;
+        f.seek(SeekFrom::Start(0))?;
+        self.runs.push(f);
+        Ok(())
+    }
+
+    fn into_sorted(mut self) -> io::Result<SortedRecords> {
+        self.chunk.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut sources = vec![RunSource::Memory(self.chunk.into_iter())];
+        sources.extend(
+            self.runs
+                .into_iter()
+                .map(|f| RunSource::File(BufReader::new(f).lines())),
+        );
+        let mut sorted = SortedRecords {
+            heads: vec![None; sources.len()],
+            sources,
+            heap: BinaryHeap::new(),
+        };
+        for i in 0..sorted.sources.len() {
+            sorted.advance(i)?;
+        }
+        Ok(sorted)
+    }
+}
+
+enum RunSource {
+    Memory(std::vec::IntoIter<(SortKey, std::string::String)>),
+    File(io::Lines<BufReader<File>>),
+}
+
+impl RunSource {
+    fn next(&mut self) -> Option<io::Result<(SortKey, std::string::String)>> {
+        match self {
+            RunSource::Memory(it) => it.next().map(Ok),
+            RunSource::File(lines) => Some(lines.next()?.and_then(|line| {
+                let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid sort record");
+                let mut fields = line.splitn(5, '\t');
+                let mut key = [0u64; 4];
+                for k in key.iter_mut() {
+                    *k = fields
+                        .next()
+                        .and_then(|f| f.parse().ok())
+                        .ok_or_else(invalid)?;
+                }
+                let text = fields.next().ok_or_else(invalid)?;
+                Ok((key, text.to_string()))
+            })),
+        }
+    }
+}
+
+/// The records of an ExternalSort in key order.
+struct SortedRecords {
+    sources: Vec<RunSource>,
+    // the next record of each source.
+    heads: Vec<Option<std::string::String>>,
+    heap: BinaryHeap<Reverse<(SortKey, usize)>>,
+}
+
+impl SortedRecords {
+    fn advance(&mut self, i: usize) -> io::Result<()> {
+        if let Some((key, text)) = self.sources[i].next().transpose()? {
+            self.heads[i] = Some(text);
+            self.heap.push(Reverse((key, i)));
+        }
+        Ok(())
+    }
+
+    fn peek_key(&self) -> Option<SortKey> {
+        self.heap.peek().map(|Reverse((key, _))| *key)
+    }
+}
+
+impl Iterator for SortedRecords {
+    type Item = io::Result<(SortKey, std::string::String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((key, i)) = self.heap.pop()?;
+        let text = self.heads[i].take().expect("heap entries have a record");
+        Some(self.advance(i).map(|_| (key, text)))
+    }
+}
+
+/// Iterates over one end of each pair sorted by the genome order. Pairs are usually sorted by
+/// their first end only so this is the secondary sort that lets the other end be intersected.
+/// The ends are sorted in temporary files so memory does not grow with the number of pairs.
+/// Ends on chromosomes that are not in the genome file are not reported.
+pub struct PairEndIterator {
+    ends: SortedRecords,
+    format: PairFormat,
+    side: PairSide,
+    n: usize,
+}
+
+impl PairEndIterator {
+    pub fn new<R: BufRead>(
+        reader: R,
+        side: PairSide,
+        chrom_order: &HashMap<String, Chromosome>,
+    ) -> io::Result<Self> {
+        let mut ends = ExternalSort::new(SORT_CHUNK);
+        let mut format = PairFormat::Bedpe;
+        for (index, pair) in read_pairs(reader).enumerate() {
+            let pair = pair?;
+            format = pair.format;
+            if let Some(key) = end_key(&pair, index, side, chrom_order) {
+                ends.push(key, pair.line().to_string())?;
+            }
+        }
+        Ok(PairEndIterator {
+            ends: ends.into_sorted()?,
+            format,
+            side,
+            n: 0,
+        })
+    }
+}
+
+impl PositionedIterator for PairEndIterator {
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        let ([_, start, stop, index], line) = match self.ends.next()? {
+            Ok(r) => r,
+            Err(e) => return Some(Err(e)),
+        };
+        self.n += 1;
+        Some(PairRecord::parse(&line, self.format).map(|pair| {
+            Position::Pair(PairEnd {
+                pair: Arc::new(pair),
+                index: index as usize,
+                side: self.side,
+                start,
+                stop,
+            })
+        }))
+    }
+
+    fn name(&self) -> String {
+        String::from(format!("pairs|{} end:{}", self.side, self.n))
+    }
+}
+
+/// A B interval that overlaps one end of a pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndHit {
+    pub chrom: String,
+    pub start: u64,
+    pub stop: u64,
+    /// When B is a file of pairs, the index of the B pair, which of its ends overlaps and its
+    /// line.
+    pub pair: Option<(usize, PairSide, String)>,
+}
+
+impl EndHit {
+    fn from_position(p: &Position) -> Self {
+        EndHit {
+            chrom: String::from(p.chrom()),
+            start: p.start(),
+            stop: p.stop(),
+            pair: match p {
+                Position::Pair(end) => Some((end.index, end.side, String::from(end.pair.line()))),
+                _ => None,
+            },
+        }
+    }
+
+    // a line of text for the temporary files.
+    fn encode(&self) -> std::string::String {
+        match &self.pair {
+            None => format!("{}\t{}\t{}", self.chrom, self.start, self.stop),
+            Some((index, side, line)) => format!(
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                self.chrom, self.start, self.stop, index, side, line
+            ),
+        }
+    }
+
+    fn decode(s: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid pair overlap record");
+        let f: Vec<&str> = s.splitn(6, '\t').collect();
+        let int = |i: usize| f.get(i).and_then(|v| v.parse().ok()).ok_or_else(invalid);
+        let pair = match f.len() {
+            3 => None,
+            6 => {
+                let side = match f[4] {
+                    "first" => PairSide::First,
+                    "second" => PairSide::Second,
+                    _ => return Err(invalid()),
+                };
+                Some((int(3)? as usize, side, String::from(f[5])))
+            }
+            _ => return Err(invalid()),
+        };
+        Ok(EndHit {
+            chrom: String::from(f[0]),
+            start: int(1)?,
+            stop: int(2)?,
+            pair,
+        })
+    }
+}
+
+/// Find the B intervals that overlap each end of the pairs read from `reader` and call `report`
+/// with each pair, in input order, and the B intervals that overlap its first and second ends.
+/// The pairs must be sorted by their first end, which is intersected as the pairs are read. The
+/// second ends and the overlaps are sorted in temporary files so memory does not grow with the
+/// number of pairs. `b_iters` opens the B files, which are read once for each end and must be
+/// sorted by the genome order.
+pub fn pair_overlaps<R, B, F>(
+    reader: R,
+    mut b_iters: B,
+    chrom_order: &HashMap<String, Chromosome>,
+    strand_mode: StrandMode,
+    mut report: F,
+) -> io::Result<()>
+where
+    R: BufRead + 'static,
+    B: FnMut() -> io::Result<Vec<Box<dyn PositionedIterator>>>,
+    F: FnMut(&PairRecord, &[EndHit], &[EndHit]) -> io::Result<()>,
+{
+    // each pair and the overlaps of its first end, in input order.
+    let mut pairs = BufWriter::new(tempfile::tempfile()?);
+    let mut second_ends = ExternalSort::new(SORT_CHUNK);
+    let mut format = PairFormat::Bedpe;
+    let mut save = |index: usize, pair: &PairRecord, hits: &[EndHit]| -> io::Result<()> {
+        format = pair.format;
+        writeln!(pairs, "{}\t{}", hits.len(), pair.line())?;
+        for h in hits {
+            writeln!(pairs, "{}", h.encode())?;
+        }
+        if let Some(key) = end_key(pair, index, PairSide::Second, chrom_order) {
+            second_ends.push(key, pair.line().to_string())?;
+        }
+        Ok(())
+    };
+
+    let read: ReadPairs = Rc::default();
+    let first_ends =
+        PairReader::new(reader, PairSide::First, chrom_order).with_read_pairs(Rc::clone(&read));
+    let it =
+        IntersectionIterator::new(Box::new(first_ends), b_iters()?, chrom_order, -1, -1, false)?
+            .with_strand_mode(strand_mode);
+    for intersection in it {
+        let intersection = intersection?;
+        let base = intersection
+            .base_interval
+            .try_lock()
+            .expect("failed to lock base_interval");
+        let Position::Pair(end) = &*base else {
+            unreachable!("PairReader reports pair ends");
+        };
+        let hits: Vec<EndHit> = intersection
+            .overlapping
+            .iter()
+            .map(|o| {
+                EndHit::from_position(&o.interval.try_lock().expect("failed to lock interval"))
+            })
+            .collect();
+        // pairs read before this one have no first end to intersect.
+        loop {
+            let next = read.borrow_mut().pop_front();
+            let (index, pair) = next.expect("pair ends are reported after their pair is read");
+            if index == end.index {
+                save(index, &pair, &hits)?;
+                break;
+            }
+            save(index, &pair, &[])?;
+        }
+    }
+    loop {
+        let next = read.borrow_mut().pop_front();
+        let Some((index, pair)) = next else {
+            break;
+        };
+        save(index, &pair, &[])?;
+    }
+
+    // the overlaps of the second ends, sorted by the index of their pair.
+    let mut second_hits = ExternalSort::new(SORT_CHUNK);
+    let second_ends = PairEndIterator {
+        ends: second_ends.into_sorted()?,
+        format,
+        side: PairSide::Second,
+        n: 0,
+    };
+    let it = IntersectionIterator::new(
+        Box::new(second_ends),
+        b_iters()?,
+        chrom_order,
+        -1,
+        -1,
+        false,
+    )?
+    .with_strand_mode(strand_mode);
+    let mut n = 0u64;
+    for intersection in it {
+        let intersection = intersection?;
+        let base = intersection
+            .base_interval
+            .try_lock()
+            .expect("failed to lock base_interval");
+        let Position::Pair(end) = &*base else {
+            unreachable!("PairEndIterator reports pair ends");
+        };
+        for o in &intersection.overlapping {
+            let hit =
+                EndHit::from_position(&o.interval.try_lock().expect("failed to lock interval"));
+            second_hits.push([end.index as u64, n, 0, 0], hit.encode())?;
+            n += 1;
+        }
+    }
+    let mut second_hits = second_hits.into_sorted()?;
+
+    let mut f = pairs.into_inner().map_err(|e| e.into_error())?;
+    f.seek(SeekFrom::Start(0))?;
+    let mut lines = BufReader::new(f).lines();
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid pair record");
+    let mut index = 0u64;
+    while let Some(line) = lines.next() {
+        let line = line?;
+        let (n_hits, line) = line.split_once('\t').ok_or_else(invalid)?;
+        let n_hits: usize = n_hits.parse().map_err(|_| invalid())?;
+        let pair = PairRecord::parse(line, format)?;
+        let first = (0..n_hits)
+            .map(|_| EndHit::decode(&lines.next().ok_or_else(invalid)??))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut second = Vec::new();
+        while second_hits.peek_key().is_some_and(|k| k[0] == index) {
+            let (_, hit) = second_hits.next().expect("peeked")?;
+            second.push(EndHit::decode(&hit)?);
+        }
+        report(&pair, &first, &second)?;
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Whether the first and second ends of a pair overlap another pair, given which ends of the
+/// other pair (`[first, second]`) overlap each of them. Both ends overlap only if they overlap
+/// different ends of the other pair; ends that overlap the same single end count as the first.
+pub fn paired_ends(first: [bool; 2], second: [bool; 2]) -> (bool, bool) {
+    if (first[0] && second[1]) || (first[1] && second[0]) {
+        return (true, true);
+    }
+    let first = first[0] || first[1];
+    (first, !first && (second[0] || second[1]))
+}
+
+/// Which ends of a pair must overlap for the pair to be reported. Bedtools -type
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, ValueEnum)]
+pub enum PairOverlapType {
+    /// Report overlaps with either end.
+    #[value(name = "either")]
+    #[default]
+    Either,
+    /// Report overlaps only if both ends overlap.
+    #[value(name = "both")]
+    Both,
+    /// Report overlaps only if exactly one end overlaps.
+    #[value(name = "xor")]
+    Xor,
+    /// Report pairs where neither end overlaps (without the overlaps).
+    #[value(name = "neither")]
+    Neither,
+    /// Report pairs where the ends do not both overlap (without the overlaps).
+    #[value(name = "notboth")]
+    NotBoth,
+}
+
+impl PairOverlapType {
+    /// Neither and NotBoth report the pairs that do not match Either and Both.
+    pub fn is_negated(&self) -> bool {
+        matches!(self, PairOverlapType::Neither | PairOverlapType::NotBoth)
+    }
+
+    /// Do overlaps on the `first` and `second` ends satisfy this type? For the negated types,
+    /// this is whether they satisfy Either (Neither) or Both (NotBoth).
+    pub fn matches(&self, first: bool, second: bool) -> bool {
+        match self {
+            PairOverlapType::Either | PairOverlapType::Neither => first || second,
+            PairOverlapType::Both | PairOverlapType::NotBoth => first && second,
+            PairOverlapType::Xor => first != second,
+        }
+    }
+}
+
+impl FromStr for PairOverlapType {
+    type Err = std::string::String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bedder_bed::BedderBed;
+    use std::io::Cursor;
+
+    fn genome() -> HashMap<String, Chromosome> {
+        crate::chrom_ordering::parse_genome("chr1\t1000\nchr2\t1000\n".as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_bedpe() {
+        let p = PairRecord::parse(
+            "chr1\t10\t20\tchr2\t30\t40\tp1\t5\t+\t-\textra\n",
+            PairFormat::Bedpe,
+        )
+        .unwrap();
+        let second = p.end(PairSide::Second).unwrap();
+        assert_eq!(
+            (second.chrom.as_str(), second.start, second.stop),
+            ("chr2", 30, 40)
+        );
+        assert_eq!(second.strand, Strand::Reverse);
+        assert_eq!(p.end(PairSide::First).unwrap().strand, Strand::Forward);
+        assert_eq!((p.name.as_deref(), p.score), (Some("p1"), Some(5.0)));
+        assert_eq!(p.line(), "chr1\t10\t20\tchr2\t30\t40\tp1\t5\t+\t-\textra");
+
+        let p = PairRecord::parse("chr1\t10\t20\t.\t-1\t-1", PairFormat::Bedpe).unwrap();
+        assert!(p.end(PairSide::Second).is_none());
+        assert_eq!(p.name, None);
+        assert!(PairRecord::parse("chr1\t10\t20\tchr2\t30", PairFormat::Bedpe).is_err());
+    }
+
+    #[test]
+    fn test_parse_paf() {
+        let line = "read1\t1000\t10\t200\t-\tchr2\t5000\t300\t490\t180\t190\t60\ttp:A:P";
+        assert_eq!(PairFormat::detect(line), PairFormat::Paf);
+        assert_eq!(
+            PairFormat::detect("chr1\t10\t20\tchr2\t30\t40"),
+            PairFormat::Bedpe
+        );
+        let p = PairRecord::parse(line, PairFormat::Paf).unwrap();
+        assert_eq!(p.end(PairSide::First).unwrap().start, 10);
+        let target = p.end(PairSide::Second).unwrap();
+        assert_eq!(
+            (target.chrom.as_str(), target.start, target.stop),
+            ("chr2", 300, 490)
+        );
+        assert_eq!(target.strand, Strand::Reverse);
+        assert_eq!((p.name.as_deref(), p.score), (Some("read1"), Some(60.0)));
+    }
+
+    fn b() -> io::Result<Vec<Box<dyn PositionedIterator>>> {
+        Ok(vec![Box::new(BedderBed::new(
+            Cursor::new("chr1\t150\t160\nchr2\t15\t16\n"),
+            None::<String>,
+        ))])
+    }
+
+    fn overlaps(pairs: &'static str) -> io::Result<Vec<(String, Vec<u64>, Vec<u64>)>> {
+        let mut result = vec![];
+        pair_overlaps(
+            pairs.as_bytes(),
+            b,
+            &genome(),
+            StrandMode::Ignore,
+            |pair, first, second| {
+                let starts =
+                    |hits: &[EndHit]| -> Vec<u64> { hits.iter().map(|h| h.start).collect() };
+                result.push((pair.name.clone().unwrap(), starts(first), starts(second)));
+                Ok(())
+            },
+        )?;
+        Ok(result)
+    }
+
+    #[test]
+    fn test_pair_overlaps() {
+        let pairs = "#header
+chr1\t10\t20\tchr1\t500\t600\tp1
+.\t-1\t-1\tchr2\t10\t20\tp2
+chr1\t150\t200\tchr1\t100\t200\tp3
+chr2\t10\t20\tchr1\t100\t200\tp4
+";
+        // pairs are reported in input order, including p2 without a first end.
+        assert_eq!(
+            overlaps(pairs).unwrap(),
+            vec![
+                (String::from("p1"), vec![], vec![]),
+                (String::from("p2"), vec![], vec![15]),
+                (String::from("p3"), vec![150], vec![150]),
+                (String::from("p4"), vec![15], vec![150]),
+            ]
+        );
+
+        let unsorted = "chr2\t10\t20\tchr1\t100\t200\tp1\nchr1\t10\t20\tchr1\t500\t600\tp2\n";
+        let e = overlaps(unsorted).unwrap_err();
+        assert!(e.to_string().contains("sorted by their first end"), "{}", e);
+    }
+
+    #[test]
+    fn test_pair_end_iterator() {
+        let pairs = "chr1\t10\t20\tchr2\t50\t60\tp1\nchr1\t30\t40\tchr1\t500\t600\tp2\nchr2\t5\t6\tchr1\t7\t8\tp3\n";
+        let mut ends = PairEndIterator::new(pairs.as_bytes(), PairSide::Second, &genome()).unwrap();
+        let mut result = vec![];
+        while let Some(p) = ends.next_position(None) {
+            let Position::Pair(end) = p.unwrap() else {
+                unreachable!();
+            };
+            result.push((end.index, end.chrom().to_string(), end.start()));
+        }
+        assert_eq!(
+            result,
+            vec![
+                (2, "chr1".to_string(), 7),
+                (1, "chr1".to_string(), 500),
+                (0, "chr2".to_string(), 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_external_sort() {
+        // small chunks so the records are merged from several temporary files.
+        let mut sort = ExternalSort::new(2);
+        for (i, k) in [5u64, 1, 4, 2, 3].iter().enumerate() {
+            sort.push([*k, 0, 0, i as u64], format!("r{}\tx", k))
+                .unwrap();
+        }
+        assert_eq!(sort.runs.len(), 2);
+        let sorted: Vec<_> = sort.into_sorted().unwrap().map(|r| r.unwrap().1).collect();
+        assert_eq!(sorted, vec!["r1\tx", "r2\tx", "r3\tx", "r4\tx", "r5\tx"]);
+
+        let hit = EndHit {
+            chrom: String::from("chr1"),
+            start: 5,
+            stop: 10,
+            pair: Some((3, PairSide::Second, String::from("chr1\t5\t10\tchr2\t1\t2"))),
+        };
+        assert_eq!(EndHit::decode(&hit.encode()).unwrap(), hit);
+    }
+
+    #[test]
+    fn test_pair_overlap_type() {
+        use PairOverlapType::*;
+        assert!(Either.matches(true, false) && !Either.matches(false, false));
+        assert!(Both.matches(true, true) && !Both.matches(true, false));
+        assert!(Xor.matches(false, true) && !Xor.matches(true, true));
+        assert!(Neither.is_negated() && !Xor.is_negated());
+        assert_eq!("notboth".parse::<PairOverlapType>(), Ok(NotBoth));
+
+        // both ends overlapping only the first end of the other pair is not a match on both.
+        assert_eq!(paired_ends([true, false], [true, false]), (true, false));
+        assert_eq!(paired_ends([true, false], [false, true]), (true, true));
+        assert_eq!(paired_ends([false, true], [true, true]), (true, true));
+        assert_eq!(paired_ends([false, false], [false, true]), (false, true));
+    }
+}
@@ -12,5 +12,5 @@ pub struct ClosestCmdArgs {
 }
 
 pub fn closest_command(args: ClosestCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
-    process_bedder(args.common, None, Some(args.closest))
+    process_bedder(args.common, None, Some(args.closest), None)
 }
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::shared::HELP_TEMPLATE;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Report regions of the genome not covered by the input intervals.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+The genome file must have a length (second column) for every chromosome.
+Chromosomes without any input intervals are reported in full.
+
+EXAMPLE:
+    $ bedder complement -a tests/map_b.bed -g tests/hg38.small.fai
+    chr1\t0\t120
+    chr1\t190\t350
+    chr1\t380\t248956422
+    chr2\t0\t242193529
+    ..."
+)]
+pub struct ComplementCmdArgs {
+    #[arg(
+        help = "input file (must be sorted by the genome file order; - for stdin)",
+        short = 'a'
+    )]
+    pub input_path: PathBuf,
+
+    #[arg(
+        help = "genome file with chromosome order and lengths",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "output file (default: stdout)",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+}
+
+pub fn complement_command(args: ComplementCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let chrom_order =
+        bedder::chrom_ordering::parse_genome(std::fs::File::open(&args.genome_file)?)?;
+
+    let (reader, _) = bedder::sniff::open_path(&args.input_path, None)?;
+    let reader = reader.with_chromosome_order(&chrom_order);
+
+    let complement = bedder::complement::ComplementIterator::new(
+        reader.into_positioned_iterator(),
+        &chrom_order,
+    )
+    .map_err(|e| format!("{} (genome file: {})", e, args.genome_file.display()))?;
+
+    let mut bed_writer = if args.output_path.to_str() == Some("-") {
+        bedder::bedder_bed::simplebed::BedWriter::from_writer(Box::new(std::io::BufWriter::new(
+            std::io::stdout(),
+        )))?
+    } else {
+        bedder::bedder_bed::simplebed::BedWriter::new(&args.output_path)?
+    };
+
+    for position in complement {
+        match position? {
+            bedder::position::Position::Bed(bed) => bed_writer.write_record(&bed.0)?,
+            _ => unreachable!("complement reports BED positions"),
+        }
+    }
+    bed_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::shared::{BInputArgs, IntervalWriter, StrandArgs, HELP_TEMPLATE};
+use bedder::bedder_bed::BedValue;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Coverage: report how much of each A interval is covered by B intervals.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+OUTPUT:
+    Each A interval is followed by:
+        the number of B intervals overlapping A
+        the number of bases in A covered by at least one B (overlapping B are not double-counted)
+        the length of A
+        the fraction of A covered
+
+    With --hist, each A interval is instead reported once per observed depth, followed by:
+        the depth
+        the number of bases in A at that depth
+        the length of A
+        the fraction of A at that depth
+
+EXAMPLE:
+    $ bedder coverage -a tests/map_a.bed -b tests/map_b.bed -g tests/hg38.small.fai
+    chr1\t100\t200\tgeneA\t10\t3\t70\t100\t0.7000000
+    chr1\t300\t400\tgeneB\t20\t1\t30\t100\t0.3000000"
+)]
+pub struct CoverageCmdArgs {
+    #[arg(help = "input A file (query, - for stdin)", short = 'a')]
+    pub query_path: PathBuf,
+
+    #[arg(help = "input B file(s)", short = 'b', required = true)]
+    pub other_paths: Vec<PathBuf>,
+
+    #[arg(
+        help = "genome file for chromosome ordering",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "output file (default: stdout). A .bedgraph/.bdg/.bg or .bw/.bigwig suffix writes the fraction covered (or the mean depth with --mean) as BedGraph or bigWig",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+
+    #[arg(
+        help = "report a histogram of depth for each A interval instead of the summary columns",
+        long = "hist"
+    )]
+    pub hist: bool,
+
+    #[arg(
+        help = "append the mean depth across each A interval to the summary columns",
+        long = "mean",
+        conflicts_with = "hist"
+    )]
+    pub mean: bool,
+
+    #[command(flatten)]
+    pub strand: StrandArgs,
+
+    #[command(flatten)]
+    pub b_input: BInputArgs,
+}
+
+fn format_fraction(f: f64) -> String {
+    format!("{:.7}", f)
+}
+
+pub fn coverage_command(args: CoverageCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let chrom_order = bedder::chrom_ordering::parse_genome(File::open(&args.genome_file)?)?;
+
+    let (a_reader, a_file_type) = bedder::sniff::open_path(&args.query_path, None)?;
+    if !matches!(a_file_type, bedder::sniff::FileType::Bed) {
+        return Err(
+            "coverage currently only supports BED files for -a (output is BED-based)".into(),
+        );
+    }
+
+    let b_iters = args
+        .other_paths
+        .iter()
+        .map(|p| args.b_input.open(p, &chrom_order).map(|(b_iter, _)| b_iter))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let ii = bedder::intersection::IntersectionIterator::new(
+        a_reader.into_positioned_iterator(),
+        b_iters,
+        &chrom_order,
+        -1,    // max_distance: not used
+        -1,    // n_closest: not used
+        false, // can_skip_ahead: need every A interval reported
+    )?
+    .with_strand_mode(args.strand.strand_mode());
+
+    let mut writer = IntervalWriter::new(&args.output_path, &chrom_order)?;
+    if args.hist && writer.is_signal() {
+        return Err("--hist cannot be written as BedGraph/bigWig".into());
+    }
+
+    for intersection in ii {
+        let intersection = intersection?;
+        let coverage = intersection.coverage();
+        let base = intersection
+            .base_interval
+            .try_lock()
+            .expect("failed to lock base_interval");
+        let bed_record = match &*base {
+            bedder::position::Position::Bed(bed) => &bed.0,
+            _ => return Err("coverage only supports BED input".into()),
+        };
+
+        if args.hist {
+            for (depth, &bases) in coverage.histogram.iter().enumerate() {
+                if bases == 0 {
+                    continue;
+                }
+                let mut record = bed_record.clone();
+                record.push_field(BedValue::Integer(depth as i64));
+                record.push_field(BedValue::Integer(bases as i64));
+                record.push_field(BedValue::Integer(coverage.length as i64));
+                record.push_field(BedValue::String(format_fraction(
+                    bases as f64 / coverage.length as f64,
+                )));
+                writer.write(&record, ".")?;
+            }
+            continue;
+        }
+
+        let mut record = bed_record.clone();
+        record.push_field(BedValue::Integer(coverage.n_overlaps as i64));
+        record.push_field(BedValue::Integer(coverage.covered_bases as i64));
+        record.push_field(BedValue::Integer(coverage.length as i64));
+        // the last column is the value for BedGraph/bigWig output.
+        let mut value = format_fraction(coverage.fraction());
+        record.push_field(BedValue::String(value.clone()));
+        if args.mean {
+            value = format_fraction(coverage.mean_depth());
+            record.push_field(BedValue::String(value.clone()));
+        }
+        writer.write(&record, &value)?;
+    }
+
+    writer.finish()
+}
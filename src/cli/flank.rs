@@ -0,0 +1,43 @@
+use clap::Parser;
+
+use crate::cli::shared::HELP_TEMPLATE;
+use crate::cli::slop::{write_slop, SlopAmountArgs, SlopIoArgs};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Flank: report the regions flanking each interval.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+Each interval is replaced by its left and/or right flank; the interval itself is not reported.
+Flanks are clamped to 0 and to the chromosome length from the genome file (when it has one),
+and empty flanks are skipped. Output is re-sorted by start.
+
+EXAMPLES:
+    1. 20 bases on each side:
+
+        $ bedder flank -a tests/map_a.bed -g tests/hg38.small.fai -b 20
+        chr1\t80\t100\tgeneA\t10
+        chr1\t200\t220\tgeneA\t10
+        chr1\t280\t300\tgeneB\t20
+        chr1\t400\t420\tgeneB\t20
+
+    2. Promoters: 2kb upstream of each gene according to the strand in column 6:
+
+        $ bedder flank -a genes.bed -g genome.fai -l 2000 -s"
+)]
+pub struct FlankCmdArgs {
+    #[command(flatten)]
+    pub io: SlopIoArgs,
+
+    #[command(flatten)]
+    pub amounts: SlopAmountArgs,
+}
+
+pub fn flank_command(args: FlankCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    write_slop(&args.io, &args.amounts, true)
+}
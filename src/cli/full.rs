@@ -15,5 +15,5 @@ pub struct FullCmdArgs {
 }
 
 pub fn full_command(args: FullCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
-    process_bedder(args.common, Some(args.overlap), Some(args.closest))
+    process_bedder(args.common, Some(args.overlap), Some(args.closest), None)
 }
@@ -0,0 +1,117 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use bedder::bedder_d4::D4Writer;
+use bedder::writer::{SignalFormat, SignalWriter};
+use clap::Parser;
+
+use crate::cli::shared::HELP_TEMPLATE;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Genome coverage: report the depth of every base in the genome as a BedGraph.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+Adjacent bases with the same depth are reported as a single BedGraph interval.
+Zero-depth regions (including chromosomes absent from the input) are reported unless
+--skip-zero is given; this requires a length for every chromosome in the genome file.
+
+EXAMPLE:
+    $ bedder genomecov -a tests/map_b.bed -g tests/hg38.small.fai
+    chr1\t0\t120\t0
+    chr1\t120\t130\t1
+    chr1\t130\t150\t2
+    chr1\t150\t170\t3
+    chr1\t170\t180\t2
+    chr1\t180\t190\t1
+    chr1\t190\t350\t0
+    chr1\t350\t380\t1
+    chr1\t380\t248956422\t0
+    chr2\t0\t242193529\t0
+    ..."
+)]
+pub struct GenomecovCmdArgs {
+    #[arg(
+        help = "input file (must be sorted by the genome file order; - for stdin)",
+        short = 'a'
+    )]
+    pub input_path: PathBuf,
+
+    #[arg(
+        help = "genome file with chromosome order and lengths",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(help = "do not report regions with zero depth", long = "skip-zero")]
+    pub skip_zero: bool,
+
+    #[arg(
+        help = "output file (default: stdout). A .bw/.bigwig suffix writes bigWig and a .d4 suffix writes D4",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+}
+
+pub fn genomecov_command(args: GenomecovCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let chrom_order =
+        bedder::chrom_ordering::parse_genome(std::fs::File::open(&args.genome_file)?)?;
+
+    let (reader, _) = bedder::sniff::open_path(&args.input_path, None)?;
+    let reader = reader.with_chromosome_order(&chrom_order);
+
+    let runs = bedder::coverage::GenomeCoverageIterator::new(
+        reader.into_positioned_iterator(),
+        &chrom_order,
+        !args.skip_zero,
+    )
+    .map_err(|e| format!("{} (genome file: {})", e, args.genome_file.display()))?;
+
+    if args.output_path.extension().is_some_and(|e| e == "d4") {
+        let mut writer = D4Writer::new(&args.output_path, &chrom_order)?;
+        for run in runs {
+            let run = run?;
+            writer.write(&run.chrom, run.start, run.stop, run.depth)?;
+        }
+        writer.finish()?;
+        return Ok(());
+    }
+
+    if SignalFormat::from_path(&args.output_path) == Some(SignalFormat::BigWig) {
+        let mut writer = SignalWriter::new(&args.output_path, SignalFormat::BigWig, &chrom_order)?;
+        for run in runs {
+            let run = run?;
+            writer.write(&run.chrom, run.start, run.stop, run.depth as f64)?;
+        }
+        writer.finish()?;
+        return Ok(());
+    }
+
+    let mut out: Box<dyn Write> = if args.output_path.to_str() == Some("-") {
+        Box::new(std::io::BufWriter::new(std::io::stdout()))
+    } else {
+        Box::new(std::io::BufWriter::new(std::fs::File::create(
+            &args.output_path,
+        )?))
+    };
+
+    for run in runs {
+        let run = run?;
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}",
+            run.chrom, run.start, run.stop, run.depth
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -12,5 +12,5 @@ pub struct IntersectCmdArgs {
 }
 
 pub fn intersect_command(args: IntersectCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
-    process_bedder(args.common, Some(args.overlap), None)
+    process_bedder(args.common, Some(args.overlap), None, None)
 }
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::shared::HELP_TEMPLATE;
+use bedder::windows::{WindowIterator, WindowSpec};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Make windows: split the genome, or a set of regions, into fixed-size or a fixed number of windows.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+Windows can also be used directly as the query of map and intersect with
+--window-size/--window-step or --n-windows, without writing them to a file.
+
+EXAMPLES:
+    1. 50-base windows sliding by 25 bases across each region in tests/map_a.bed:
+
+        $ bedder makewindows -a tests/map_a.bed -g tests/hg38.small.fai -w 50 -s 25
+        chr1\t100\t150
+        chr1\t125\t175
+        chr1\t150\t200
+        chr1\t175\t200
+        chr1\t300\t350
+        chr1\t325\t375
+        chr1\t350\t400
+        chr1\t375\t400
+
+    2. Split each region into 2 windows, naming each by region name and window number:
+
+        $ bedder makewindows -a tests/map_a.bed -g tests/hg38.small.fai -n 2 --name
+        chr1\t100\t150\tgeneA_1
+        chr1\t150\t200\tgeneA_2
+        chr1\t300\t350\tgeneB_1
+        chr1\t350\t400\tgeneB_2
+
+    3. 1 Mb windows across the whole genome (requires chromosome lengths):
+
+        $ bedder makewindows -g tests/hg38.small.fai -w 1000000"
+)]
+pub struct MakewindowsCmdArgs {
+    #[arg(
+        help = "regions to split into windows (must be sorted by the genome file order). Default: every chromosome in the genome file.",
+        short = 'a'
+    )]
+    pub input_path: Option<PathBuf>,
+
+    #[arg(
+        help = "genome file for chromosome ordering (and lengths when -a is not given)",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "window size in bases",
+        short = 'w',
+        long = "size",
+        required_unless_present = "n_windows",
+        conflicts_with = "n_windows"
+    )]
+    pub size: Option<u64>,
+
+    #[arg(
+        help = "start a window every this many bases (default: --size, i.e. tiling windows)",
+        short = 's',
+        long = "step",
+        requires = "size"
+    )]
+    pub step: Option<u64>,
+
+    #[arg(
+        help = "split each region (or chromosome) into this many windows",
+        short = 'n',
+        long = "n-windows"
+    )]
+    pub n_windows: Option<u64>,
+
+    #[arg(
+        help = "name each window by its 1-based number in its region, prefixed by the region name if it has one",
+        long = "name"
+    )]
+    pub name: bool,
+
+    #[arg(
+        help = "output file (default: stdout)",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+}
+
+pub fn makewindows_command(args: MakewindowsCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let spec = match (args.size, args.n_windows) {
+        (Some(size), _) => WindowSpec::Size {
+            size,
+            step: args.step.unwrap_or(size),
+        },
+        (None, Some(n)) => WindowSpec::Count(n),
+        (None, None) => return Err("one of --size or --n-windows is required".into()),
+    };
+
+    let chrom_order =
+        bedder::chrom_ordering::parse_genome(std::fs::File::open(&args.genome_file)?)?;
+
+    let windows = match &args.input_path {
+        Some(path) => {
+            let (reader, _) = bedder::sniff::open_path(path, None)?;
+            let reader = reader.with_chromosome_order(&chrom_order);
+            WindowIterator::from_regions(reader.into_positioned_iterator(), spec)?
+        }
+        None => WindowIterator::from_genome(&chrom_order, spec)
+            .map_err(|e| format!("{} (genome file: {})", e, args.genome_file.display()))?,
+    }
+    .with_names(args.name);
+
+    let mut bed_writer = if args.output_path.to_str() == Some("-") {
+        bedder::bedder_bed::simplebed::BedWriter::from_writer(Box::new(std::io::BufWriter::new(
+            std::io::stdout(),
+        )))?
+    } else {
+        bedder::bedder_bed::simplebed::BedWriter::new(&args.output_path)?
+    };
+
+    for window in windows {
+        match window? {
+            bedder::position::Position::Bed(bed) => bed_writer.write_record(&bed.0)?,
+            _ => unreachable!("windows are always BED positions"),
+        }
+    }
+    bed_writer.flush()?;
+    Ok(())
+}
@@ -5,7 +5,9 @@ use std::path::PathBuf;
 use clap::{Parser, ValueEnum};
 use pyo3::prelude::*;
 
-use crate::cli::shared::HELP_TEMPLATE;
+use crate::cli::shared::{
+    open_query, BInputArgs, IntervalWriter, StrandArgs, WindowArgs, HELP_TEMPLATE,
+};
 
 /// The aggregation operation to apply to B values.
 #[derive(Debug, Clone, PartialEq, Eq, ValueEnum)]
@@ -85,10 +87,11 @@ impl std::str::FromStr for MapOpSpec {
 }
 
 /// Value source spec accepted by CLI for `-c/--column`.
-/// Supports BED columns or python extractors (`py:<name>`).
+/// Supports BED columns, GFF/GTF attributes (`attr:<name>`) or python extractors (`py:<name>`).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MapValueSelector {
     BedColumn(usize),
+    Attribute(String),
     PythonExtractor(String),
 }
 
@@ -102,10 +105,16 @@ impl std::str::FromStr for MapValueSelector {
             }
             return Ok(MapValueSelector::PythonExtractor(name.to_string()));
         }
+        if let Some(name) = raw.strip_prefix("attr:") {
+            if name.trim().is_empty() {
+                return Err("attribute name cannot be empty after 'attr:'".to_string());
+            }
+            return Ok(MapValueSelector::Attribute(name.to_string()));
+        }
 
         let col = raw.parse::<usize>().map_err(|_| {
             format!(
-                "invalid column selector '{}'; expected integer BED column or py:<name>",
+                "invalid column selector '{}'; expected integer BED column, attr:<name> or py:<name>",
                 raw
             )
         })?;
@@ -125,19 +134,21 @@ enum RuntimeAggOp<'py> {
 #[derive(Debug)]
 enum RuntimeValueSelector<'py> {
     BedColumn(usize),
+    Attribute(String),
     PythonExtractor(bedder::py::CompiledMapValuePython<'py>),
 }
 
 /// Extract a value from a B interval for aggregation.
-/// Logs a warning (once per column) when a non-numeric value is encountered.
+/// Logs a warning (once per column or attribute) when a non-numeric value is encountered.
 ///
-/// Accepts the B interval's `Arc` directly. For BedColumn selectors it locks
+/// Accepts the B interval's `Arc` directly. For BedColumn and Attribute selectors it locks
 /// internally; for Python extractors it passes the Arc (cheap clone) so
 /// the Python side can wrap it in a PyPosition without deep-copying.
 fn extract_value(
     b_arc: &std::sync::Arc<parking_lot::Mutex<bedder::position::Position>>,
     selector: &RuntimeValueSelector<'_>,
     warned_columns: &mut HashSet<usize>,
+    warned_attributes: &mut HashSet<String>,
 ) -> Result<Option<f64>, Box<dyn std::error::Error>> {
     match selector {
         RuntimeValueSelector::BedColumn(column) => {
@@ -148,6 +159,17 @@ fn extract_value(
             }
             Ok(val)
         }
+        RuntimeValueSelector::Attribute(name) => {
+            let b_pos = b_arc.try_lock().expect("failed to lock b interval");
+            let val = b_pos
+                .attribute(name)
+                .and_then(|v| v.trim().parse::<f64>().ok());
+            if val.is_none() && !warned_attributes.contains(name.as_str()) {
+                log::warn!("Missing or non-numeric value for attribute {}.", name);
+                warned_attributes.insert(name.clone());
+            }
+            Ok(val)
+        }
         RuntimeValueSelector::PythonExtractor(extractor) => {
             extractor.eval_position(b_arc).map_err(|e| {
                 std::io::Error::other(format!(
@@ -171,7 +193,7 @@ fn is_builtin_count(operation: &RuntimeAggOp<'_>) -> bool {
 ///   - len(c) == 1: replicate c to match len(o)
 ///   - len(o) == 1: replicate o to match len(c)
 ///   - otherwise: error
-fn expand_ops<C: Clone, O: Clone>(
+pub(crate) fn expand_ops<C: Clone, O: Clone>(
     columns: &[C],
     operations: &[O],
 ) -> Result<Vec<(C, O)>, Box<dyn std::error::Error>> {
@@ -264,6 +286,7 @@ fn compile_selector<'py>(
 ) -> Result<RuntimeValueSelector<'py>, Box<dyn std::error::Error>> {
     match selector {
         MapValueSelector::BedColumn(col) => Ok(RuntimeValueSelector::BedColumn(*col)),
+        MapValueSelector::Attribute(name) => Ok(RuntimeValueSelector::Attribute(name.clone())),
         MapValueSelector::PythonExtractor(name) => {
             if let Some(functions_map) = functions_map {
                 let extractor = bedder::py::CompiledMapValuePython::new(name, functions_map)
@@ -347,11 +370,18 @@ fn compile_python_ops<'py>(
     Ok(compiled)
 }
 
-fn file_type_name(file_type: &bedder::sniff::FileType) -> &'static str {
+pub(crate) fn file_type_name(file_type: &bedder::sniff::FileType) -> &'static str {
     match file_type {
         bedder::sniff::FileType::Bed => "BED",
         bedder::sniff::FileType::Vcf => "VCF",
         bedder::sniff::FileType::Bcf => "BCF",
+        bedder::sniff::FileType::Sam => "SAM",
+        bedder::sniff::FileType::Bam => "BAM",
+        bedder::sniff::FileType::Cram => "CRAM",
+        bedder::sniff::FileType::Gff => "GFF/GTF",
+        bedder::sniff::FileType::BigWig => "bigWig",
+        bedder::sniff::FileType::BigBed => "bigBed",
+        bedder::sniff::FileType::D4 => "D4",
     }
 }
 
@@ -364,33 +394,55 @@ fn operation_name(op: &MapOpSpec) -> String {
     }
 }
 
-/// Non-BED B inputs (VCF/BCF) cannot satisfy BED numeric selectors (`-c <int>`).
+/// Non-BED B inputs (VCF/BCF) cannot satisfy BED numeric selectors (`-c <int>`) and only
+/// GFF/GTF inputs have attributes (`-c attr:<name>`).
 /// Without this guard, non-count ops would silently receive no values and emit ".".
 fn validate_b_type_for_ops(
     b_file_type: &bedder::sniff::FileType,
     ops: &[(MapValueSelector, MapOpSpec)],
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if matches!(b_file_type, bedder::sniff::FileType::Bed) {
-        return Ok(());
-    }
+    // bigWig, bigBed and D4 records are read as BED; bigWig values and D4 depths are column 5.
+    let is_bed = matches!(
+        b_file_type,
+        bedder::sniff::FileType::Bed
+            | bedder::sniff::FileType::BigWig
+            | bedder::sniff::FileType::BigBed
+            | bedder::sniff::FileType::D4
+    );
+    let is_gff = matches!(b_file_type, bedder::sniff::FileType::Gff);
 
     for (selector, op) in ops {
-        if let MapValueSelector::BedColumn(column) = selector {
-            // `count` is overlap cardinality only; it does not read selected values, so it is
-            // valid even when `-b` is VCF/BCF and BED numeric selectors are otherwise invalid.
-            if matches!(op, MapOpSpec::Builtin(AggOp::Count)) {
-                continue;
+        // `count` is overlap cardinality only; it does not read selected values, so it is
+        // valid even when `-b` cannot provide the selected values.
+        if matches!(op, MapOpSpec::Builtin(AggOp::Count)) {
+            continue;
+        }
+        match selector {
+            MapValueSelector::BedColumn(column) if !is_bed => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "non-BED -b input ({}) cannot use BED column selector -c {} with -O {}; use -c py:<name> for VCF/BCF value extraction or -c attr:<name> for GFF/GTF",
+                        file_type_name(b_file_type),
+                        column,
+                        operation_name(op)
+                    ),
+                )
+                .into());
             }
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                format!(
-                    "non-BED -b input ({}) cannot use BED column selector -c {} with -O {}; use -c py:<name> for VCF/BCF value extraction",
-                    file_type_name(b_file_type),
-                    column,
-                    operation_name(op)
-                ),
-            )
-            .into());
+            MapValueSelector::Attribute(name) if !is_gff => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "-c attr:{} with -O {} requires a GFF/GTF -b input, not {}",
+                        name,
+                        operation_name(op),
+                        file_type_name(b_file_type)
+                    ),
+                )
+                .into());
+            }
+            _ => {}
         }
     }
 
@@ -401,9 +453,10 @@ fn run_map_with_ops<'a, 'py>(
     ii: bedder::intersection::IntersectionIterator<'a>,
     args: &MapCmdArgs,
     ops: &[(RuntimeValueSelector<'py>, RuntimeAggOp<'py>)],
-    bed_writer: &mut bedder::bedder_bed::simplebed::BedWriter,
+    writer: &mut IntervalWriter,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut warned_columns: HashSet<usize> = HashSet::new();
+    let mut warned_attributes: HashSet<String> = HashSet::new();
 
     for intersection_result in ii {
         let intersection = intersection_result?;
@@ -426,6 +479,9 @@ fn run_map_with_ops<'a, 'py>(
             let mut insertion_order: Vec<String> = Vec::new();
 
             for overlap in &intersection.overlapping {
+                if args.b_input.split && intersection.block_overlap(&overlap.interval) == 0 {
+                    continue;
+                }
                 let b_name = {
                     let b_pos = overlap
                         .interval
@@ -449,9 +505,12 @@ fn run_map_with_ops<'a, 'py>(
                 for (i, (selector, op)) in ops.iter().enumerate() {
                     if is_builtin_count(op) {
                         counts[i] += 1;
-                    } else if let Some(val) =
-                        extract_value(&overlap.interval, selector, &mut warned_columns)?
-                    {
+                    } else if let Some(val) = extract_value(
+                        &overlap.interval,
+                        selector,
+                        &mut warned_columns,
+                        &mut warned_attributes,
+                    )? {
                         value_vecs[i].push(val);
                     }
                 }
@@ -465,7 +524,7 @@ fn run_map_with_ops<'a, 'py>(
                         compute_aggregate_result(op, &[], 0)?,
                     ));
                 }
-                bed_writer.write_record(&record)?;
+                writer.write(&record, ".")?;
             } else {
                 for b_name in &insertion_order {
                     let (value_vecs, counts) = groups.get(b_name).expect("group key exists");
@@ -475,7 +534,7 @@ fn run_map_with_ops<'a, 'py>(
                         let agg_result = compute_aggregate_result(op, &value_vecs[i], counts[i])?;
                         record.push_field(bedder::bedder_bed::BedValue::String(agg_result));
                     }
-                    bed_writer.write_record(&record)?;
+                    writer.write(&record, ".")?;
                 }
             }
         } else {
@@ -484,6 +543,9 @@ fn run_map_with_ops<'a, 'py>(
             let mut counts: Vec<usize> = vec![0; ops.len()];
 
             for overlap in &intersection.overlapping {
+                if args.b_input.split && intersection.block_overlap(&overlap.interval) == 0 {
+                    continue;
+                }
                 if args.name_match {
                     let b_pos = overlap
                         .interval
@@ -498,24 +560,28 @@ fn run_map_with_ops<'a, 'py>(
                 for (i, (selector, op)) in ops.iter().enumerate() {
                     if is_builtin_count(op) {
                         counts[i] += 1;
-                    } else if let Some(val) =
-                        extract_value(&overlap.interval, selector, &mut warned_columns)?
-                    {
+                    } else if let Some(val) = extract_value(
+                        &overlap.interval,
+                        selector,
+                        &mut warned_columns,
+                        &mut warned_attributes,
+                    )? {
                         value_vecs[i].push(val);
                     }
                 }
             }
 
             let mut record = bed_record.clone();
+            // BedGraph/bigWig output has a single operation so its result is the value.
+            let mut value = String::from(".");
             for (i, (_, op)) in ops.iter().enumerate() {
-                let agg_result = compute_aggregate_result(op, &value_vecs[i], counts[i])?;
-                record.push_field(bedder::bedder_bed::BedValue::String(agg_result));
+                value = compute_aggregate_result(op, &value_vecs[i], counts[i])?;
+                record.push_field(bedder::bedder_bed::BedValue::String(value.clone()));
             }
-            bed_writer.write_record(&record)?;
+            writer.write(&record, &value)?;
         }
     }
 
-    bed_writer.flush()?;
     Ok(())
 }
 
@@ -592,11 +658,37 @@ EXAMPLES:
         # tests/map_ops.py defines: def bedder_vcf_dp(iv) -> float
         $ bedder map -a tests/map_a.bed -b tests/map_b.vcf -g tests/hg38.small.fai --python tests/map_ops.py -c py:vcf_dp -O sum,mean,count
         chr1\t100\t200\tgeneA\t10\t15\t5\t3
-        chr1\t300\t400\tgeneB\t20\t4\t4\t1"
+        chr1\t300\t400\tgeneB\t20\t4\t4\t1
+
+    8. Summarize B in 50-base windows across each A interval (no temp file needed):
+
+        $ bedder map -a tests/map_a.bed -b tests/map_b.bed -g tests/hg38.small.fai --window-size 50
+        chr1\t100\t150\t12
+        chr1\t150\t200\t15
+        chr1\t300\t350\t.
+        chr1\t350\t400\t4
+
+        Without -a, windows are generated across every chromosome in the genome file.
+
+    9. Count reads (SAM/BAM/CRAM; unmapped reads are ignored) per region:
+
+        $ bedder map -a targets.bed -b reads.bam -g genome.fai -O count
+
+    10. Sum a numeric GFF/GTF attribute of the overlapping features:
+
+        $ bedder map -a targets.bed -b genes.gtf -g genome.fai -c attr:level -O sum,count
+
+    11. Mean bigWig signal per region (bigWig values are column 5, the default):
+
+        $ bedder map -a targets.bed -b signal.bw -g genome.fai -O mean,max"
 )]
 pub struct MapCmdArgs {
-    #[arg(help = "input A file (query)", short = 'a')]
-    pub query_path: PathBuf,
+    #[arg(
+        help = "input A file (query, - for stdin). With --window-size or --n-windows, the regions to split into windows (default: the whole genome)",
+        short = 'a',
+        required_unless_present_any = ["window_size", "n_windows"]
+    )]
+    pub query_path: Option<PathBuf>,
 
     #[arg(help = "input B file (database)", short = 'b')]
     pub other_path: PathBuf,
@@ -610,7 +702,7 @@ pub struct MapCmdArgs {
     pub genome_file: PathBuf,
 
     #[arg(
-        help = "Value selector(s) for mapped B intervals. Use 1-indexed BED column(s) (default: 5 = score) or py:<name> extractors. Comma-separated for multiple.",
+        help = "Value selector(s) for mapped B intervals. Use 1-indexed BED column(s) (default: 5 = score), attr:<name> for GFF/GTF attributes or py:<name> extractors. Comma-separated for multiple.",
         short = 'c',
         long = "column",
         default_value = "5",
@@ -628,7 +720,7 @@ pub struct MapCmdArgs {
     pub operations: Vec<MapOpSpec>,
 
     #[arg(
-        help = "output file (default: stdout)",
+        help = "output file (default: stdout). A .bedgraph/.bdg/.bg or .bw/.bigwig suffix writes the single -O result as BedGraph or bigWig",
         short = 'o',
         long = "output",
         default_value = "-"
@@ -654,6 +746,15 @@ pub struct MapCmdArgs {
         help = "python file with bedder_<name> functions used by py:<name> operations"
     )]
     pub python_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub strand: StrandArgs,
+
+    #[command(flatten)]
+    pub windows: WindowArgs,
+
+    #[command(flatten)]
+    pub b_input: BInputArgs,
 }
 
 pub fn map_command(args: MapCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
@@ -666,21 +767,16 @@ pub fn map_command(args: MapCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
     let chrom_order =
         bedder::chrom_ordering::parse_genome(std::fs::File::open(&args.genome_file)?)?;
 
-    let a_file = std::io::BufReader::new(std::fs::File::open(&args.query_path)?);
-    let (a_reader, a_file_type) = bedder::sniff::open(a_file, &args.query_path)?;
+    let (a_iter, a_file_type, _) =
+        open_query(args.query_path.as_ref(), &args.windows, &chrom_order)?;
 
     if !matches!(a_file_type, bedder::sniff::FileType::Bed) {
         return Err("map currently only supports BED files for -a (output is BED-based)".into());
     }
 
-    let a_iter = a_reader.into_positioned_iterator();
-
-    let b_file = std::io::BufReader::new(std::fs::File::open(&args.other_path)?);
-    let (b_reader, b_file_type) = bedder::sniff::open(b_file, &args.other_path)?;
+    let (b_iter, b_file_type) = args.b_input.open(&args.other_path, &chrom_order)?;
     validate_b_type_for_ops(&b_file_type, &ops)?;
 
-    let b_iter = b_reader.into_positioned_iterator();
-
     let ii = bedder::intersection::IntersectionIterator::new(
         a_iter,
         vec![b_iter],
@@ -688,29 +784,31 @@ pub fn map_command(args: MapCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
         -1,    // max_distance: not used
         -1,    // n_closest: not used
         false, // can_skip_ahead: need every A interval reported
-    )?;
+    )?
+    .with_strand_mode(args.strand.strand_mode());
 
-    let mut bed_writer = if args.output_path.to_str() == Some("-") {
-        bedder::bedder_bed::simplebed::BedWriter::from_writer(Box::new(std::io::BufWriter::new(
-            std::io::stdout(),
-        )))?
-    } else {
-        bedder::bedder_bed::simplebed::BedWriter::new(&args.output_path)?
-    };
+    let mut writer = IntervalWriter::new(&args.output_path, &chrom_order)?;
+    if writer.is_signal() && (ops.len() != 1 || args.group_by_b) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "BedGraph/bigWig output requires a single -O operation and cannot be used with -G",
+        )
+        .into());
+    }
 
     if has_python_specs {
         Python::initialize();
         Python::attach(|py| -> Result<(), Box<dyn std::error::Error>> {
             // Compile Python callables once and reuse per row to avoid per-record Python lookup cost.
             let compiled_ops = compile_python_ops(py, &ops, args.python_file.as_ref())?;
-            run_map_with_ops(ii, &args, &compiled_ops, &mut bed_writer)
+            run_map_with_ops(ii, &args, &compiled_ops, &mut writer)
         })?;
     } else {
         let compiled_ops = compile_builtin_ops(&ops)?;
-        run_map_with_ops(ii, &args, &compiled_ops, &mut bed_writer)?;
+        run_map_with_ops(ii, &args, &compiled_ops, &mut writer)?;
     }
 
-    Ok(())
+    writer.finish()
 }
 
 #[cfg(test)]
@@ -809,8 +907,13 @@ mod tests {
             "py:dp".parse::<MapValueSelector>().unwrap(),
             MapValueSelector::PythonExtractor("dp".to_string())
         );
+        assert_eq!(
+            "attr:gene_name".parse::<MapValueSelector>().unwrap(),
+            MapValueSelector::Attribute("gene_name".to_string())
+        );
         assert!("0".parse::<MapValueSelector>().is_err());
         assert!("py:".parse::<MapValueSelector>().is_err());
+        assert!("attr:".parse::<MapValueSelector>().is_err());
         assert!("nope".parse::<MapValueSelector>().is_err());
     }
 
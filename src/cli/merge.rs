@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::map::{expand_ops, file_type_name, AggOp};
+use crate::cli::shared::HELP_TEMPLATE;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Merge overlapping or nearby intervals from a sorted file.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+EXAMPLES:
+    Given tests/map_b.bed:
+        chr1\t120\t180\tgeneA\t5
+        chr1\t130\t170\tgeneB\t7
+        chr1\t150\t190\tgeneA\t3
+        chr1\t350\t380\tgeneB\t4
+
+    1. Merge overlapping and book-ended intervals:
+
+        $ bedder merge -a tests/map_b.bed -g tests/hg38.small.fai
+        chr1\t120\t190
+        chr1\t350\t380
+
+    2. Merge intervals within 200 bases and summarize the score column:
+
+        $ bedder merge -a tests/map_b.bed -g tests/hg38.small.fai -d 200 -c 5 -O sum,count
+        chr1\t120\t380\t19\t4"
+)]
+pub struct MergeCmdArgs {
+    #[arg(
+        help = "input file (must be sorted by the genome file order; - for stdin)",
+        short = 'a'
+    )]
+    pub input_path: PathBuf,
+
+    #[arg(
+        help = "genome file for chromosome ordering",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "maximum distance between intervals to merge. 0 merges overlapping and book-ended intervals. A negative value requires at least that many bases of overlap.",
+        short = 'd',
+        long = "max-distance",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    pub max_distance: i64,
+
+    #[arg(
+        help = "only merge intervals on the same strand (BED column 6). The strand is reported after the end column.",
+        short = 's',
+        long = "same-strand"
+    )]
+    pub same_strand: bool,
+
+    #[arg(
+        help = "1-indexed BED column(s) to summarize for each merged interval. Comma-separated for multiple.",
+        short = 'c',
+        long = "column",
+        value_delimiter = ','
+    )]
+    pub columns: Vec<usize>,
+
+    #[arg(
+        help = "aggregation operation(s) applied to --column: count,sum,mean,min,max,median. Comma-separated for multiple.",
+        short = 'O',
+        long = "operation",
+        default_value = "sum",
+        value_delimiter = ','
+    )]
+    pub operations: Vec<AggOp>,
+
+    #[arg(
+        help = "output file (default: stdout)",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+}
+
+pub fn merge_command(args: MergeCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let ops = if args.columns.is_empty() {
+        vec![]
+    } else {
+        expand_ops(&args.columns, &args.operations)?
+    };
+    if let Some((column, _)) = ops.iter().find(|(c, _)| *c == 0) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "column index must be >= 1 (columns are 1-indexed); got {}",
+                column
+            ),
+        )
+        .into());
+    }
+
+    let chrom_order =
+        bedder::chrom_ordering::parse_genome(std::fs::File::open(&args.genome_file)?)?;
+
+    let (reader, file_type) = bedder::sniff::open_path(&args.input_path, None)?;
+    let reader = reader.with_chromosome_order(&chrom_order);
+
+    if !matches!(
+        file_type,
+        bedder::sniff::FileType::Bed
+            | bedder::sniff::FileType::BigWig
+            | bedder::sniff::FileType::BigBed
+            | bedder::sniff::FileType::D4
+    ) && ops.iter().any(|(_, op)| *op != AggOp::Count)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "non-BED input ({}) can only be summarized with -O count",
+                file_type_name(&file_type)
+            ),
+        )
+        .into());
+    }
+
+    let merged = bedder::merge::MergeIterator::new(reader.into_positioned_iterator(), &chrom_order)
+        .with_max_distance(args.max_distance)
+        .with_strand(args.same_strand);
+
+    let mut out: Box<dyn Write> = if args.output_path.to_str() == Some("-") {
+        Box::new(std::io::BufWriter::new(std::io::stdout()))
+    } else {
+        Box::new(std::io::BufWriter::new(std::fs::File::create(
+            &args.output_path,
+        )?))
+    };
+
+    let mut warned_columns: HashSet<usize> = HashSet::new();
+    let mut values: Vec<f64> = Vec::new();
+    for m in merged {
+        let m = m?;
+        write!(out, "{}\t{}\t{}", m.chrom, m.start, m.stop)?;
+        if args.same_strand {
+            write!(out, "\t{}", m.strand)?;
+        }
+        for (column, op) in &ops {
+            if *op == AggOp::Count {
+                write!(out, "\t{}", m.members.len())?;
+                continue;
+            }
+            values.clear();
+            for member in &m.members {
+                match member.column_as_f64(*column) {
+                    Some(v) => values.push(v),
+                    None => {
+                        if warned_columns.insert(*column) {
+                            log::warn!("Non-numeric value in column {}.", column);
+                        }
+                    }
+                }
+            }
+            write!(out, "\t{}", op.compute(&values))?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -1,5 +1,15 @@
 pub mod closest;
+pub mod complement;
+pub mod coverage;
+pub mod flank;
 pub mod full;
+pub mod genomecov;
 pub mod intersect;
+pub mod makewindows;
 pub mod map;
+pub mod merge;
+pub mod pairtobed;
+pub mod pairtopair;
 pub mod shared;
+pub mod slop;
+pub mod subtract;
@@ -0,0 +1,137 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::shared::{BInputArgs, StrandArgs, HELP_TEMPLATE};
+use bedder::bedder_pair::{pair_overlaps, PairOverlapType};
+use bedder::sniff::Input;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Report overlaps between the ends of BEDPE or PAF pairs and B intervals.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+OUTPUT:
+    Each reported pair is followed by the chrom, start and end of an overlapping B interval, once
+    for each end that B overlaps. Pairs must be sorted by their first end (chrom1, start1) and are
+    reported in input order. The second ends are sorted in temporary files. With --type neither or
+    notboth, the pairs are reported without B intervals.
+
+EXAMPLES:
+    Given tests/pairs_a.bedpe:
+        chr1\t100\t200\tchr2\t1000\t1100\tp1\t10\t+\t-
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+
+        chr2\t50\t60\tchr1\t600\t700\tp3\t30\t-\t+
+
+    1. Pairs with both ends overlapping tests/map_b.bed:
+
+        $ bedder pairtobed -a tests/pairs_a.bedpe -b tests/map_b.bed -g tests/hg38.small.fai --type both
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+\tchr1\t120\t180
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+\tchr1\t130\t170
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+\tchr1\t150\t190
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+\tchr1\t350\t380
+
+    2. Pairs with neither end overlapping:
+
+        $ bedder pairtobed -a tests/pairs_a.bedpe -b tests/map_b.bed -g tests/hg38.small.fai --type neither
+        chr2\t50\t60\tchr1\t600\t700\tp3\t30\t-\t+"
+)]
+pub struct PairtobedCmdArgs {
+    #[arg(
+        help = "input BEDPE or PAF file of pairs (sorted by the first end)",
+        short = 'a'
+    )]
+    pub query_path: PathBuf,
+
+    #[arg(
+        help = "input B file(s) (must be sorted by the genome file order)",
+        short = 'b',
+        required = true
+    )]
+    pub other_paths: Vec<PathBuf>,
+
+    #[arg(
+        help = "genome file for chromosome ordering",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "output file (default: stdout)",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+
+    #[arg(
+        help = "which ends of a pair must overlap B for the pair to be reported",
+        long = "type",
+        value_enum,
+        default_value = "either"
+    )]
+    pub overlap_type: PairOverlapType,
+
+    #[command(flatten)]
+    pub strand: StrandArgs,
+
+    #[command(flatten)]
+    pub b_input: BInputArgs,
+}
+
+pub fn pairtobed_command(args: PairtobedCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let chrom_order = bedder::chrom_ordering::parse_genome(File::open(&args.genome_file)?)?;
+
+    let mut out: Box<dyn Write> = if args.output_path.to_str() == Some("-") {
+        Box::new(std::io::BufWriter::new(std::io::stdout()))
+    } else {
+        Box::new(std::io::BufWriter::new(File::create(&args.output_path)?))
+    };
+
+    // B files are read once for each end.
+    let b_iters = || {
+        args.other_paths
+            .iter()
+            .map(|p| {
+                args.b_input
+                    .open(p, &chrom_order)
+                    .map(|(b_iter, _)| b_iter)
+                    .map_err(|e| std::io::Error::other(e.to_string()))
+            })
+            .collect::<std::io::Result<Vec<_>>>()
+    };
+    pair_overlaps(
+        Input::open(&args.query_path)?,
+        b_iters,
+        &chrom_order,
+        args.strand.strand_mode(),
+        |pair, first, second| {
+            let matched = args
+                .overlap_type
+                .matches(!first.is_empty(), !second.is_empty());
+            if args.overlap_type.is_negated() {
+                if !matched {
+                    writeln!(out, "{}", pair.line())?;
+                }
+                return Ok(());
+            }
+            if matched {
+                for b in first.iter().chain(second.iter()) {
+                    writeln!(out, "{}\t{}\t{}\t{}", pair.line(), b.chrom, b.start, b.stop)?;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::shared::{StrandArgs, HELP_TEMPLATE};
+use bedder::bedder_pair::{
+    pair_overlaps, paired_ends, PairEndIterator, PairOverlapType, PairReader, PairSide,
+};
+use bedder::position::PositionedIterator;
+use bedder::sniff::Input;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Report overlaps between two files of BEDPE or PAF pairs.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+OUTPUT:
+    Each reported A pair is followed by the B pair it overlaps. Both ends overlap when the ends of A
+    overlap different ends of B, in either orientation. Both files must be sorted by the first end
+    (chrom1, start1); A pairs are reported in input order. The second ends are sorted in temporary
+    files. With --type neither or notboth, the A pairs are reported without B pairs.
+
+EXAMPLES:
+    Given tests/pairs_a.bedpe:
+        chr1\t100\t200\tchr2\t1000\t1100\tp1\t10\t+\t-
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+
+        chr2\t50\t60\tchr1\t600\t700\tp3\t30\t-\t+
+    and tests/pairs_b.bedpe:
+        chr1\t150\t155\tchr1\t365\t366\tq1\t1\t+\t+
+        chr2\t1050\t1060\tchr1\t100\t110\tq2\t2\t-\t+
+
+    1. Pairs with both ends overlapping a B pair:
+
+        $ bedder pairtopair -a tests/pairs_a.bedpe -b tests/pairs_b.bedpe -g tests/hg38.small.fai
+        chr1\t100\t200\tchr2\t1000\t1100\tp1\t10\t+\t-\tchr2\t1050\t1060\tchr1\t100\t110\tq2\t2\t-\t+
+        chr1\t140\t160\tchr1\t360\t370\tp2\t20\t+\t+\tchr1\t150\t155\tchr1\t365\t366\tq1\t1\t+\t+
+
+    2. Pairs with neither end overlapping a B pair:
+
+        $ bedder pairtopair -a tests/pairs_a.bedpe -b tests/pairs_b.bedpe -g tests/hg38.small.fai --type neither
+        chr2\t50\t60\tchr1\t600\t700\tp3\t30\t-\t+"
+)]
+pub struct PairtopairCmdArgs {
+    #[arg(
+        help = "input BEDPE or PAF file of A pairs (sorted by the first end)",
+        short = 'a'
+    )]
+    pub query_path: PathBuf,
+
+    #[arg(
+        help = "input BEDPE or PAF file of B pairs (sorted by the first end)",
+        short = 'b'
+    )]
+    pub other_path: PathBuf,
+
+    #[arg(
+        help = "genome file for chromosome ordering",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "output file (default: stdout)",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+
+    #[arg(
+        help = "which ends of an A pair must overlap a B pair for the pairs to be reported",
+        long = "type",
+        value_enum,
+        default_value = "both"
+    )]
+    pub overlap_type: PairOverlapType,
+
+    #[command(flatten)]
+    pub strand: StrandArgs,
+}
+
+pub fn pairtopair_command(args: PairtopairCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let chrom_order = bedder::chrom_ordering::parse_genome(File::open(&args.genome_file)?)?;
+
+    let mut out: Box<dyn Write> = if args.output_path.to_str() == Some("-") {
+        Box::new(std::io::BufWriter::new(std::io::stdout()))
+    } else {
+        Box::new(std::io::BufWriter::new(File::create(&args.output_path)?))
+    };
+
+    // both ends of the B pairs; the B file is read for each end of A.
+    let b_iters = || -> std::io::Result<Vec<Box<dyn PositionedIterator>>> {
+        Ok(vec![
+            Box::new(PairReader::new(
+                Input::open(&args.other_path)?,
+                PairSide::First,
+                &chrom_order,
+            )),
+            Box::new(PairEndIterator::new(
+                Input::open(&args.other_path)?,
+                PairSide::Second,
+                &chrom_order,
+            )?),
+        ])
+    };
+    pair_overlaps(
+        Input::open(&args.query_path)?,
+        b_iters,
+        &chrom_order,
+        args.strand.strand_mode(),
+        |pair, first, second| {
+            // the B pairs (and their lines) that each end overlaps with [a_side][b_side].
+            let mut hits: BTreeMap<usize, ([[bool; 2]; 2], &str)> = BTreeMap::new();
+            for (a_side, end_hits) in [first, second].into_iter().enumerate() {
+                for hit in end_hits {
+                    let Some((b, b_side, line)) = &hit.pair else {
+                        unreachable!("B pairs are reported as pair ends");
+                    };
+                    let b_side = match b_side {
+                        PairSide::First => 0,
+                        PairSide::Second => 1,
+                    };
+                    let entry = hits.entry(*b).or_insert(([[false; 2]; 2], line.as_str()));
+                    entry.0[a_side][b_side] = true;
+                }
+            }
+            let matched: Vec<&str> = hits
+                .values()
+                .filter(|([first, second], _)| {
+                    let (first, second) = paired_ends(*first, *second);
+                    args.overlap_type.matches(first, second)
+                })
+                .map(|(_, line)| *line)
+                .collect();
+            if args.overlap_type.is_negated() {
+                if matched.is_empty() {
+                    writeln!(out, "{}", pair.line())?;
+                }
+                return Ok(());
+            }
+            for b in matched {
+                writeln!(out, "{}\t{}", pair.line(), b)?;
+            }
+            Ok(())
+        },
+    )?;
+    out.flush()?;
+    Ok(())
+}
@@ -1,13 +1,22 @@
+use bedder::bedder_bam::AlignmentFilter;
+use bedder::bedder_gff::{AttributeCondition, GffFilter};
+use bedder::chrom_ordering::Chromosome;
 use bedder::column::Column;
 use bedder::hts_format::Format;
-use bedder::report_options::{IntersectionMode, IntersectionPart, OverlapAmount, ReportOptions};
-use bedder::writer::{InputHeader, Writer};
-use clap::Parser;
+use bedder::intersection::QueryWindow;
+use bedder::position::PositionedIterator;
+use bedder::report_options::{
+    ClosestDirection, DistanceMode, IntersectionMode, IntersectionPart, OverlapAmount,
+    ReportOptions, StrandMode, TieMode,
+};
+use bedder::windows::WindowSpec;
+use bedder::writer::{IndexType, InputHeader, OutputFormat, SignalFormat, SignalWriter, Writer};
+use clap::{Parser, ValueEnum};
+use hashbrown::HashMap;
 use pyo3::prelude::*;
 use std::ffi::CString;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 pub const HELP_TEMPLATE: &str =
@@ -15,8 +24,12 @@ pub const HELP_TEMPLATE: &str =
 
 #[derive(Parser, Debug)]
 pub struct CommonArgs {
-    #[arg(help = "input file", short = 'a')]
-    pub query_path: PathBuf,
+    #[arg(
+        help = "input file (- for stdin). With --window-size or --n-windows, the regions to split into windows (default: the whole genome)",
+        short = 'a',
+        required_unless_present_any = ["window_size", "n_windows"]
+    )]
+    pub query_path: Option<PathBuf>,
 
     #[arg(help = "other file", short = 'b', required = true)]
     pub other_paths: Vec<PathBuf>,
@@ -30,20 +43,35 @@ pub struct CommonArgs {
     pub genome_file: PathBuf,
 
     #[arg(
-        help = "columns to output (format: name:type:description:number:value_parser)",
+        help = "columns to output (format: name:type:description:number:value_parser). Prefix with FORMAT/ to write per-sample VCF FORMAT fields",
         short = 'c',
         long = "columns"
     )]
     pub columns: Vec<String>,
 
     #[arg(
-        help = "output file (default: stdout)",
+        help = "output file (default: stdout). A .gz or .bgz suffix writes bgzipped output. A .vcf or .bcf suffix writes VCF/BCF, with a header from the genome file for BED queries",
         short = 'o',
         long = "output",
         default_value = "-"
     )]
     pub output_path: PathBuf,
 
+    #[arg(
+        help = "build a tabix (tbi) or CSI index of the bgzipped output when it is complete",
+        long = "index",
+        value_enum
+    )]
+    pub index: Option<IndexType>,
+
+    #[arg(
+        help = "write the query format (native), a table with a header of named fields (tsv, csv or jsonl) or a typed columnar file (parquet or arrow)",
+        long = "output-format",
+        value_enum,
+        default_value = "native"
+    )]
+    pub output_format: OutputFormat,
+
     #[arg(
         help = "python file with functions to be used in columns",
         long = "python"
@@ -63,6 +91,440 @@ pub struct CommonArgs {
         help = "don't use indexed query"
     )]
     pub dont_use_indexes: bool,
+
+    #[command(flatten)]
+    pub strand: StrandArgs,
+
+    #[command(flatten)]
+    pub windows: WindowArgs,
+
+    #[command(flatten)]
+    pub slop: QuerySlopArgs,
+
+    #[command(flatten)]
+    pub b_input: BInputArgs,
+}
+
+// options for opening -b files: the CRAM reference, alignment and GFF/GTF filters, and
+// whether BED12 records and spliced alignments are split into their blocks.
+#[derive(Parser, Debug)]
+pub struct BInputArgs {
+    #[arg(
+        help = "reference FASTA used to decode CRAM -b files (default: from the REF_PATH environment variable)",
+        long = "reference"
+    )]
+    pub reference: Option<PathBuf>,
+
+    #[arg(
+        help = "use the blocks of BED12 records and the aligned blocks of spliced alignments (split on CIGAR N) rather than their whole span (bedtools -split)",
+        long = "split"
+    )]
+    pub split: bool,
+
+    #[command(flatten)]
+    pub alignments: AlignmentArgs,
+
+    #[command(flatten)]
+    pub gff: GffArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct AlignmentArgs {
+    #[arg(
+        help = "only use alignments (SAM/BAM/CRAM -b files) with at least this mapping quality",
+        long = "min-mapq",
+        default_value_t = 0
+    )]
+    pub min_mapq: u8,
+
+    #[arg(
+        help = "only use alignments with all of these SAM flag bits set (decimal or 0x hex)",
+        long = "require-flags",
+        value_parser = parse_sam_flags,
+        default_value = "0"
+    )]
+    pub require_flags: u16,
+
+    #[arg(
+        help = "don't use alignments with any of these SAM flag bits set (decimal or 0x hex)",
+        long = "exclude-flags",
+        value_parser = parse_sam_flags,
+        default_value = "0"
+    )]
+    pub exclude_flags: u16,
+
+    #[arg(
+        help = "don't use duplicate alignments (flag 0x400)",
+        long = "skip-duplicates"
+    )]
+    pub skip_duplicates: bool,
+
+    #[arg(
+        help = "don't use secondary alignments (flag 0x100)",
+        long = "skip-secondary"
+    )]
+    pub skip_secondary: bool,
+
+    #[arg(
+        help = "don't use supplementary alignments (flag 0x800)",
+        long = "skip-supplementary"
+    )]
+    pub skip_supplementary: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct GffArgs {
+    #[arg(
+        help = "only use GFF/GTF -b features of these types (column 3, e.g. exon,gene). Comma-separated for multiple.",
+        long = "feature-type",
+        value_delimiter = ','
+    )]
+    pub feature_types: Vec<String>,
+
+    #[arg(
+        help = "only use GFF/GTF -b features whose attribute equals a value (key=value) or matches a regular expression (key~regex). Repeat to require several.",
+        long = "attribute"
+    )]
+    pub attributes: Vec<AttributeCondition>,
+}
+
+impl GffArgs {
+    fn filter(&self) -> GffFilter {
+        GffFilter {
+            feature_types: self
+                .feature_types
+                .iter()
+                .map(|t| t.as_str().into())
+                .collect(),
+            attributes: self.attributes.clone(),
+        }
+    }
+}
+
+fn parse_sam_flags(s: &str) -> Result<u16, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
+impl AlignmentArgs {
+    fn filter(&self) -> AlignmentFilter {
+        let mut exclude_flags = self.exclude_flags;
+        if self.skip_duplicates {
+            exclude_flags |= AlignmentFilter::DUPLICATE;
+        }
+        if self.skip_secondary {
+            exclude_flags |= AlignmentFilter::SECONDARY;
+        }
+        if self.skip_supplementary {
+            exclude_flags |= AlignmentFilter::SUPPLEMENTARY;
+        }
+        AlignmentFilter {
+            min_mapq: self.min_mapq,
+            require_flags: self.require_flags,
+            exclude_flags,
+        }
+    }
+}
+
+impl BInputArgs {
+    /// Open a -b file, which may be SAM, BAM, CRAM, GFF/GTF, bigWig, bigBed or D4 in addition
+    /// to BED and VCF/BCF. Alignments are filtered (and split) and GFF/GTF features are filtered
+    /// according to these arguments.
+    pub(crate) fn open(
+        &self,
+        path: &Path,
+        chrom_order: &HashMap<bedder::string::String, Chromosome>,
+    ) -> Result<(Box<dyn PositionedIterator>, bedder::sniff::FileType), Box<dyn std::error::Error>>
+    {
+        let (reader, file_type) = bedder::sniff::open_path(path, self.reference.as_deref())?;
+        let reader = reader.with_chromosome_order(chrom_order);
+        let iter: Box<dyn PositionedIterator> = match reader {
+            bedder::sniff::BedderReader::BedderBam(bam) => Box::new(
+                bam.with_filter(self.alignments.filter())
+                    .with_split(self.split),
+            ),
+            bedder::sniff::BedderReader::BedderGff(gff) => {
+                Box::new(gff.with_filter(self.gff.filter()))
+            }
+            reader => reader.into_positioned_iterator(),
+        };
+        Ok((iter, file_type))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct StrandArgs {
+    #[arg(
+        help = "only report B intervals on the same strand as A (BED column 6). Intervals with unknown strand are not reported.",
+        short = 's',
+        long = "same-strand",
+        conflicts_with = "opposite_strand"
+    )]
+    pub same_strand: bool,
+
+    #[arg(
+        help = "only report B intervals on the opposite strand from A (BED column 6). Intervals with unknown strand are not reported.",
+        short = 'S',
+        long = "opposite-strand"
+    )]
+    pub opposite_strand: bool,
+}
+
+impl StrandArgs {
+    pub fn strand_mode(&self) -> StrandMode {
+        if self.same_strand {
+            StrandMode::Same
+        } else if self.opposite_strand {
+            StrandMode::Opposite
+        } else {
+            StrandMode::Ignore
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct WindowArgs {
+    #[arg(
+        help = "use windows of this many bases as the query (A) intervals instead of reading them from -a",
+        long = "window-size",
+        conflicts_with = "n_windows"
+    )]
+    pub window_size: Option<u64>,
+
+    #[arg(
+        help = "start a window every this many bases (default: --window-size, i.e. tiling windows)",
+        long = "window-step",
+        requires = "window_size"
+    )]
+    pub window_step: Option<u64>,
+
+    #[arg(
+        help = "use this many windows per region (or chromosome) as the query (A) intervals",
+        long = "n-windows"
+    )]
+    pub n_windows: Option<u64>,
+}
+
+impl WindowArgs {
+    pub fn spec(&self) -> Option<WindowSpec> {
+        match (self.window_size, self.n_windows) {
+            (Some(size), _) => Some(WindowSpec::Size {
+                size,
+                step: self.window_step.unwrap_or(size),
+            }),
+            (None, Some(n)) => Some(WindowSpec::Count(n)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct QuerySlopArgs {
+    #[arg(
+        help = "extend each A interval by this many bases (or fraction of its length, e.g. 0.5 or 50%) on both sides before intersecting",
+        long = "a-slop"
+    )]
+    pub a_slop: Option<OverlapAmount>,
+
+    #[arg(
+        help = "extend each A interval by this amount before its start (overrides --a-slop)",
+        long = "a-left"
+    )]
+    pub a_left: Option<OverlapAmount>,
+
+    #[arg(
+        help = "extend each A interval by this amount after its end (overrides --a-slop)",
+        long = "a-right"
+    )]
+    pub a_right: Option<OverlapAmount>,
+
+    #[arg(
+        help = "use the flanks added by --a-slop/--a-left/--a-right as the query instead of the extended A intervals",
+        long = "a-flank"
+    )]
+    pub a_flank: bool,
+
+    #[arg(
+        help = "--a-left and --a-right are upstream and downstream of A according to its strand (BED column 6)",
+        long = "a-stranded"
+    )]
+    pub a_stranded: bool,
+}
+
+impl QuerySlopArgs {
+    /// Wrap the query intervals in a SlopIterator if any of the options are set.
+    pub fn apply(
+        &self,
+        query: Box<dyn PositionedIterator>,
+        chrom_order: &HashMap<bedder::string::String, Chromosome>,
+    ) -> Result<Box<dyn PositionedIterator>, Box<dyn std::error::Error>> {
+        if self.a_slop.is_none() && self.a_left.is_none() && self.a_right.is_none() {
+            if self.a_flank || self.a_stranded {
+                return Err(
+                    "--a-flank and --a-stranded require --a-slop, --a-left or --a-right".into(),
+                );
+            }
+            return Ok(query);
+        }
+        let both = self.a_slop.clone().unwrap_or(OverlapAmount::Bases(0));
+        let left = self.a_left.clone().unwrap_or_else(|| both.clone());
+        let right = self.a_right.clone().unwrap_or(both);
+        Ok(Box::new(
+            bedder::slop::SlopIterator::new(query, chrom_order, left, right)
+                .with_strand(self.a_stranded)
+                .with_flank(self.a_flank),
+        ))
+    }
+}
+
+/// Open the query (A) intervals. When windows are requested they are generated from
+/// the -a regions, or from the genome when there is no -a, and reported as BED.
+pub(crate) fn open_query(
+    query_path: Option<&PathBuf>,
+    windows: &WindowArgs,
+    chrom_order: &HashMap<bedder::string::String, Chromosome>,
+) -> Result<
+    (
+        Box<dyn PositionedIterator>,
+        bedder::sniff::FileType,
+        InputHeader,
+    ),
+    Box<dyn std::error::Error>,
+> {
+    let Some(query_path) = query_path else {
+        let spec = windows
+            .spec()
+            .ok_or("-a is required unless --window-size or --n-windows is given")?;
+        let iter = bedder::windows::WindowIterator::from_genome(chrom_order, spec)?;
+        return Ok((
+            Box::new(iter),
+            bedder::sniff::FileType::Bed,
+            InputHeader::None,
+        ));
+    };
+
+    let (a_bed_reader_obj, query_file_type) = bedder::sniff::open_path(query_path, None)?;
+    let a_bed_reader_obj = a_bed_reader_obj.with_chromosome_order(chrom_order);
+
+    if let Some(spec) = windows.spec() {
+        let iter = bedder::windows::WindowIterator::from_regions(
+            a_bed_reader_obj.into_positioned_iterator(),
+            spec,
+        )?;
+        return Ok((
+            Box::new(iter),
+            bedder::sniff::FileType::Bed,
+            InputHeader::None,
+        ));
+    }
+
+    let input_header_for_writer: InputHeader = match query_file_type {
+        bedder::sniff::FileType::Vcf | bedder::sniff::FileType::Bcf => match &a_bed_reader_obj {
+            bedder::sniff::BedderReader::BedderVcf(vcf_reader) => {
+                InputHeader::Vcf(vcf_reader.header.clone())
+            }
+            _ => {
+                log::warn!(
+                    "Query file type is {:?} but reader is not BedderVcf, cannot extract header.",
+                    query_file_type
+                );
+                InputHeader::None
+            }
+        },
+        bedder::sniff::FileType::Bed
+        | bedder::sniff::FileType::BigWig
+        | bedder::sniff::FileType::BigBed
+        | bedder::sniff::FileType::D4 => InputHeader::None,
+        bedder::sniff::FileType::Sam
+        | bedder::sniff::FileType::Bam
+        | bedder::sniff::FileType::Cram => {
+            return Err(format!(
+                "alignments ({}) are not supported for -a; use them as -b",
+                query_path.display()
+            )
+            .into())
+        }
+        bedder::sniff::FileType::Gff => {
+            return Err(format!(
+                "GFF/GTF ({}) is not supported for -a; use it as -b",
+                query_path.display()
+            )
+            .into())
+        }
+    };
+
+    Ok((
+        a_bed_reader_obj.into_positioned_iterator(),
+        query_file_type,
+        input_header_for_writer,
+    ))
+}
+
+/// Output for commands that report one row per interval. Rows are written as BED unless the
+/// output path has a BedGraph (`.bedgraph`, `.bdg`, `.bg`) or bigWig (`.bw`, `.bigwig`)
+/// suffix, in which case only the interval and a single value are written.
+pub(crate) enum IntervalWriter {
+    Bed(bedder::bedder_bed::simplebed::BedWriter),
+    Signal(SignalWriter),
+}
+
+impl IntervalWriter {
+    pub(crate) fn new(
+        path: &Path,
+        chrom_order: &HashMap<bedder::string::String, Chromosome>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if path.to_str() == Some("-") {
+            return Ok(IntervalWriter::Bed(
+                bedder::bedder_bed::simplebed::BedWriter::from_writer(Box::new(
+                    std::io::BufWriter::new(std::io::stdout()),
+                ))?,
+            ));
+        }
+        Ok(match SignalFormat::from_path(path) {
+            Some(format) => IntervalWriter::Signal(SignalWriter::new(path, format, chrom_order)?),
+            None => IntervalWriter::Bed(bedder::bedder_bed::simplebed::BedWriter::new(path)?),
+        })
+    }
+
+    pub(crate) fn is_signal(&self) -> bool {
+        matches!(self, IntervalWriter::Signal(_))
+    }
+
+    /// Write `record`, or its interval and `value` for BedGraph/bigWig. Rows with a
+    /// missing (".") value are not written to BedGraph/bigWig.
+    pub(crate) fn write(
+        &mut self,
+        record: &bedder::bedder_bed::SimpleBedRecord,
+        value: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            IntervalWriter::Bed(w) => w.write_record(record)?,
+            IntervalWriter::Signal(_) if value == "." => {}
+            IntervalWriter::Signal(w) => {
+                let v: f64 = value.parse().map_err(|_| {
+                    format!(
+                        "non-numeric value '{}' for {}:{}-{} cannot be written as BedGraph/bigWig",
+                        value,
+                        record.chrom(),
+                        record.start(),
+                        record.end()
+                    )
+                })?;
+                w.write(record.chrom(), record.start(), record.end(), v)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            IntervalWriter::Bed(mut w) => w.flush()?,
+            IntervalWriter::Signal(w) => w.finish()?,
+        }
+        Ok(())
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -112,6 +574,45 @@ pub struct OverlapArgs {
         long = "b-requirements"
     )]
     pub b_requirements: Option<OverlapAmount>,
+
+    #[arg(
+        help = "report B intervals within this many bases of each A interval (like bedtools window). A is reported unchanged; overlap requirements and B pieces are measured against the extended window.",
+        short = 'w',
+        long = "window"
+    )]
+    pub window: Option<u64>,
+
+    #[arg(
+        help = "window before the start of each A interval (overrides --window)",
+        long = "window-left"
+    )]
+    pub window_left: Option<u64>,
+
+    #[arg(
+        help = "window after the end of each A interval (overrides --window)",
+        long = "window-right"
+    )]
+    pub window_right: Option<u64>,
+
+    #[arg(
+        help = "--window-left and --window-right are upstream and downstream according to the strand of A (BED column 6)",
+        long = "window-stranded"
+    )]
+    pub window_stranded: bool,
+}
+
+impl OverlapArgs {
+    pub fn query_window(&self) -> Option<QueryWindow> {
+        if self.window.is_none() && self.window_left.is_none() && self.window_right.is_none() {
+            return None;
+        }
+        let both = self.window.unwrap_or(0);
+        Some(QueryWindow {
+            left: self.window_left.unwrap_or(both),
+            right: self.window_right.unwrap_or(both),
+            stranded: self.window_stranded,
+        })
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -134,17 +635,81 @@ By default, there is no distance limit.
 When used, the default overlap requirement is set to 0, so that non-overlapping intervals can be reported."
     )]
     pub max_distance: Option<i64>,
+
+    #[arg(
+        long = "signed-distance",
+        short = 'D',
+        help = "report a signed distance column (distance_<mode>) relative to the reference (ref), the strand of A (a) or the strand of B (b) (bedtools -D).
+Negative distances are upstream. This also sets how --direction decides upstream and downstream (default: ref)."
+    )]
+    pub signed_distance: Option<DistanceMode>,
+
+    #[arg(
+        long = "direction",
+        help = "only report non-overlapping intervals upstream or downstream of A, as signed by --signed-distance (bedtools -id/-iu). Overlapping intervals are always reported.",
+        default_value = "both"
+    )]
+    pub direction: ClosestDirection,
+
+    #[arg(
+        long = "ties",
+        short = 't',
+        help = "which equidistant intervals to report when they don't all fit in --n-closest. 'all' may report more than n intervals (bedtools -t).",
+        default_value = "first"
+    )]
+    pub ties: TieMode,
+}
+
+#[derive(Parser, Debug)]
+pub struct SubtractArgs {
+    #[arg(
+        help = "remove the entire A interval if any B interval satisfies the overlap requirements (bedtools -A)",
+        short = 'A',
+        long = "remove-whole"
+    )]
+    pub remove_whole: bool,
+
+    #[arg(
+        help = "overlap required, relative to A, for a B interval to be subtracted. A float value < 1 or a number ending with % will be the fraction (or %) of the interval. An integer will be the number of bases.",
+        short = 'r',
+        long = "a-requirements",
+        default_value = "1"
+    )]
+    pub a_requirements: OverlapAmount,
+
+    #[arg(
+        help = "overlap required, relative to B, for a B interval to be subtracted. A float value < 1 or a number ending with % will be the fraction (or %) of the interval. An integer will be the number of bases.",
+        short = 'R',
+        long = "b-requirements",
+        default_value = "1"
+    )]
+    pub b_requirements: OverlapAmount,
 }
 
 pub fn process_bedder(
-    common_args: CommonArgs,
+    mut common_args: CommonArgs,
     overlap_args: Option<OverlapArgs>,
     closest_args: Option<ClosestArgs>,
+    subtract_args: Option<SubtractArgs>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let n_closest = closest_args.as_ref().and_then(|c| c.n_closest);
     let max_distance = closest_args.as_ref().and_then(|c| c.max_distance);
-    let a_requirements = overlap_args.as_ref().and_then(|o| o.a_requirements.clone());
-    let b_requirements = overlap_args.as_ref().and_then(|o| o.b_requirements.clone());
+    let signed_distance = closest_args.as_ref().and_then(|c| c.signed_distance);
+    if let Some(mode) = signed_distance {
+        // the value of the distance_<mode> column is the signed distance.
+        let mode = mode.to_possible_value().expect("no skipped distance modes");
+        common_args
+            .columns
+            .push(format!("distance_{}", mode.get_name()));
+    }
+    let a_requirements = overlap_args
+        .as_ref()
+        .and_then(|o| o.a_requirements.clone())
+        .or_else(|| subtract_args.as_ref().map(|s| s.a_requirements.clone()));
+    let b_requirements = overlap_args
+        .as_ref()
+        .and_then(|o| o.b_requirements.clone())
+        .or_else(|| subtract_args.as_ref().map(|s| s.b_requirements.clone()));
     let intersection_mode = overlap_args
         .as_ref()
         .map(|o| o.intersection_mode.clone())
@@ -167,6 +732,12 @@ pub fn process_bedder(
         std::process::exit(1);
     }
 
+    let query_window = overlap_args.as_ref().and_then(|o| o.query_window());
+    if query_window.is_some() && (n_closest.is_some() || max_distance.is_some()) {
+        log::error!("Cannot specify --window with --n-closest or --max-distance.");
+        std::process::exit(1);
+    }
+
     if n_closest.is_some() {
         if a_requirements.is_some() || b_requirements.is_some() {
             log::error!("Cannot specify --n-closest with --a-requirements or --b-requirements. The 'closest' command is for finding nearest intervals, which may not overlap so overlap requirements are not applicable.");
@@ -180,35 +751,21 @@ pub fn process_bedder(
     let chrom_order =
         bedder::chrom_ordering::parse_genome(std::fs::File::open(&common_args.genome_file)?)?;
 
-    let afhile = BufReader::new(File::open(&common_args.query_path)?);
-
-    let (a_bed_reader_obj, query_file_type) = bedder::sniff::open(afhile, &common_args.query_path)?;
-
-    let input_header_for_writer: InputHeader = match query_file_type {
-        bedder::sniff::FileType::Vcf | bedder::sniff::FileType::Bcf => match &a_bed_reader_obj {
-            bedder::sniff::BedderReader::BedderVcf(vcf_reader) => {
-                InputHeader::Vcf(vcf_reader.header.clone())
-            }
-            _ => {
-                log::warn!(
-                    "Query file type is {:?} but reader is not BedderVcf, cannot extract header.",
-                    query_file_type
-                );
-                InputHeader::None
-            }
-        },
-        bedder::sniff::FileType::Bed => InputHeader::None,
-    };
-
-    let a_iter = a_bed_reader_obj.into_positioned_iterator();
+    let (a_iter, query_file_type, mut input_header_for_writer) = open_query(
+        common_args.query_path.as_ref(),
+        &common_args.windows,
+        &chrom_order,
+    )?;
+    let a_iter = common_args.slop.apply(a_iter, &chrom_order)?;
 
     let b_iters: Vec<_> = common_args
         .other_paths
         .iter()
-        .map(|p| -> Result<_, Box<dyn std::error::Error>> {
-            let fh = BufReader::new(File::open(p)?);
-            let (b_reader, _) = bedder::sniff::open(fh, p)?;
-            Ok(b_reader.into_positioned_iterator())
+        .map(|p| {
+            common_args
+                .b_input
+                .open(p, &chrom_order)
+                .map(|(b_iter, _)| b_iter)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
@@ -217,23 +774,48 @@ pub fn process_bedder(
         IntersectionPart::WholeWide | IntersectionPart::Whole
     ) && !common_args.dont_use_indexes;
 
-    let ii = bedder::intersection::IntersectionIterator::new(
+    let strand_mode = common_args.strand.strand_mode();
+
+    let mut ii = bedder::intersection::IntersectionIterator::new(
         a_iter,
         b_iters,
         &chrom_order,
         max_distance.unwrap_or(-1),
         n_closest.unwrap_or(-1),
         can_skip_ahead,
-    )?;
+    )?
+    .with_strand_mode(strand_mode);
+    if let Some(window) = query_window {
+        ii = ii.with_window(window);
+    }
+    if let Some(closest_args) = &closest_args {
+        ii = ii
+            .with_closest_direction(closest_args.direction, signed_distance.unwrap_or_default())
+            .with_ties(closest_args.ties);
+    }
 
     let mut output_format = match query_file_type {
         bedder::sniff::FileType::Bed => Format::Bed,
         bedder::sniff::FileType::Vcf => Format::Vcf,
         bedder::sniff::FileType::Bcf => Format::Bcf,
+        bedder::sniff::FileType::Sam => Format::Sam,
+        bedder::sniff::FileType::Bam => Format::Bam,
+        bedder::sniff::FileType::Cram => Format::Cram,
+        bedder::sniff::FileType::Gff
+        | bedder::sniff::FileType::BigWig
+        | bedder::sniff::FileType::BigBed
+        | bedder::sniff::FileType::D4 => Format::Bed,
     };
 
-    if output_format == Format::Vcf && common_args.output_path.to_str().unwrap().ends_with(".bcf") {
-        output_format = Format::Bcf
+    // BED queries are written as VCF, with a header from the genome file, when the output
+    // path asks for it.
+    match Format::vcf_from_path(common_args.output_path.to_str().unwrap()) {
+        Some(vcf_format) if output_format == Format::Bed => {
+            input_header_for_writer = InputHeader::from_genome(&chrom_order);
+            output_format = vcf_format;
+        }
+        Some(vcf_format) if output_format == Format::Vcf => output_format = vcf_format,
+        _ => {}
     }
 
     let (a_reqs, b_reqs) = if n_closest.is_some() || max_distance.is_some() {
@@ -256,6 +838,8 @@ pub fn process_bedder(
             .b_piece(b_piece.clone())
             .a_requirements(a_reqs)
             .b_requirements(b_reqs)
+            .strand(strand_mode)
+            .split(common_args.b_input.split)
             .build(),
     );
 
@@ -311,17 +895,34 @@ pub fn process_bedder(
             None
         };
 
-        let mut output = Writer::init(
-            common_args.output_path.to_str().unwrap(),
-            Some(output_format),
-            None,
-            input_header_for_writer,
-            &py_columns,
-        )?;
+        let mut output = match common_args.output_format {
+            OutputFormat::Native => Writer::init(
+                common_args.output_path.to_str().unwrap(),
+                Some(output_format),
+                None,
+                input_header_for_writer,
+                &py_columns,
+            )?,
+            table_format => Writer::init_table(
+                common_args.output_path.to_str().unwrap(),
+                table_format,
+                None,
+                common_args.other_paths.len(),
+                &py_columns,
+            )?,
+        };
+        if let Some(index) = common_args.index {
+            output = output.with_index(index)?;
+        }
         bedder::py::initialize_python(py).expect("Failed to initialize Python environment");
 
         for intersection in ii {
             let mut intersection = intersection.expect("error getting intersection");
+            if let Some(subtract_args) = &subtract_args {
+                let report = intersection.subtract(&report_options, subtract_args.remove_whole);
+                output.write_report(&report, &py_columns, compiled_filter.as_ref())?;
+                continue;
+            }
             output.write(
                 &mut intersection,
                 report_options.clone(),
@@ -329,6 +930,7 @@ pub fn process_bedder(
                 compiled_filter.as_ref(),
             )?;
         }
+        output.finish()?;
         Ok::<(), Box<dyn std::error::Error>>(())
     })
 }
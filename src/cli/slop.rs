@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::shared::HELP_TEMPLATE;
+use bedder::report_options::OverlapAmount;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Slop: extend each interval by a number of bases or a fraction of its length.",
+    long_about = None,
+    rename_all = "kebab-case",
+    help_template = HELP_TEMPLATE,
+    arg_required_else_help = true,
+    after_long_help = "\
+Intervals are clamped to 0 and to the chromosome length from the genome file (when it has one).
+Output is re-sorted when extending changes the order of the intervals.
+
+EXAMPLES:
+    1. Extend both sides by 50 bases:
+
+        $ bedder slop -a tests/map_a.bed -g tests/hg38.small.fai -b 50
+        chr1\t50\t250\tgeneA\t10
+        chr1\t250\t450\tgeneB\t20
+
+    2. Extend 2kb upstream according to the strand in column 6:
+
+        $ bedder slop -a genes.bed -g genome.fai -l 2000 -s
+
+    3. Extend the end by half of each interval's length:
+
+        $ bedder slop -a tests/map_a.bed -g tests/hg38.small.fai -r 50%
+        chr1\t100\t250\tgeneA\t10
+        chr1\t300\t450\tgeneB\t20"
+)]
+pub struct SlopCmdArgs {
+    #[command(flatten)]
+    pub io: SlopIoArgs,
+
+    #[command(flatten)]
+    pub amounts: SlopAmountArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SlopIoArgs {
+    #[arg(
+        help = "input file (must be sorted by the genome file order; - for stdin)",
+        short = 'a'
+    )]
+    pub input_path: PathBuf,
+
+    #[arg(
+        help = "genome file with chromosome order and lengths",
+        short = 'g',
+        long = "genome",
+        required = true
+    )]
+    pub genome_file: PathBuf,
+
+    #[arg(
+        help = "output file (default: stdout)",
+        short = 'o',
+        long = "output",
+        default_value = "-"
+    )]
+    pub output_path: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+pub struct SlopAmountArgs {
+    #[arg(
+        help = "amount for both sides: a number of bases or a fraction of the interval length (e.g. 0.5 or 50%)",
+        short = 'b',
+        long = "both"
+    )]
+    pub both: Option<OverlapAmount>,
+
+    #[arg(
+        help = "amount before the start of each interval (overrides --both)",
+        short = 'l',
+        long = "left"
+    )]
+    pub left: Option<OverlapAmount>,
+
+    #[arg(
+        help = "amount after the end of each interval (overrides --both)",
+        short = 'r',
+        long = "right"
+    )]
+    pub right: Option<OverlapAmount>,
+
+    #[arg(
+        help = "--left and --right are upstream and downstream according to the strand (BED column 6)",
+        short = 's',
+        long = "strand"
+    )]
+    pub strand: bool,
+}
+
+impl SlopAmountArgs {
+    fn left_right(&self) -> Result<(OverlapAmount, OverlapAmount), Box<dyn std::error::Error>> {
+        if self.both.is_none() && self.left.is_none() && self.right.is_none() {
+            return Err("one of --both, --left or --right is required".into());
+        }
+        let both = self.both.clone().unwrap_or(OverlapAmount::Bases(0));
+        let left = self.left.clone().unwrap_or_else(|| both.clone());
+        let right = self.right.clone().unwrap_or(both);
+        Ok((left, right))
+    }
+}
+
+pub(crate) fn write_slop(
+    io: &SlopIoArgs,
+    amounts: &SlopAmountArgs,
+    flank: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (left, right) = amounts.left_right()?;
+
+    let chrom_order = bedder::chrom_ordering::parse_genome(std::fs::File::open(&io.genome_file)?)?;
+
+    let (reader, file_type) = bedder::sniff::open_path(&io.input_path, None)?;
+    if !matches!(file_type, bedder::sniff::FileType::Bed) {
+        return Err("slop and flank currently only support BED input".into());
+    }
+
+    let intervals = bedder::slop::SlopIterator::new(
+        reader.into_positioned_iterator(),
+        &chrom_order,
+        left,
+        right,
+    )
+    .with_strand(amounts.strand)
+    .with_flank(flank);
+
+    let mut bed_writer = if io.output_path.to_str() == Some("-") {
+        bedder::bedder_bed::simplebed::BedWriter::from_writer(Box::new(std::io::BufWriter::new(
+            std::io::stdout(),
+        )))?
+    } else {
+        bedder::bedder_bed::simplebed::BedWriter::new(&io.output_path)?
+    };
+
+    for position in intervals {
+        match position? {
+            bedder::position::Position::Bed(bed) => bed_writer.write_record(&bed.0)?,
+            _ => unreachable!("BED input gives BED positions"),
+        }
+    }
+    bed_writer.flush()?;
+    Ok(())
+}
+
+pub fn slop_command(args: SlopCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    write_slop(&args.io, &args.amounts, false)
+}
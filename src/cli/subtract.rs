@@ -0,0 +1,16 @@
+use crate::cli::shared::{process_bedder, CommonArgs, SubtractArgs};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about=None, rename_all = "kebab-case", help_template = crate::cli::shared::HELP_TEMPLATE, arg_required_else_help = true)]
+pub struct SubtractCmdArgs {
+    #[command(flatten)]
+    pub common: CommonArgs,
+
+    #[command(flatten)]
+    pub subtract: SubtractArgs,
+}
+
+pub fn subtract_command(args: SubtractCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
+    process_bedder(args.common, None, None, Some(args.subtract))
+}
@@ -3,6 +3,7 @@ use std::str::FromStr;
 
 use crate::py::{CompiledPython, PyReportFragment, PythonFunction};
 use crate::report::ReportFragment;
+use crate::report_options::DistanceMode;
 #[derive(Debug, PartialEq)]
 pub enum Value {
     Int(i32),
@@ -30,9 +31,44 @@ pub enum Number {
     One,
     R,
     A,
+    /// one value per genotype, for FORMAT fields.
+    G,
     Dot,
 }
 
+/// The VCF field that a column is written to. Column specs prefixed with `FORMAT/` are
+/// written as FORMAT fields and those with `INFO/` or no prefix as INFO fields.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum VcfField {
+    #[default]
+    Info,
+    /// Per-sample values, in sample order. A single value is used for every sample.
+    Format,
+}
+
+impl VcfField {
+    /// Split the (case-insensitive) `INFO/` or `FORMAT/` prefix from a column spec.
+    fn strip_prefix(s: &str) -> (Self, &str) {
+        for (prefix, field) in [("FORMAT/", VcfField::Format), ("INFO/", VcfField::Info)] {
+            if s.get(..prefix.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+            {
+                return (field, &s[prefix.len()..]);
+            }
+        }
+        (VcfField::Info, s)
+    }
+}
+
+impl std::fmt::Display for VcfField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VcfField::Info => write!(f, "INFO"),
+            VcfField::Format => write!(f, "FORMAT"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum ColumnError {
     InvalidValue(String),
@@ -72,6 +108,10 @@ pub trait ColumnReporter: std::fmt::Debug {
     fn ftype(&self) -> &Type; // Type is some enum from noodles or here that limits to relevant types
     fn description(&self) -> &str;
     fn number(&self) -> &Number;
+    /// report whether the column is an INFO or a per-sample FORMAT field of the VCF
+    fn vcf_field(&self) -> VcfField {
+        VcfField::Info
+    }
 
     /// Each report fragment is a different line in the output. So we can't pass a report.
     fn value(&self, r: &ReportFragment) -> Result<Value, ColumnError>;
@@ -82,8 +122,12 @@ pub enum ValueParser {
     LuaExpression(String),
     Count,
     Distance,
+    /// Distance signed by a DistanceMode; negative is upstream.
+    SignedDistance(DistanceMode),
     Sum,
     Bases,
+    CoveredBases,
+    CoveredFraction,
     ChromStartEnd,
     OriginalInterval,
 }
@@ -93,6 +137,7 @@ pub struct Column<'py> {
     ftype: Type,
     description: String,
     number: Number,
+    field: VcfField,
     // enum for python-expression|count|sum|bases|...
     pub value_parser: Option<ValueParser>,
 
@@ -112,10 +157,16 @@ impl Column<'_> {
             ftype,
             description,
             number,
+            field: VcfField::Info,
             value_parser,
             py: None,
         }
     }
+
+    pub fn with_vcf_field(mut self, field: VcfField) -> Self {
+        self.field = field;
+        self
+    }
 }
 
 impl TryFrom<&str> for ValueParser {
@@ -134,8 +185,13 @@ impl TryFrom<&str> for ValueParser {
             }
             "count" => Ok(ValueParser::Count),
             "dist" | "distance" => Ok(ValueParser::Distance),
+            "dist_ref" | "distance_ref" => Ok(ValueParser::SignedDistance(DistanceMode::Ref)),
+            "dist_a" | "distance_a" => Ok(ValueParser::SignedDistance(DistanceMode::A)),
+            "dist_b" | "distance_b" => Ok(ValueParser::SignedDistance(DistanceMode::B)),
             "sum" => Ok(ValueParser::Sum),
             "bases" => Ok(ValueParser::Bases),
+            "covered_bases" => Ok(ValueParser::CoveredBases),
+            "covered_fraction" => Ok(ValueParser::CoveredFraction),
             "chrom_start_end" | "cse" => Ok(ValueParser::ChromStartEnd),
             "original_interval" | "oi" => Ok(ValueParser::OriginalInterval),
             _ => Err(ColumnError::InvalidValueParser(s.to_string())),
@@ -150,8 +206,13 @@ impl std::fmt::Display for ValueParser {
             ValueParser::LuaExpression(s) => write!(f, "lua:{}", s),
             ValueParser::Count => write!(f, "count"),
             ValueParser::Distance => write!(f, "distance"),
+            ValueParser::SignedDistance(DistanceMode::Ref) => write!(f, "distance_ref"),
+            ValueParser::SignedDistance(DistanceMode::A) => write!(f, "distance_a"),
+            ValueParser::SignedDistance(DistanceMode::B) => write!(f, "distance_b"),
             ValueParser::Sum => write!(f, "sum"),
             ValueParser::Bases => write!(f, "bases"),
+            ValueParser::CoveredBases => write!(f, "covered_bases"),
+            ValueParser::CoveredFraction => write!(f, "covered_fraction"),
             ValueParser::ChromStartEnd => write!(f, "chrom_start_end"),
             ValueParser::OriginalInterval => write!(f, "original_interval"),
         }
@@ -181,14 +242,23 @@ impl ColumnReporter for Column<'_> {
         &self.number
     }
 
+    fn vcf_field(&self) -> VcfField {
+        self.field
+    }
+
     fn value(&self, r: &ReportFragment) -> Result<Value, ColumnError> {
         match &self.value_parser {
             Some(ValueParser::Count) => Ok(Value::Int(r.b.len() as i32)),
-            Some(ValueParser::Distance) => {
-                // Calculate the distance between the intervals
-                // This is more complex as we need to extract distances from the intervals
-                // For now, return 0.0 as a placeholder
-                Ok(Value::Int(r.distance() as i32))
+            Some(ValueParser::Distance) => Ok(Value::Int(match r.distance() {
+                // no b interval, as bedtools.
+                u64::MAX => -1,
+                d => d.min(i32::MAX as u64) as i32,
+            })),
+            Some(ValueParser::SignedDistance(mode)) => {
+                Ok(Value::Int(match r.signed_distance(*mode) {
+                    Some(d) => d.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                    None => -1,
+                }))
             }
             Some(ValueParser::Sum) => {
                 // Sum the scores of overlapping intervals if they exist
@@ -210,6 +280,19 @@ impl ColumnReporter for Column<'_> {
 
                 Ok(Value::Int(bases))
             }
+            Some(ValueParser::CoveredBases) => {
+                // Unlike Bases, overlapping b intervals are only counted once.
+                let coverage = r.coverage().ok_or_else(|| {
+                    ColumnError::InvalidValue(format!("No base interval for {}", r.id))
+                })?;
+                Ok(Value::Int(coverage.covered_bases as i32))
+            }
+            Some(ValueParser::CoveredFraction) => {
+                let coverage = r.coverage().ok_or_else(|| {
+                    ColumnError::InvalidValue(format!("No base interval for {}", r.id))
+                })?;
+                Ok(Value::Float(coverage.fraction() as f32))
+            }
             Some(ValueParser::ChromStartEnd) => {
                 if let Some(a) = &r.a {
                     let a = a
@@ -279,6 +362,7 @@ impl std::fmt::Display for Number {
             Number::One => write!(f, "1"),
             Number::R => write!(f, "R"),
             Number::A => write!(f, "A"),
+            Number::G => write!(f, "G"),
             Number::Dot => write!(f, "."),
         }
     }
@@ -312,12 +396,31 @@ impl std::fmt::Debug for Column<'_> {
 /// qual:Float:Phred-scaled quality score:1
 /// gt:String:Genotype:1
 ///
-/// and return a Column
+/// and return a Column. A `FORMAT/` prefix, e.g. `FORMAT/cn:Integer:Copy number:1:py:cn`,
+/// writes the column as a per-sample VCF FORMAT field.
 impl TryFrom<(&str, &HashMap<String, PythonFunction<'_>>)> for Column<'_> {
     type Error = ColumnError;
 
     fn try_from(
         (s, functions_map): (&str, &HashMap<String, PythonFunction<'_>>),
+    ) -> Result<Self, ColumnError> {
+        let (field, s) = VcfField::strip_prefix(s);
+        let column = Column::parse(s, field, functions_map)?;
+        if field == VcfField::Format && column.ftype == Type::Flag {
+            return Err(ColumnError::InvalidType(format!(
+                "FORMAT field {} can not be a Flag",
+                column.name
+            )));
+        }
+        Ok(column.with_vcf_field(field))
+    }
+}
+
+impl Column<'_> {
+    fn parse(
+        s: &str,
+        field: VcfField,
+        functions_map: &HashMap<String, PythonFunction<'_>>,
     ) -> Result<Self, ColumnError> {
         let parts: Vec<&str> = s.splitn(5, ':').collect();
         if parts.len() == 1
@@ -348,8 +451,16 @@ impl TryFrom<(&str, &HashMap<String, PythonFunction<'_>>)> for Column<'_> {
             && (parts[0] == "count"
                 || parts[0] == "sum"
                 || parts[0] == "bases"
+                || parts[0] == "covered_bases"
+                || parts[0] == "covered_fraction"
                 || parts[0] == "dist"
-                || parts[0] == "distance")
+                || parts[0] == "distance"
+                || parts[0] == "dist_ref"
+                || parts[0] == "distance_ref"
+                || parts[0] == "dist_a"
+                || parts[0] == "distance_a"
+                || parts[0] == "dist_b"
+                || parts[0] == "distance_b")
         {
             return Ok(match parts[0] {
                 "count" => Column::new(
@@ -373,6 +484,20 @@ impl TryFrom<(&str, &HashMap<String, PythonFunction<'_>>)> for Column<'_> {
                     Number::One,
                     Some(ValueParser::Bases),
                 ),
+                "covered_bases" => Column::new(
+                    "covered_bases".to_string(),
+                    Type::Integer,
+                    "Bases of A covered by B".to_string(),
+                    Number::One,
+                    Some(ValueParser::CoveredBases),
+                ),
+                "covered_fraction" => Column::new(
+                    "covered_fraction".to_string(),
+                    Type::Float,
+                    "Fraction of A covered by B".to_string(),
+                    Number::One,
+                    Some(ValueParser::CoveredFraction),
+                ),
                 "dist" | "distance" => Column::new(
                     "dist".to_string(),
                     Type::Integer,
@@ -380,7 +505,16 @@ impl TryFrom<(&str, &HashMap<String, PythonFunction<'_>>)> for Column<'_> {
                     Number::One,
                     Some(ValueParser::Distance),
                 ),
-                _ => unreachable!(),
+                p => {
+                    let parser = ValueParser::try_from(p)?;
+                    Column::new(
+                        parser.to_string(),
+                        Type::Integer,
+                        "Signed distance (negative is upstream)".to_string(),
+                        Number::One,
+                        Some(parser),
+                    )
+                }
             });
         }
         // TODO: first need to get the type and number from the python function.
@@ -393,12 +527,20 @@ impl TryFrom<(&str, &HashMap<String, PythonFunction<'_>>)> for Column<'_> {
                         "Function {} not found",
                         function_name
                     )))?;
+            let (ftype, is_list) = compiled
+                .value_type()
+                .map_err(|e| ColumnError::InvalidType(e.to_string()))?;
+            // a list has one value per sample for FORMAT fields.
+            let number = if is_list && field == VcfField::Info {
+                Number::Dot
+            } else {
+                Number::One
+            };
             return Ok(Column::new(
                 function_name.clone(),
-                Type::try_from(compiled.return_type())
-                    .map_err(|e| ColumnError::InvalidType(e.to_string()))?,
+                ftype,
                 function_name.clone(),
-                Number::One,
+                number,
                 Some(ValueParser::PythonExpression(function_name)),
             ));
         }
@@ -434,6 +576,7 @@ impl TryFrom<(&str, &HashMap<String, PythonFunction<'_>>)> for Column<'_> {
             ftype,
             description,
             number,
+            field: VcfField::Info,
             value_parser,
             py: None,
         })
@@ -464,6 +607,7 @@ impl TryFrom<&str> for Number {
             "one" | "1" => Ok(Number::One),
             "r" => Ok(Number::R),
             "a" => Ok(Number::A),
+            "g" => Ok(Number::G),
             "dot" | "." => Ok(Number::Dot),
             _ => Err(ColumnError::InvalidNumber(s.to_string())),
         }
@@ -554,6 +698,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_format_column() {
+        let hm = HashMap::new();
+        let col = Column::try_from(("FORMAT/cn:Integer:Copy number:G", &hm)).unwrap();
+        assert_eq!(col.name(), "cn");
+        assert_eq!(col.vcf_field(), VcfField::Format);
+        assert_eq!(col.number(), &Number::G);
+        assert_eq!(col.number().to_string(), "G");
+
+        let col = Column::try_from(("info/dp:Integer", &hm)).unwrap();
+        assert_eq!((col.name(), col.vcf_field()), ("dp", VcfField::Info));
+        assert_eq!(
+            Column::try_from(("count", &hm)).unwrap().vcf_field(),
+            VcfField::Info
+        );
+        assert!(Column::try_from(("format/hit:Flag", &hm)).is_err());
+    }
+
+    #[test]
+    fn test_parse_coverage_columns() {
+        let hm = HashMap::new();
+        let col = Column::try_from(("covered_bases", &hm)).unwrap();
+        assert_eq!(col.ftype(), &Type::Integer);
+        assert!(matches!(col.value_parser, Some(ValueParser::CoveredBases)));
+        let col = Column::try_from(("covered_fraction", &hm)).unwrap();
+        assert_eq!(col.ftype(), &Type::Float);
+        assert!(matches!(
+            col.value_parser,
+            Some(ValueParser::CoveredFraction)
+        ));
+    }
+
+    #[test]
+    fn test_parse_signed_distance_columns() {
+        let hm = HashMap::new();
+        let col = Column::try_from(("dist_a", &hm)).unwrap();
+        assert_eq!(col.name(), "distance_a");
+        assert_eq!(col.ftype(), &Type::Integer);
+        assert!(matches!(
+            col.value_parser,
+            Some(ValueParser::SignedDistance(DistanceMode::A))
+        ));
+        let col = Column::try_from(("distance_b", &hm)).unwrap();
+        assert!(matches!(
+            col.value_parser,
+            Some(ValueParser::SignedDistance(DistanceMode::B))
+        ));
+    }
+
     #[test]
     fn test_invalid_type() {
         let input = "count:Invalid:A description";
@@ -0,0 +1,461 @@
+use crate::column::{Column, ColumnReporter, Number, Type, Value, VcfField};
+use crate::report::Report;
+use crate::writer::{OutputFormat, TableRow};
+use arrow::array::{
+    ArrayRef, BooleanArray, Float32Array, Int32Array, ListArray, ListBuilder, StringArray,
+    StringBuilder, StructArray, UInt64Array,
+};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{DataType, Field, Fields, Float32Type, Int32Type, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+
+// rows buffered before they are written as a record batch.
+const BATCH_SIZE: usize = 65_536;
+
+fn arrow_error(path: &str, e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("error writing {}: {}", path, e))
+}
+
+/// The arrow type of a column. Columns with `Number` R, A, G or `.` are lists.
+fn column_type(ftype: &Type, number: &Number) -> DataType {
+    let t = match ftype {
+        Type::Integer => DataType::Int32,
+        Type::Float => DataType::Float32,
+        Type::Character | Type::String => DataType::Utf8,
+        Type::Flag => DataType::Boolean,
+    };
+    match number {
+        Number::Not | Number::One => t,
+        Number::R | Number::A | Number::G | Number::Dot => {
+            DataType::List(Arc::new(Field::new("item", t, true)))
+        }
+    }
+}
+
+// chrom, start and end of a B interval.
+fn interval_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("chrom", DataType::Utf8, false),
+        Field::new("start", DataType::UInt64, false),
+        Field::new("end", DataType::UInt64, false),
+    ])
+}
+
+/// `a_chrom`, `a_start`, `a_end`, a list of (chrom, start, end) structs named `b1`, `b2`, ...
+/// for each B file, then the columns.
+fn report_schema(n_b: usize, columns: &[Column<'_>]) -> Schema {
+    let mut fields = vec![
+        Field::new("a_chrom", DataType::Utf8, true),
+        Field::new("a_start", DataType::UInt64, true),
+        Field::new("a_end", DataType::UInt64, true),
+    ];
+    for i in 1..=n_b {
+        let item = Field::new("item", DataType::Struct(interval_fields()), true);
+        fields.push(Field::new(
+            format!("b{}", i),
+            DataType::List(Arc::new(item)),
+            false,
+        ));
+    }
+    for c in columns {
+        // FORMAT columns have values for each sample.
+        let number = match c.vcf_field() {
+            VcfField::Info => c.number(),
+            VcfField::Format => &Number::Dot,
+        };
+        fields.push(Field::new(c.name(), column_type(c.ftype(), number), true));
+    }
+    Schema::new(fields)
+}
+
+fn invalid_value(name: &str, value: &Value, expected: &DataType) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("value {:?} of column {} is not a {}", value, name, expected),
+    )
+}
+
+fn as_i32(name: &str, value: &Value) -> io::Result<Vec<i32>> {
+    match value {
+        Value::Int(i) => Ok(vec![*i]),
+        Value::VecInt(v) => Ok(v.clone()),
+        Value::Flag(b) => Ok(vec![*b as i32]),
+        _ => Err(invalid_value(name, value, &DataType::Int32)),
+    }
+}
+
+fn as_f32(name: &str, value: &Value) -> io::Result<Vec<f32>> {
+    match value {
+        Value::Float(f) => Ok(vec![*f]),
+        Value::VecFloat(v) => Ok(v.clone()),
+        Value::Int(i) => Ok(vec![*i as f32]),
+        Value::VecInt(v) => Ok(v.iter().map(|i| *i as f32).collect()),
+        _ => Err(invalid_value(name, value, &DataType::Float32)),
+    }
+}
+
+fn as_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => vec![s.clone()],
+        Value::VecString(v) => v.clone(),
+        Value::VecInt(v) => v.iter().map(|i| i.to_string()).collect(),
+        Value::VecFloat(v) => v.iter().map(|f| f.to_string()).collect(),
+        v => vec![v.to_string()],
+    }
+}
+
+// a scalar column takes the single value of a list; anything else is an error.
+fn scalar<T>(name: &str, value: &Value, mut v: Vec<T>, t: &DataType) -> io::Result<T> {
+    match v.len() {
+        1 => Ok(v.remove(0)),
+        _ => Err(invalid_value(name, value, t)),
+    }
+}
+
+/// Build the array of a column from the values of each row.
+fn column_array(field: &Field, values: &[&Value]) -> io::Result<ArrayRef> {
+    let name = field.name();
+    let t = field.data_type();
+    Ok(match t {
+        DataType::Int32 => Arc::new(Int32Array::from(
+            values
+                .iter()
+                .map(|v| scalar(name, v, as_i32(name, v)?, t))
+                .collect::<io::Result<Vec<_>>>()?,
+        )),
+        DataType::Float32 => Arc::new(Float32Array::from(
+            values
+                .iter()
+                .map(|v| scalar(name, v, as_f32(name, v)?, t))
+                .collect::<io::Result<Vec<_>>>()?,
+        )),
+        DataType::Utf8 => Arc::new(StringArray::from(
+            values
+                .iter()
+                .map(|v| scalar(name, v, as_strings(v), t))
+                .collect::<io::Result<Vec<_>>>()?,
+        )),
+        DataType::Boolean => Arc::new(BooleanArray::from(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Flag(b) => Ok(*b),
+                    Value::Int(i) => Ok(*i != 0),
+                    _ => Err(invalid_value(name, v, t)),
+                })
+                .collect::<io::Result<Vec<_>>>()?,
+        )),
+        DataType::List(item) => match item.data_type() {
+            DataType::Int32 => {
+                let lists = values
+                    .iter()
+                    .map(|v| as_i32(name, v).map(|l| Some(l.into_iter().map(Some))))
+                    .collect::<io::Result<Vec<_>>>()?;
+                Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(lists))
+            }
+            DataType::Float32 => {
+                let lists = values
+                    .iter()
+                    .map(|v| as_f32(name, v).map(|l| Some(l.into_iter().map(Some))))
+                    .collect::<io::Result<Vec<_>>>()?;
+                Arc::new(ListArray::from_iter_primitive::<Float32Type, _, _>(lists))
+            }
+            DataType::Utf8 => {
+                let mut builder = ListBuilder::new(StringBuilder::new());
+                for v in values {
+                    for s in as_strings(v) {
+                        builder.values().append_value(s);
+                    }
+                    builder.append(true);
+                }
+                Arc::new(builder.finish())
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("column {} can not be a list of {}", name, item.data_type()),
+                ))
+            }
+        },
+        _ => unreachable!("column types are from column_type"),
+    })
+}
+
+enum BatchWriter {
+    Parquet(ArrowWriter<File>),
+    Arrow(arrow::ipc::writer::FileWriter<BufWriter<File>>),
+}
+
+/// Writes report fragments as Parquet or Arrow IPC record batches. The schema has the fields of
+/// the tabular output formats, with the B intervals of each file as a list of structs and the
+/// columns typed from their `Type` and `Number`.
+pub struct ColumnarWriter {
+    writer: BatchWriter,
+    schema: SchemaRef,
+    path: String,
+    n_b: usize,
+    rows: Vec<TableRow>,
+}
+
+impl ColumnarWriter {
+    pub fn new(
+        path: &str,
+        format: OutputFormat,
+        n_b: usize,
+        columns: &[Column<'_>],
+    ) -> io::Result<Self> {
+        let schema = Arc::new(report_schema(n_b, columns));
+        let file = File::create(path)?;
+        let writer = match format {
+            OutputFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(parquet::basic::Compression::SNAPPY)
+                    .build();
+                BatchWriter::Parquet(
+                    ArrowWriter::try_new(file, schema.clone(), Some(props))
+                        .map_err(|e| arrow_error(path, e))?,
+                )
+            }
+            OutputFormat::Arrow => BatchWriter::Arrow(
+                arrow::ipc::writer::FileWriter::try_new(BufWriter::new(file), &schema)
+                    .map_err(|e| arrow_error(path, e))?,
+            ),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} output is not columnar", format),
+                ))
+            }
+        };
+        Ok(ColumnarWriter {
+            writer,
+            schema,
+            path: path.to_string(),
+            n_b,
+            rows: Vec::with_capacity(BATCH_SIZE),
+        })
+    }
+
+    pub fn write_report<T: ColumnReporter>(
+        &mut self,
+        report: &Report,
+        crs: &[T],
+        filter: Option<&crate::py::CompiledExpr<'_>>,
+    ) -> io::Result<()> {
+        for frag in report.iter() {
+            if let Some(row) = TableRow::from_fragment(frag, crs, filter)? {
+                self.rows.push(row);
+            }
+            if self.rows.len() >= BATCH_SIZE {
+                self.write_batch()?;
+            }
+        }
+        Ok(())
+    }
+
+    // the list of B intervals of file `i` in each row.
+    fn b_array(&self, i: usize) -> ArrayRef {
+        let (mut chroms, mut starts, mut ends) = (Vec::new(), Vec::new(), Vec::new());
+        let mut offsets = vec![0i32];
+        for row in &self.rows {
+            if row.id == i {
+                for (chrom, start, end) in &row.b {
+                    chroms.push(chrom.as_str());
+                    starts.push(*start);
+                    ends.push(*end);
+                }
+            }
+            offsets.push(chroms.len() as i32);
+        }
+        let intervals = StructArray::new(
+            interval_fields(),
+            vec![
+                Arc::new(StringArray::from(chroms)) as ArrayRef,
+                Arc::new(UInt64Array::from(starts)),
+                Arc::new(UInt64Array::from(ends)),
+            ],
+            None,
+        );
+        Arc::new(ListArray::new(
+            Arc::new(Field::new(
+                "item",
+                DataType::Struct(interval_fields()),
+                true,
+            )),
+            OffsetBuffer::new(offsets.into()),
+            Arc::new(intervals),
+            None,
+        ))
+    }
+
+    fn write_batch(&mut self) -> io::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let a = |f: fn(&(String, u64, u64)) -> u64| -> ArrayRef {
+            Arc::new(UInt64Array::from(
+                self.rows
+                    .iter()
+                    .map(|r| r.a.as_ref().map(f))
+                    .collect::<Vec<_>>(),
+            ))
+        };
+        let mut arrays: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(
+                self.rows
+                    .iter()
+                    .map(|r| r.a.as_ref().map(|(chrom, _, _)| chrom.as_str()))
+                    .collect::<Vec<_>>(),
+            )),
+            a(|(_, start, _)| *start),
+            a(|(_, _, end)| *end),
+        ];
+        for i in 0..self.n_b {
+            arrays.push(self.b_array(i));
+        }
+        let columns = &self.schema.fields()[3 + self.n_b..];
+        for (c, field) in columns.iter().enumerate() {
+            let values: Vec<&Value> = self.rows.iter().map(|r| &r.values[c]).collect();
+            arrays.push(column_array(field, &values)?);
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(|e| arrow_error(&self.path, e))?;
+        match &mut self.writer {
+            BatchWriter::Parquet(w) => w.write(&batch).map_err(|e| arrow_error(&self.path, e))?,
+            BatchWriter::Arrow(w) => w.write(&batch).map_err(|e| arrow_error(&self.path, e))?,
+        }
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// Write the remaining rows and the file footer.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_batch()?;
+        match self.writer {
+            BatchWriter::Parquet(w) => {
+                w.close().map_err(|e| arrow_error(&self.path, e))?;
+            }
+            BatchWriter::Arrow(w) => {
+                // into_inner writes the footer; the buffered file must still be flushed.
+                w.into_inner()
+                    .map_err(|e| arrow_error(&self.path, e))?
+                    .flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+    use crate::report::ReportFragment;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::UInt64Type;
+    use std::sync::Mutex;
+
+    fn report() -> Report {
+        let position = |start: u64, end: u64| {
+            Arc::new(Mutex::new(Position::Bed(
+                crate::bedder_bed::BedRecord::new("chr1", start, end, None, None, vec![]),
+            )))
+        };
+        Report::new(vec![
+            ReportFragment {
+                a: Some(position(10, 20)),
+                b: vec![position(5, 12), position(15, 30)],
+                id: 1,
+            },
+            ReportFragment {
+                a: Some(position(40, 50)),
+                b: vec![],
+                id: 0,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_column_type() {
+        assert_eq!(column_type(&Type::Integer, &Number::One), DataType::Int32);
+        assert_eq!(column_type(&Type::Flag, &Number::Not), DataType::Boolean);
+        assert!(matches!(
+            column_type(&Type::String, &Number::Dot),
+            DataType::List(item) if item.data_type() == &DataType::Utf8
+        ));
+    }
+
+    #[test]
+    fn test_write_parquet_and_arrow() {
+        let hm = std::collections::HashMap::new();
+        let columns = vec![Column::try_from(("count", &hm)).unwrap()];
+        let dir = tempfile::tempdir().unwrap();
+
+        for format in [OutputFormat::Parquet, OutputFormat::Arrow] {
+            let path = dir.path().join(format!("out.{:?}", format));
+            let path = path.to_str().unwrap();
+            let mut w = ColumnarWriter::new(path, format, 2, &columns).unwrap();
+            w.write_report(&report(), &columns, None).unwrap();
+            w.finish().unwrap();
+
+            let batches: Vec<RecordBatch> = match format {
+                OutputFormat::Parquet => {
+                    parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(
+                        File::open(path).unwrap(),
+                    )
+                    .unwrap()
+                    .build()
+                    .unwrap()
+                    .collect::<Result<_, _>>()
+                    .unwrap()
+                }
+                _ => arrow::ipc::reader::FileReader::try_new(File::open(path).unwrap(), None)
+                    .unwrap()
+                    .collect::<Result<_, _>>()
+                    .unwrap(),
+            };
+            assert_eq!(batches.len(), 1);
+            let batch = &batches[0];
+            assert_eq!(batch.num_rows(), 2);
+            let a_start = batch
+                .column_by_name("a_start")
+                .unwrap()
+                .as_primitive::<UInt64Type>();
+            assert_eq!(a_start.values().to_vec(), vec![10, 40]);
+
+            let b1 = batch.column_by_name("b1").unwrap().as_list::<i32>();
+            assert!(b1.value(0).is_empty() && b1.value(1).is_empty());
+            let b2 = batch.column_by_name("b2").unwrap().as_list::<i32>();
+            let b2_0 = b2.value(0);
+            let starts = b2_0
+                .as_struct()
+                .column_by_name("start")
+                .unwrap()
+                .as_primitive::<UInt64Type>();
+            assert_eq!(starts.values().to_vec(), vec![5, 15]);
+
+            let count = batch
+                .column_by_name("count")
+                .unwrap()
+                .as_primitive::<Int32Type>();
+            assert_eq!(count.values().to_vec(), vec![2, 0]);
+        }
+
+        // list columns take lists and single values.
+        let field = Field::new("lens", column_type(&Type::Integer, &Number::Dot), true);
+        let values = [Value::VecInt(vec![7, 15]), Value::Int(3)];
+        let lens = column_array(&field, &values.iter().collect::<Vec<_>>()).unwrap();
+        let lens = lens.as_list::<i32>().value(1);
+        assert_eq!(lens.as_primitive::<Int32Type>().values().to_vec(), vec![3]);
+
+        // but scalar columns do not take lists.
+        let field = Field::new("count", DataType::Int32, true);
+        let values = [Value::VecInt(vec![1, 2])];
+        assert!(column_array(&field, &values.iter().collect::<Vec<_>>()).is_err());
+    }
+}
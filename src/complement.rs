@@ -0,0 +1,271 @@
+use crate::bedder_bed::BedRecord;
+use crate::chrom_ordering::Chromosome;
+use crate::position::{Position, PositionedIterator};
+use crate::string::String;
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::io::{self, Error};
+
+/// ComplementIterator reports every region of the genome that is not covered by a sorted
+/// `PositionedIterator`, including whole chromosomes that never appear in the input.
+///
+/// Regions are reported in genome order as BED3 positions. Every chromosome in the genome
+/// must have a length.
+pub struct ComplementIterator {
+    inner: Box<dyn PositionedIterator>,
+    // (name, length) in genome-file order.
+    chromosomes: Vec<(String, u64)>,
+    chromosome_order: HashMap<String, Chromosome>,
+
+    // index into chromosomes of the chromosome currently being complemented.
+    current: usize,
+    // end of the covered region on the current chromosome.
+    covered_to: u64,
+    last_start: u64,
+    queue: VecDeque<Position>,
+    done: bool,
+}
+
+impl ComplementIterator {
+    pub fn new(
+        inner: Box<dyn PositionedIterator>,
+        chromosome_order: &HashMap<String, Chromosome>,
+    ) -> io::Result<Self> {
+        let mut chromosomes: Vec<(&String, &Chromosome)> = chromosome_order.iter().collect();
+        chromosomes.sort_by_key(|(_, c)| c.index);
+
+        let missing: Vec<&str> = chromosomes
+            .iter()
+            .filter(|(_, c)| c.length.is_none())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "complement requires a length for every chromosome in the genome file; missing for: {}",
+                    missing.join(", ")
+                ),
+            ));
+        }
+
+        Ok(ComplementIterator {
+            inner,
+            chromosomes: chromosomes
+                .into_iter()
+                .map(|(name, c)| (name.clone(), c.length.expect("checked above") as u64))
+                .collect(),
+            chromosome_order: chromosome_order.clone(),
+            current: 0,
+            covered_to: 0,
+            last_start: 0,
+            queue: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    fn push_gap(&mut self, start: u64, stop: u64) {
+        if start < stop {
+            let chrom = &self.chromosomes[self.current].0;
+            self.queue.push_back(Position::Bed(BedRecord::new(
+                chrom,
+                start,
+                stop,
+                None,
+                None,
+                vec![],
+            )));
+        }
+    }
+
+    // report the rest of each chromosome before `index`.
+    fn finish_chromosomes_before(&mut self, index: usize) {
+        while self.current < index {
+            let length = self.chromosomes[self.current].1;
+            self.push_gap(self.covered_to, length);
+            self.current += 1;
+            self.covered_to = 0;
+            self.last_start = 0;
+        }
+    }
+
+    fn add(&mut self, position: &Position) -> io::Result<()> {
+        let index = match self.chromosome_order.get(position.chrom()) {
+            Some(c) => c.index,
+            None => {
+                return Err(Error::other(format!(
+                    "invalid chromosome: {}:{}-{} in iterator {}",
+                    position.chrom(),
+                    position.start() + 1,
+                    position.stop(),
+                    self.inner.name()
+                )))
+            }
+        };
+        if index < self.current || (index == self.current && position.start() < self.last_start) {
+            return Err(Error::other(format!(
+                "intervals from {} out of order: {}:{} should be before {}:{}",
+                self.inner.name(),
+                position.chrom(),
+                position.start() + 1,
+                self.chromosomes[self.current].0,
+                self.last_start + 1
+            )));
+        }
+        self.finish_chromosomes_before(index);
+
+        let length = self.chromosomes[index].1;
+        if position.stop() > length {
+            return Err(Error::other(format!(
+                "interval beyond end of chromosome: {}:{}-{}",
+                position.chrom(),
+                position.start() + 1,
+                position.stop()
+            )));
+        }
+
+        self.push_gap(self.covered_to, position.start());
+        self.covered_to = self.covered_to.max(position.stop());
+        self.last_start = position.start();
+        Ok(())
+    }
+}
+
+impl Iterator for ComplementIterator {
+    type Item = io::Result<Position>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(p) = self.queue.pop_front() {
+                return Some(Ok(p));
+            }
+            if self.done {
+                return None;
+            }
+            match self.inner.next_position(None) {
+                Some(Ok(p)) => {
+                    if let Err(e) = self.add(&p) {
+                        return Some(Err(e));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.done = true;
+                    self.finish_chromosomes_before(self.chromosomes.len());
+                }
+            }
+        }
+    }
+}
+
+impl PositionedIterator for ComplementIterator {
+    fn name(&self) -> String {
+        format!("complement:{}", self.inner.name())
+    }
+
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chrom_ordering::parse_genome;
+    use crate::interval::Interval;
+
+    struct Intervals {
+        ivs: std::vec::IntoIter<Position>,
+    }
+
+    impl PositionedIterator for Intervals {
+        fn name(&self) -> String {
+            String::from("intervals")
+        }
+
+        fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+            self.ivs.next().map(Ok)
+        }
+    }
+
+    fn intervals(ivs: &[(&str, u64, u64)]) -> Box<dyn PositionedIterator> {
+        Box::new(Intervals {
+            ivs: ivs
+                .iter()
+                .map(|(chrom, start, stop)| {
+                    Position::Interval(Interval {
+                        chrom: String::from(*chrom),
+                        start: *start,
+                        stop: *stop,
+                        ..Default::default()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    fn regions(it: ComplementIterator) -> Vec<(String, u64, u64)> {
+        it.map(|p| {
+            let p = p.expect("error in complement");
+            (String::from(p.chrom()), p.start(), p.stop())
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_complement() {
+        let genome = parse_genome("chr1\t100\nchr2\t50\nchr3\t20\n".as_bytes()).unwrap();
+        let ivs = intervals(&[
+            ("chr1", 10, 20),
+            ("chr1", 15, 30),
+            ("chr1", 18, 25),
+            ("chr1", 90, 100),
+            ("chr3", 0, 5),
+        ]);
+        let r = regions(ComplementIterator::new(ivs, &genome).unwrap());
+        assert_eq!(
+            r,
+            vec![
+                (String::from("chr1"), 0, 10),
+                (String::from("chr1"), 30, 90),
+                (String::from("chr2"), 0, 50),
+                (String::from("chr3"), 5, 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_complement_empty_input() {
+        let genome = parse_genome("chr1\t100\nchr2\t50\n".as_bytes()).unwrap();
+        let r = regions(ComplementIterator::new(intervals(&[]), &genome).unwrap());
+        assert_eq!(
+            r,
+            vec![
+                (String::from("chr1"), 0, 100),
+                (String::from("chr2"), 0, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_complement_missing_length() {
+        let genome = parse_genome("chr1\t100\nchr2\n".as_bytes()).unwrap();
+        let e = ComplementIterator::new(intervals(&[]), &genome)
+            .err()
+            .expect("expected missing length error");
+        assert!(e.to_string().contains("chr2"));
+    }
+
+    #[test]
+    fn test_complement_errors() {
+        let genome = parse_genome("chr1\t100\nchr2\t50\n".as_bytes()).unwrap();
+        let ivs = intervals(&[("chr2", 10, 20), ("chr1", 10, 20)]);
+        let r: Vec<_> = ComplementIterator::new(ivs, &genome).unwrap().collect();
+        assert!(r.iter().any(|p| p.is_err()));
+
+        let ivs = intervals(&[("chr2", 10, 60)]);
+        let r: Vec<_> = ComplementIterator::new(ivs, &genome).unwrap().collect();
+        assert!(r.iter().any(|p| p.is_err()));
+    }
+}
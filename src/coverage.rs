@@ -0,0 +1,415 @@
+use crate::chrom_ordering::Chromosome;
+use crate::position::{Position, PositionedIterator};
+use crate::string::String;
+use hashbrown::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::io::{self, Error};
+
+/// Coverage of a query interval by a set of (possibly overlapping) intervals.
+///
+/// Overlapping intervals are not double-counted: `covered_bases` is the number of bases in the
+/// query with depth of at least 1.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Coverage {
+    /// number of intervals that were considered.
+    pub n_overlaps: u64,
+    /// number of query bases covered by at least one interval.
+    pub covered_bases: u64,
+    /// length of the query interval.
+    pub length: u64,
+    /// histogram[d] is the number of query bases with depth d.
+    pub histogram: Vec<u64>,
+}
+
+impl Coverage {
+    /// Compute coverage of `start..stop` with a sweep over the (start, stop) of each interval.
+    /// Intervals are clipped to the query; they need not be sorted.
+    pub fn new<I>(start: u64, stop: u64, intervals: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let length = stop.saturating_sub(start);
+        let mut n_overlaps = 0;
+        let mut events: Vec<(u64, i64)> = Vec::new();
+        for (s, e) in intervals {
+            n_overlaps += 1;
+            let (s, e) = (s.max(start), e.min(stop));
+            if s < e {
+                events.push((s, 1));
+                events.push((e, -1));
+            }
+        }
+        events.sort_unstable();
+
+        let mut histogram = vec![0u64; 1];
+        let mut depth: i64 = 0;
+        let mut pos = start;
+        for (p, delta) in events {
+            if p > pos {
+                let d = depth as usize;
+                if d >= histogram.len() {
+                    histogram.resize(d + 1, 0);
+                }
+                histogram[d] += p - pos;
+                pos = p;
+            }
+            depth += delta;
+        }
+        if stop > pos {
+            histogram[0] += stop - pos;
+        }
+
+        Coverage {
+            n_overlaps,
+            covered_bases: length - histogram[0],
+            length,
+            histogram,
+        }
+    }
+
+    /// Fraction of the query covered by at least one interval. 0 for empty queries.
+    pub fn fraction(&self) -> f64 {
+        if self.length == 0 {
+            return 0.0;
+        }
+        self.covered_bases as f64 / self.length as f64
+    }
+
+    /// Mean depth across the query. 0 for empty queries.
+    pub fn mean_depth(&self) -> f64 {
+        if self.length == 0 {
+            return 0.0;
+        }
+        let total: u64 = self
+            .histogram
+            .iter()
+            .enumerate()
+            .map(|(d, n)| d as u64 * n)
+            .sum();
+        total as f64 / self.length as f64
+    }
+}
+
+/// A run of bases on a chromosome that share the same depth (one line of a BedGraph).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthRun {
+    pub chrom: String,
+    pub start: u64,
+    pub stop: u64,
+    pub depth: u64,
+}
+
+/// GenomeCoverageIterator streams a sorted `PositionedIterator` and reports the depth of every
+/// base in the genome as runs of equal depth. Adjacent runs with the same depth are combined.
+///
+/// By default zero-depth runs are reported, including whole chromosomes that are absent from the
+/// input, which requires a length for every chromosome in the genome.
+pub struct GenomeCoverageIterator {
+    inner: Box<dyn PositionedIterator>,
+    // (name, length) in genome-file order.
+    chromosomes: Vec<(String, Option<u64>)>,
+    chromosome_order: HashMap<String, Chromosome>,
+    report_zero: bool,
+
+    // index into chromosomes of the chromosome currently being reported.
+    current: usize,
+    // everything before pos on the current chromosome has been reported.
+    pos: u64,
+    last_start: u64,
+    // stops of the intervals that cover pos.
+    stops: BinaryHeap<Reverse<u64>>,
+    // the most recent run, held back so it can be extended by an adjacent run of equal depth.
+    pending: Option<DepthRun>,
+    queue: VecDeque<DepthRun>,
+    done: bool,
+}
+
+impl GenomeCoverageIterator {
+    pub fn new(
+        inner: Box<dyn PositionedIterator>,
+        chromosome_order: &HashMap<String, Chromosome>,
+        report_zero: bool,
+    ) -> io::Result<Self> {
+        let mut chromosomes: Vec<(&String, &Chromosome)> = chromosome_order.iter().collect();
+        chromosomes.sort_by_key(|(_, c)| c.index);
+
+        if report_zero {
+            let missing: Vec<&str> = chromosomes
+                .iter()
+                .filter(|(_, c)| c.length.is_none())
+                .map(|(name, _)| name.as_str())
+                .collect();
+            if !missing.is_empty() {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "reporting zero-depth regions requires a length for every chromosome in the genome file; missing for: {}",
+                        missing.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        Ok(GenomeCoverageIterator {
+            inner,
+            chromosomes: chromosomes
+                .into_iter()
+                .map(|(name, c)| (name.clone(), c.length.map(|l| l as u64)))
+                .collect(),
+            chromosome_order: chromosome_order.clone(),
+            report_zero,
+            current: 0,
+            pos: 0,
+            last_start: 0,
+            stops: BinaryHeap::new(),
+            pending: None,
+            queue: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    fn emit(&mut self, start: u64, stop: u64, depth: u64) {
+        if start >= stop || (depth == 0 && !self.report_zero) {
+            return;
+        }
+        let chrom = &self.chromosomes[self.current].0;
+        if let Some(p) = &mut self.pending {
+            if p.stop == start && p.depth == depth && &p.chrom == chrom {
+                p.stop = stop;
+                return;
+            }
+        }
+        let run = DepthRun {
+            chrom: chrom.clone(),
+            start,
+            stop,
+            depth,
+        };
+        if let Some(p) = self.pending.replace(run) {
+            self.queue.push_back(p);
+        }
+    }
+
+    // report depth up to each stop that is <= limit (or all stops if limit is None).
+    fn flush_stops(&mut self, limit: Option<u64>) {
+        while let Some(&Reverse(stop)) = self.stops.peek() {
+            if limit.is_some_and(|l| stop > l) {
+                break;
+            }
+            let depth = self.stops.len() as u64;
+            self.emit(self.pos, stop, depth);
+            self.pos = self.pos.max(stop);
+            self.stops.pop();
+        }
+    }
+
+    // report the rest of each chromosome before `index`.
+    fn finish_chromosomes_before(&mut self, index: usize) {
+        while self.current < index {
+            self.flush_stops(None);
+            if let Some(length) = self.chromosomes[self.current].1 {
+                self.emit(self.pos, length, 0);
+            }
+            self.current += 1;
+            self.pos = 0;
+            self.last_start = 0;
+        }
+    }
+
+    fn add(&mut self, position: &Position) -> io::Result<()> {
+        let index = match self.chromosome_order.get(position.chrom()) {
+            Some(c) => c.index,
+            None => {
+                return Err(Error::other(format!(
+                    "invalid chromosome: {}:{}-{} in iterator {}",
+                    position.chrom(),
+                    position.start() + 1,
+                    position.stop(),
+                    self.inner.name()
+                )))
+            }
+        };
+        if index < self.current || (index == self.current && position.start() < self.last_start) {
+            return Err(Error::other(format!(
+                "intervals from {} out of order: {}:{} should be before {}:{}",
+                self.inner.name(),
+                position.chrom(),
+                position.start() + 1,
+                self.chromosomes[self.current].0,
+                self.last_start + 1
+            )));
+        }
+        self.finish_chromosomes_before(index);
+
+        if let Some(length) = self.chromosomes[index].1 {
+            if position.stop() > length {
+                return Err(Error::other(format!(
+                    "interval beyond end of chromosome: {}:{}-{}",
+                    position.chrom(),
+                    position.start() + 1,
+                    position.stop()
+                )));
+            }
+        }
+
+        let start = position.start();
+        self.flush_stops(Some(start));
+        let depth = self.stops.len() as u64;
+        self.emit(self.pos, start, depth);
+        self.pos = self.pos.max(start);
+        if position.stop() > start {
+            self.stops.push(Reverse(position.stop()));
+        }
+        self.last_start = start;
+        Ok(())
+    }
+}
+
+impl Iterator for GenomeCoverageIterator {
+    type Item = io::Result<DepthRun>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(r) = self.queue.pop_front() {
+                return Some(Ok(r));
+            }
+            if self.done {
+                return self.pending.take().map(Ok);
+            }
+            match self.inner.next_position(None) {
+                Some(Ok(p)) => {
+                    if let Err(e) = self.add(&p) {
+                        return Some(Err(e));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.done = true;
+                    self.finish_chromosomes_before(self.chromosomes.len());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chrom_ordering::parse_genome;
+    use crate::interval::Interval;
+
+    struct Intervals {
+        ivs: std::vec::IntoIter<Position>,
+    }
+
+    impl PositionedIterator for Intervals {
+        fn name(&self) -> String {
+            String::from("intervals")
+        }
+
+        fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+            self.ivs.next().map(Ok)
+        }
+    }
+
+    fn intervals(ivs: &[(&str, u64, u64)]) -> Box<dyn PositionedIterator> {
+        Box::new(Intervals {
+            ivs: ivs
+                .iter()
+                .map(|(chrom, start, stop)| {
+                    Position::Interval(Interval {
+                        chrom: String::from(*chrom),
+                        start: *start,
+                        stop: *stop,
+                        ..Default::default()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    fn runs(it: GenomeCoverageIterator) -> Vec<(String, u64, u64, u64)> {
+        it.map(|r| {
+            let r = r.expect("error in genome coverage");
+            (r.chrom, r.start, r.stop, r.depth)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_coverage_overlapping() {
+        // 10..30 has 5..15, 12..14 and 25..40 => depth 1 at 10..12, 14..15, 25..30; depth 2 at 12..14
+        let c = Coverage::new(10, 30, vec![(5, 15), (12, 14), (25, 40)]);
+        assert_eq!(c.n_overlaps, 3);
+        assert_eq!(c.length, 20);
+        assert_eq!(c.covered_bases, 10);
+        assert_eq!(c.histogram, vec![10, 8, 2]);
+        assert_eq!(c.fraction(), 0.5);
+        assert_eq!(c.mean_depth(), 12.0 / 20.0);
+    }
+
+    #[test]
+    fn test_coverage_empty() {
+        let c = Coverage::new(10, 30, vec![]);
+        assert_eq!(c.covered_bases, 0);
+        assert_eq!(c.histogram, vec![20]);
+        assert_eq!(c.fraction(), 0.0);
+
+        let c = Coverage::new(10, 10, vec![(5, 15)]);
+        assert_eq!(c.length, 0);
+        assert_eq!(c.fraction(), 0.0);
+    }
+
+    #[test]
+    fn test_genome_coverage() {
+        let genome = parse_genome("chr1\t100\nchr2\t50\nchr3\t20\n".as_bytes()).unwrap();
+        let ivs = intervals(&[
+            ("chr1", 10, 30),
+            ("chr1", 20, 40),
+            // book-ended with the previous interval at the same depth.
+            ("chr1", 40, 50),
+            ("chr3", 0, 20),
+        ]);
+        let r = runs(GenomeCoverageIterator::new(ivs, &genome, true).unwrap());
+        assert_eq!(
+            r,
+            vec![
+                (String::from("chr1"), 0, 10, 0),
+                (String::from("chr1"), 10, 20, 1),
+                (String::from("chr1"), 20, 30, 2),
+                (String::from("chr1"), 30, 50, 1),
+                (String::from("chr1"), 50, 100, 0),
+                (String::from("chr2"), 0, 50, 0),
+                (String::from("chr3"), 0, 20, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_genome_coverage_skip_zero() {
+        // lengths are not needed when zero-depth runs are not reported.
+        let genome = parse_genome("chr1\nchr2\n".as_bytes()).unwrap();
+        let ivs = intervals(&[("chr1", 10, 30), ("chr1", 10, 20), ("chr2", 5, 6)]);
+        let r = runs(GenomeCoverageIterator::new(ivs, &genome, false).unwrap());
+        assert_eq!(
+            r,
+            vec![
+                (String::from("chr1"), 10, 20, 2),
+                (String::from("chr1"), 20, 30, 1),
+                (String::from("chr2"), 5, 6, 1),
+            ]
+        );
+
+        assert!(GenomeCoverageIterator::new(intervals(&[]), &genome, true).is_err());
+    }
+
+    #[test]
+    fn test_coverage_identical_intervals() {
+        let c = Coverage::new(0, 10, vec![(0, 10), (0, 10)]);
+        assert_eq!(c.covered_bases, 10);
+        assert_eq!(c.histogram, vec![0, 0, 10]);
+        assert_eq!(c.mean_depth(), 2.0);
+    }
+}
@@ -29,6 +29,25 @@ pub enum Format {
     D4 = hts::htsExactFormat_d4_format,
 }
 
+impl Format {
+    /// The variant format implied by an output path: `.vcf` (optionally with `.gz` or `.bgz`)
+    /// or `.bcf`. None for other paths.
+    pub fn vcf_from_path(path: &str) -> Option<Self> {
+        let path = path.to_ascii_lowercase();
+        let path = path
+            .strip_suffix(".gz")
+            .or_else(|| path.strip_suffix(".bgz"))
+            .unwrap_or(&path);
+        if path.ends_with(".vcf") {
+            Some(Format::Vcf)
+        } else if path.ends_with(".bcf") {
+            Some(Format::Bcf)
+        } else {
+            None
+        }
+    }
+}
+
 impl From<Format> for hts::htsExactFormat {
     fn from(format: Format) -> Self {
         format as hts::htsExactFormat
@@ -101,6 +120,21 @@ pub enum Compression {
     Maximum = hts::htsCompression_compression_maximum,
 }
 
+impl Compression {
+    /// The compression implied by an output path: `.gz`, `.bgz` and `.bcf` outputs are
+    /// bgzipped so they can be indexed. Other paths (and `-`) are not compressed.
+    pub fn from_path(path: &str) -> Self {
+        let ext = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("gz" | "bgz" | "bcf") => Compression::Bgzf,
+            _ => Compression::None,
+        }
+    }
+}
+
 impl From<Compression> for hts::htsCompression {
     fn from(compression: Compression) -> Self {
         compression as hts::htsCompression
@@ -1,6 +1,6 @@
 use crate::chrom_ordering::Chromosome;
 use crate::report::Report;
-use crate::report_options::ReportOptions;
+use crate::report_options::{ClosestDirection, DistanceMode, ReportOptions, StrandMode, TieMode};
 use crate::string::String;
 use hashbrown::HashMap;
 use parking_lot::Mutex;
@@ -10,7 +10,7 @@ use std::io;
 use std::io::Error;
 use std::sync::Arc;
 
-use crate::position::{Position, PositionedIterator};
+use crate::position::{Position, PositionedIterator, Strand};
 
 /// An iterator that returns the intersection of multiple iterators.
 pub struct IntersectionIterator<'a> {
@@ -44,6 +44,54 @@ pub struct IntersectionIterator<'a> {
     /// Whether we can skip ahead when no overlaps are expected.
     /// This is true when we don't need to report every query interval (e.g. when a_piece is None or Piece).
     can_skip_ahead: bool,
+
+    /// strand_mode limits reported intervals to those on the same (or opposite) strand as the base interval.
+    /// This is applied here (and not only in the report) so that n_closest finds the closest stranded intervals.
+    strand_mode: StrandMode,
+
+    /// window extends each base interval before collecting overlapping intervals.
+    window: Option<QueryWindow>,
+
+    /// closest_direction limits non-overlapping closest intervals to those upstream or downstream
+    /// of the base interval, as signed by distance_mode.
+    closest_direction: ClosestDirection,
+    distance_mode: DistanceMode,
+
+    /// tie_mode decides which equidistant intervals are kept when they don't all fit in n_closest.
+    tie_mode: TieMode,
+}
+
+/// QueryWindow extends each base interval by `left` bases before its start and `right` bases
+/// after its end before collecting overlapping intervals (like `bedtools window`). The base
+/// interval itself is reported unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryWindow {
+    pub left: u64,
+    pub right: u64,
+    /// `left` is upstream and `right` is downstream; they are swapped for base intervals on
+    /// the reverse strand.
+    pub stranded: bool,
+}
+
+impl QueryWindow {
+    /// The extended (start, stop) of `p`.
+    pub fn bounds(&self, p: &Position) -> (u64, u64) {
+        let (left, right) = if self.stranded && p.strand() == Strand::Reverse {
+            (self.right, self.left)
+        } else {
+            (self.left, self.right)
+        };
+        (p.start().saturating_sub(left), p.stop() + right)
+    }
+
+    // the most that any base interval is extended before its start.
+    fn max_left(&self) -> u64 {
+        if self.stranded {
+            self.left.max(self.right)
+        } else {
+            self.left
+        }
+    }
 }
 
 /// An Intersection wraps the Positioned that was intersected with a unique identifier.
@@ -73,6 +121,9 @@ pub(crate) type CachedReport = Arc<Mutex<Option<(ReportOptions, Arc<Report>)>>>;
 pub struct Intersections {
     pub base_interval: Arc<Mutex<Position>>,
     pub overlapping: Vec<Intersection>,
+    /// the (start, stop) used to collect `overlapping` when the base interval was extended
+    /// by a `QueryWindow`. Overlap requirements and B pieces are measured against it.
+    pub query_window: Option<(u64, u64)>,
 
     // report cache, keyed by report_options. Use Arc Mutex for interior mutability.
     pub(crate) cached_report: CachedReport,
@@ -87,6 +138,7 @@ struct QueueEntry {
     chrom_index: usize,
     start: u64,
     stop: u64,
+    strand: Strand,
 }
 
 struct ReverseOrderPosition {
@@ -209,16 +261,28 @@ impl Iterator for IntersectionIterator<'_> {
         let base_chrom_idx = self.chromosome_order[base_chrom].index;
         let base_start = base_interval_locked.start();
         let base_stop = base_interval_locked.stop();
+        let base_strand = base_interval_locked.strand();
+        let strand_mode = self.strand_mode;
+        // the window only applies in overlap mode.
+        let query_window = if self.n_closest <= 0 && self.max_distance <= 0 {
+            self.window.map(|w| w.bounds(&base_interval_locked))
+        } else {
+            None
+        };
         if self.n_closest <= 0 && self.max_distance <= 0 {
+            let (query_start, query_stop) = query_window.unwrap_or((base_start, base_stop));
             for q in self.dequeue.iter() {
                 if q.chrom_index < base_chrom_idx
-                    || (q.chrom_index == base_chrom_idx && q.stop <= base_start)
+                    || (q.chrom_index == base_chrom_idx && q.stop <= query_start)
                 {
                     continue;
                 }
-                if q.chrom_index > base_chrom_idx || q.start >= base_stop {
+                if q.chrom_index > base_chrom_idx || q.start >= query_stop {
                     break;
                 }
+                if !strand_mode.accepts(base_strand, q.strand) {
+                    continue;
+                }
                 overlapping_positions.push(q.intersection.clone());
             }
         } else {
@@ -247,9 +311,13 @@ impl Iterator for IntersectionIterator<'_> {
             let mut after_ptr = split_point;
 
             // 3. Collect all overlapping intervals first. They have distance 0.
+            // Candidates are kept as indexes into `dequeue`, which is in genome order.
+            let mut overlap_indexes = Vec::new();
             while let Some(q) = self.dequeue.get(after_ptr) {
                 if q.chrom_index == base_chrom_idx && q.start < base_stop {
-                    overlapping_positions.push(q.intersection.clone());
+                    if strand_mode.accepts(base_strand, q.strand) {
+                        overlap_indexes.push(after_ptr);
+                    }
                     after_ptr += 1;
                 } else {
                     break;
@@ -258,10 +326,14 @@ impl Iterator for IntersectionIterator<'_> {
 
             // 4. If we still need more intervals (for n_closest), get them from `before` and `after` parts of dequeue.
             if self.n_closest > 0 {
-                let mut closest_candidates = Vec::new();
-                closest_candidates.extend(overlapping_positions.into_iter().map(|o| (0, o)));
-
-                while closest_candidates.len() < self.n_closest as usize {
+                let n_closest = self.n_closest as usize;
+                let mut closest_candidates: Vec<(u64, usize)> =
+                    overlap_indexes.into_iter().map(|i| (0, i)).collect();
+                // the n_closest smallest candidate distances; the largest is the n-th closest.
+                let mut nearest: BinaryHeap<u64> =
+                    vec![0; closest_candidates.len().min(n_closest)].into();
+
+                loop {
                     let before_o = before_ptr.and_then(|p| self.dequeue.get(p));
                     let after_o = self.dequeue.get(after_ptr);
 
@@ -297,20 +369,65 @@ impl Iterator for IntersectionIterator<'_> {
                         break;
                     }
 
-                    if dist_l <= dist_r {
-                        closest_candidates.push((dist_l, before_o.unwrap().intersection.clone()));
-                        before_ptr = before_ptr.unwrap().checked_sub(1);
+                    if nearest.len() == n_closest {
+                        // with ties, keep going while the next interval could tie the n-th closest.
+                        if self.tie_mode == TieMode::First {
+                            break;
+                        }
+                        if nearest.peek().is_some_and(|nth| dist_l.min(dist_r) > *nth) {
+                            break;
+                        }
+                    }
+
+                    let candidate = if dist_l <= dist_r {
+                        let p = before_ptr.unwrap();
+                        let o = before_o.unwrap();
+                        before_ptr = p.checked_sub(1);
+                        (strand_mode.accepts(base_strand, o.strand)
+                            && self.direction_accepts(base_strand, o.strand, true))
+                        .then_some((dist_l, p))
                     } else {
-                        closest_candidates.push((dist_r, after_o.unwrap().intersection.clone()));
+                        let p = after_ptr;
+                        let o = after_o.unwrap();
                         after_ptr += 1;
+                        (strand_mode.accepts(base_strand, o.strand)
+                            && self.direction_accepts(base_strand, o.strand, false))
+                        .then_some((dist_r, p))
+                    };
+                    if let Some((dist, i)) = candidate {
+                        closest_candidates.push((dist, i));
+                        nearest.push(dist);
+                        if nearest.len() > n_closest {
+                            nearest.pop();
+                        }
                     }
                 }
 
-                // Candidates are gathered from queue metadata; sort by cached distance.
-                closest_candidates.sort_by_key(|(dist, _)| *dist);
-                closest_candidates.truncate(self.n_closest as usize);
-                overlapping_positions = closest_candidates.into_iter().map(|(_, o)| o).collect();
+                // Candidates are gathered from queue metadata; sort by cached distance, then genome order.
+                closest_candidates.sort_unstable();
+                if closest_candidates.len() > n_closest {
+                    let nth = closest_candidates[n_closest - 1].0;
+                    match self.tie_mode {
+                        TieMode::First => closest_candidates.truncate(n_closest),
+                        TieMode::All => closest_candidates.retain(|(d, _)| *d <= nth),
+                        TieMode::Last => {
+                            // keep the last of the intervals tied with the n-th closest.
+                            let closer = closest_candidates.partition_point(|(d, _)| *d < nth);
+                            let tied = closest_candidates.partition_point(|(d, _)| *d <= nth);
+                            closest_candidates.truncate(tied);
+                            closest_candidates.drain(closer..tied - (n_closest - closer));
+                        }
+                    }
+                }
+                overlapping_positions = closest_candidates
+                    .into_iter()
+                    .map(|(_, i)| self.dequeue[i].intersection.clone())
+                    .collect();
             } else {
+                overlapping_positions = overlap_indexes
+                    .into_iter()
+                    .map(|i| self.dequeue[i].intersection.clone())
+                    .collect();
                 // n_closest is 0, but max_distance is set. Collect all within distance.
                 while let Some(p) = before_ptr {
                     let o = &self.dequeue[p];
@@ -318,7 +435,11 @@ impl Iterator for IntersectionIterator<'_> {
                         debug_assert!(base_start >= o.stop);
                         let dist = base_start - o.stop;
                         if self.max_distance >= 0 && dist <= self.max_distance as u64 {
-                            overlapping_positions.push(o.intersection.clone());
+                            if strand_mode.accepts(base_strand, o.strand)
+                                && self.direction_accepts(base_strand, o.strand, true)
+                            {
+                                overlapping_positions.push(o.intersection.clone());
+                            }
                         } else {
                             break;
                         }
@@ -332,7 +453,11 @@ impl Iterator for IntersectionIterator<'_> {
                         debug_assert!(o.start >= base_stop);
                         let dist = o.start - base_stop;
                         if self.max_distance >= 0 && dist <= self.max_distance as u64 {
-                            overlapping_positions.push(o.intersection.clone());
+                            if strand_mode.accepts(base_strand, o.strand)
+                                && self.direction_accepts(base_strand, o.strand, false)
+                            {
+                                overlapping_positions.push(o.intersection.clone());
+                            }
                             after_ptr += 1;
                         } else {
                             break;
@@ -358,6 +483,7 @@ impl Iterator for IntersectionIterator<'_> {
         Some(Ok(Intersections {
             base_interval: Arc::clone(&base_interval),
             overlapping: overlapping_positions,
+            query_window,
             cached_report: Arc::new(Mutex::new(None)),
         }))
     }
@@ -387,9 +513,64 @@ impl<'a> IntersectionIterator<'a> {
             max_distance,
             n_closest,
             can_skip_ahead,
+            strand_mode: StrandMode::Ignore,
+            window: None,
+            closest_direction: ClosestDirection::Both,
+            distance_mode: DistanceMode::Ref,
+            tie_mode: TieMode::First,
         })
     }
 
+    /// Only report intervals whose strand is compatible with the base interval.
+    pub fn with_strand_mode(mut self, strand_mode: StrandMode) -> Self {
+        self.strand_mode = strand_mode;
+        self
+    }
+
+    /// Collect intervals overlapping each base interval extended by `window`.
+    /// This applies only to overlap mode (`max_distance` and `n_closest` <= 0).
+    pub fn with_window(mut self, window: QueryWindow) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Only report non-overlapping closest intervals in `direction` from the base interval,
+    /// where upstream and downstream are determined by `mode`.
+    /// This applies only to closest mode (`max_distance` or `n_closest` > 0).
+    pub fn with_closest_direction(
+        mut self,
+        direction: ClosestDirection,
+        mode: DistanceMode,
+    ) -> Self {
+        self.closest_direction = direction;
+        self.distance_mode = mode;
+        self
+    }
+
+    /// Choose which equidistant intervals are reported when they don't all fit in `n_closest`.
+    pub fn with_ties(mut self, tie_mode: TieMode) -> Self {
+        self.tie_mode = tie_mode;
+        self
+    }
+
+    // can a non-overlapping interval with strand `b` before (or after) the base interval be
+    // reported given the closest direction?
+    #[inline]
+    fn direction_accepts(&self, a: Strand, b: Strand, b_before_a: bool) -> bool {
+        let distance = if b_before_a { -1 } else { 1 };
+        self.closest_direction
+            .accepts(self.distance_mode.orient(distance, a, b))
+    }
+
+    // the (start, stop) of the base interval used to collect overlaps.
+    #[inline]
+    fn query_bounds(&self, base_interval: &Position) -> (u64, u64) {
+        match &self.window {
+            Some(w) => w.bounds(base_interval),
+            None => (base_interval.start(), base_interval.stop()),
+        }
+    }
+
     /// Peek at the next position from the min heap without consuming it
     #[inline]
     fn peek_next_heap_position(&self) -> Option<&Position> {
@@ -398,7 +579,7 @@ impl<'a> IntersectionIterator<'a> {
 
     /// Determine if we should skip ahead in the base iterator based on the next heap position
     fn should_skip_ahead(&self) -> Option<Position> {
-        if !self.can_skip_ahead {
+        if !self.can_skip_ahead || self.window.is_some() {
             return None;
         }
 
@@ -562,7 +743,10 @@ impl<'a> IntersectionIterator<'a> {
     /// historical intervals because they may still be among the closest.
     fn pop_front(&mut self, base_interval: &Position) {
         let base_chrom_idx = self.chromosome_order[base_interval.chrom()].index;
-        let base_start = base_interval.start();
+        // with a window, keep intervals that could be in the window of a later base interval.
+        let base_start = base_interval
+            .start()
+            .saturating_sub(self.window.map_or(0, |w| w.max_left()));
         loop {
             let should_pop = if let Some(interval) = self.dequeue.front() {
                 if interval.chrom_index != base_chrom_idx {
@@ -645,7 +829,7 @@ impl<'a> IntersectionIterator<'a> {
                 .expect("failed to lock base_interval");
             let arg: Option<&Position> = if !self.called[file_index] {
                 self.called[file_index] = true;
-                if self.max_distance <= 0 && position.start() > l.stop() {
+                if self.max_distance <= 0 && self.window.is_none() && position.start() > l.stop() {
                     // if the position interval is after the base interval, then we can use it for the query.
                     // NOTE: we can do other things here instead like require some distance to minimize expensive queries.
                     Some(&l)
@@ -707,6 +891,7 @@ impl<'a> IntersectionIterator<'a> {
             // and we must always add the position to the Q
             let position_start = position.start();
             let position_stop = position.stop();
+            let position_strand = position.strand();
             let rc_pos = Arc::new(Mutex::new(position));
             let intersection = Intersection {
                 interval: rc_pos.clone(),
@@ -717,17 +902,19 @@ impl<'a> IntersectionIterator<'a> {
                 chrom_index: chromosome_index,
                 start: position_start,
                 stop: position_stop,
+                strand: position_strand,
             });
 
             // if this position is after base_interval, we can stop pulling through heap
             // (but for n_closest, we need to keep pulling to get enough "after" intervals)
-            let (base_chrom_idx, base_stop) = {
+            let (base_chrom_idx, base_stop, base_strand) = {
                 let base_locked = base_interval
                     .try_lock()
                     .expect("failed to lock base_interval");
                 (
                     self.chromosome_order[base_locked.chrom()].index,
-                    base_locked.stop(),
+                    self.query_bounds(&base_locked).1,
+                    base_locked.strand(),
                 )
             };
             let should_break = if (base_chrom_idx < chromosome_index)
@@ -735,15 +922,31 @@ impl<'a> IntersectionIterator<'a> {
             {
                 if self.n_closest <= 0 {
                     true
+                } else if self.distance_mode != DistanceMode::B
+                    && !self.direction_accepts(base_strand, Strand::Unknown, false)
+                {
+                    // intervals after the base interval can't be reported so there's no need to pull them.
+                    true
                 } else {
                     // For n_closest, count how many intervals we have after base_interval.
                     let after_count = self
                         .dequeue
                         .iter()
-                        .filter(|o| o.chrom_index == base_chrom_idx && o.start >= base_stop)
+                        .filter(|o| {
+                            o.chrom_index == base_chrom_idx
+                                && o.start >= base_stop
+                                && self.strand_mode.accepts(base_strand, o.strand)
+                                && self.direction_accepts(base_strand, o.strand, false)
+                        })
                         .count();
-                    // Stop if we have enough intervals after the base.
+                    // Stop if we have enough intervals after the base, unless the next interval
+                    // ties with this one and ties may be kept.
                     after_count >= self.n_closest as usize
+                        && (self.tie_mode == TieMode::First
+                            || !self.min_heap.peek().is_some_and(|rop| {
+                                rop.chromosome_index == chromosome_index
+                                    && rop.position.start() == position_start
+                            }))
                 }
             } else {
                 false
@@ -1213,6 +1416,7 @@ mod tests {
                 chrom_index,
                 start,
                 stop,
+                strand: Strand::Unknown,
             });
         }
 
@@ -1272,6 +1476,7 @@ mod tests {
                 chrom_index,
                 start,
                 stop,
+                strand: Strand::Unknown,
             });
         }
         let base_interval2 = Position::Interval(Interval {
@@ -2039,6 +2244,187 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_window_collects_nearby_intervals() {
+        let chrom_order = parse_genome("chr1\n".as_bytes()).unwrap();
+        let iv = |start, stop| Interval {
+            chrom: String::from("chr1"),
+            start,
+            stop,
+            ..Default::default()
+        };
+        let base_ivs = Intervals::new(String::from("base"), vec![iv(100, 110), iv(300, 310)]);
+        let db_ivs = Intervals::new(
+            String::from("db"),
+            vec![
+                iv(50, 60),
+                iv(85, 95),
+                iv(115, 120),
+                iv(200, 210),
+                iv(305, 306),
+                iv(330, 340),
+            ],
+        );
+
+        let iter = IntersectionIterator::new(
+            Box::new(base_ivs),
+            vec![Box::new(db_ivs)],
+            &chrom_order,
+            -1,
+            -1,
+            false,
+        )
+        .expect("error getting iterator")
+        .with_window(QueryWindow {
+            left: 20,
+            right: 20,
+            stranded: false,
+        });
+
+        let results: Vec<_> = iter
+            .map(|r| {
+                let r = r.expect("intersection error");
+                let base = r.base_interval.try_lock().unwrap();
+                let overlapping: Vec<(u64, u64)> = r
+                    .overlapping
+                    .iter()
+                    .map(|o| {
+                        let o = o.interval.try_lock().unwrap();
+                        (o.start(), o.stop())
+                    })
+                    .collect();
+                ((base.start(), base.stop()), r.query_window, overlapping)
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ((100, 110), Some((80, 130)), vec![(85, 95), (115, 120)]),
+                ((300, 310), Some((280, 330)), vec![(305, 306)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stranded_window_bounds() {
+        use crate::bedder_bed::BedRecord;
+        let w = QueryWindow {
+            left: 100,
+            right: 10,
+            stranded: true,
+        };
+        let bed = |strand: &str| {
+            Position::Bed(BedRecord::new(
+                "chr1",
+                500,
+                600,
+                None,
+                None,
+                vec![strand.into()],
+            ))
+        };
+        assert_eq!(w.bounds(&bed("+")), (400, 610));
+        assert_eq!(w.bounds(&bed("-")), (490, 700));
+        assert_eq!(w.bounds(&bed(".")), (400, 610));
+        let w = QueryWindow {
+            stranded: false,
+            ..w
+        };
+        assert_eq!(w.bounds(&bed("-")), (400, 610));
+    }
+
+    fn closest_coords(
+        db: Vec<Interval>,
+        max_distance: i64,
+        n_closest: i64,
+        configure: impl Fn(IntersectionIterator) -> IntersectionIterator,
+    ) -> Vec<(u64, u64)> {
+        let chrom_order = parse_genome("chr1\n".as_bytes()).unwrap();
+        let base_ivs = Intervals::new(
+            String::from("base"),
+            vec![Interval {
+                chrom: String::from("chr1"),
+                start: 100,
+                stop: 110,
+                ..Default::default()
+            }],
+        );
+        let iter = IntersectionIterator::new(
+            Box::new(base_ivs),
+            vec![Box::new(Intervals::new(String::from("db"), db))],
+            &chrom_order,
+            max_distance,
+            n_closest,
+            false,
+        )
+        .expect("error getting iterator");
+        let mut coords = vec![];
+        for r in configure(iter) {
+            let r = r.expect("intersection error");
+            for o in r.overlapping.iter() {
+                let o = o.interval.try_lock().unwrap();
+                coords.push((o.start(), o.stop()));
+            }
+        }
+        coords
+    }
+
+    #[test]
+    fn test_closest_ties() {
+        let iv = |start, stop| Interval {
+            chrom: String::from("chr1"),
+            start,
+            stop,
+            ..Default::default()
+        };
+        // (80, 90) and (120, 130) are both 10 bases from (100, 110).
+        let db = || vec![iv(80, 90), iv(120, 130), iv(130, 140)];
+
+        let first = closest_coords(db(), -1, 1, |it| it);
+        assert_eq!(first, vec![(80, 90)]);
+        let last = closest_coords(db(), -1, 1, |it| it.with_ties(TieMode::Last));
+        assert_eq!(last, vec![(120, 130)]);
+        let all = closest_coords(db(), -1, 1, |it| it.with_ties(TieMode::All));
+        assert_eq!(all, vec![(80, 90), (120, 130)]);
+
+        // ties beyond n are only kept when they tie with the n-th closest.
+        let all = closest_coords(db(), -1, 2, |it| it.with_ties(TieMode::All));
+        assert_eq!(all, vec![(80, 90), (120, 130)]);
+
+        // intervals before the base are not in order of distance: (85, 90) is found before
+        // (50, 98) and stops being the 2nd closest once (50, 98) is found.
+        let db = vec![iv(50, 98), iv(85, 90), iv(115, 120)];
+        let all = closest_coords(db, -1, 2, |it| it.with_ties(TieMode::All));
+        assert_eq!(all, vec![(50, 98), (115, 120)]);
+    }
+
+    #[test]
+    fn test_closest_direction() {
+        let iv = |start, stop| Interval {
+            chrom: String::from("chr1"),
+            start,
+            stop,
+            ..Default::default()
+        };
+        let db = || vec![iv(80, 90), iv(105, 108), iv(112, 115)];
+
+        let upstream = closest_coords(db(), -1, 2, |it| {
+            it.with_closest_direction(ClosestDirection::Upstream, DistanceMode::Ref)
+        });
+        // overlapping intervals are always reported.
+        assert_eq!(upstream, vec![(105, 108), (80, 90)]);
+
+        let downstream = closest_coords(db(), -1, 2, |it| {
+            it.with_closest_direction(ClosestDirection::Downstream, DistanceMode::Ref)
+        });
+        assert_eq!(downstream, vec![(105, 108), (112, 115)]);
+
+        let within = closest_coords(db(), 50, -1, |it| {
+            it.with_closest_direction(ClosestDirection::Upstream, DistanceMode::Ref)
+        });
+        assert_eq!(within, vec![(105, 108), (80, 90)]);
+    }
+
     #[cfg(test)]
     mod calculate_skip_position_tests {
         use super::*;
@@ -2094,6 +2480,11 @@ mod tests {
                 max_distance,
                 n_closest,
                 can_skip_ahead: true,
+                strand_mode: StrandMode::Ignore,
+                window: None,
+                closest_direction: ClosestDirection::Both,
+                distance_mode: DistanceMode::Ref,
+                tie_mode: TieMode::First,
             }
         }
 
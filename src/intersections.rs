@@ -1,25 +1,41 @@
+use crate::coverage::Coverage;
 use crate::intersection::{Intersection, Intersections};
 use crate::position::Position;
 use crate::report::{Report, ReportFragment};
-use crate::report_options::{IntersectionMode, IntersectionPart, OverlapAmount, ReportOptions};
+use crate::report_options::{
+    IntersectionMode, IntersectionPart, OverlapAmount, ReportOptions, StrandMode,
+};
 #[allow(unused_imports)]
 use crate::string::String;
 use parking_lot::Mutex;
 use std::sync::Arc;
 
-/// Extract pieces of base_interval that do no overlap overlaps
+/// Extract pieces of base_interval that do no overlap overlaps.
+/// Overlaps need not be sorted and may overlap each other; they are merged before subtracting.
 fn inverse(base_interval: &Position, overlaps: &[Intersection]) -> Vec<Arc<Mutex<Position>>> {
+    let mut bounds: Vec<(u64, u64)> = overlaps
+        .iter()
+        .map(|o| {
+            let o = o.interval.try_lock().expect("failed to lock interval");
+            (o.start(), o.stop())
+        })
+        .collect();
+    bounds.sort_unstable();
+
     let mut last_start = base_interval.start();
     let mut result = Vec::new();
-    for o in overlaps {
-        let o = o.interval.try_lock().expect("failed to lock interval");
-        if o.start() > last_start {
+    for (start, stop) in bounds {
+        if start > last_start {
             let mut p = base_interval.clone_box();
             p.set_start(last_start);
-            p.set_stop(o.start());
+            p.set_stop(start.min(base_interval.stop()));
             result.push(Arc::new(Mutex::new(p)))
         }
-        last_start = o.stop();
+        // an overlap contained in an earlier one must not move last_start backwards.
+        last_start = last_start.max(stop);
+        if last_start >= base_interval.stop() {
+            break;
+        }
     }
     if last_start < base_interval.stop() {
         let mut p = base_interval.clone_box();
@@ -29,10 +45,73 @@ fn inverse(base_interval: &Position, overlaps: &[Intersection]) -> Vec<Arc<Mutex
     result
 }
 
+/// The parts of start..stop that are not covered by the sorted `bounds`, which may overlap.
+fn uncovered(start: u64, stop: u64, bounds: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut last_start = start;
+    let mut result = Vec::new();
+    for &(b_start, b_stop) in bounds {
+        if b_start > last_start {
+            result.push((last_start, b_start.min(stop)));
+        }
+        last_start = last_start.max(b_stop);
+        if last_start >= stop {
+            return result;
+        }
+    }
+    result.push((last_start, stop));
+    result
+}
+
+/// (start, stop) of the blocks of `p`: its BED12 blocks with `split`, otherwise its whole span.
+fn blocks_of(p: &Position, split: bool) -> Vec<(u64, u64)> {
+    if split {
+        if let Some(blocks) = p.blocks() {
+            return blocks;
+        }
+    }
+    vec![(p.start(), p.stop())]
+}
+
+/// The pieces where two sorted, non-overlapping sets of blocks overlap each other.
+fn intersect_blocks(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let stop = a[i].1.min(b[j].1);
+        if start < stop {
+            result.push((start, stop));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+#[inline]
+fn blocks_length(blocks: &[(u64, u64)]) -> u64 {
+    blocks.iter().map(|(start, stop)| stop - start).sum()
+}
+
+/// A copy of `p` limited to start..stop. A BED12 record gets a single block so that its
+/// blocks stay within its bounds.
+fn block_piece(p: &Position, start: u64, stop: u64) -> Position {
+    let mut piece = p.clone_box();
+    piece.set_start(start);
+    piece.set_stop(stop);
+    if let Position::Bed(b) = &mut piece {
+        b.set_blocks(&[(start, stop)]);
+    }
+    piece
+}
+
 // with a-piece, we changed the bounds of the interval so we need to then limit b intervals
-// to those that still overlap the new bounds.
+// to those that still overlap the new bounds. With `split`, b must overlap with a block.
 // Takes &mut result as it modifies the b vectors in place.
-fn filter_part_overlaps(result: &mut [ReportFragment]) {
+fn filter_part_overlaps(result: &mut [ReportFragment], split: bool) {
     // No need for initial assert, it doesn't prevent the deadlock scenario where 'a' and 'b'
     // within the same fragment point to the same locked Arc.
 
@@ -66,7 +145,13 @@ fn filter_part_overlaps(result: &mut [ReportFragment]) {
 
                 // drop(b) happens here automatically when guard goes out of scope
                 // Keep b only if it overlaps with a
-                a.start() <= b.stop() && a.stop() >= b.start()
+                if split {
+                    blocks_of(&b, true)
+                        .iter()
+                        .any(|&(start, stop)| a.start() < stop && a.stop() > start)
+                } else {
+                    a.start() <= b.stop() && a.stop() >= b.start()
+                }
             }
         });
         // drop(a) happens here automatically when guard goes out of scope
@@ -98,13 +183,44 @@ impl Intersections {
         let max_id = self.overlapping.iter().map(|o| o.id).max().unwrap_or(0);
         let mut grouped_intersections = vec![vec![]; max_id as usize + 1];
 
+        let base_strand = self
+            .base_interval
+            .try_lock()
+            .expect("failed to lock interval")
+            .strand();
+
         // Group overlaps by Intersection.id
         // since all constraints on overlap are per source.
         // TODO: avoid this allocation by filtering on id in push_overlap_fragments
         for intersection in &self.overlapping {
+            if report_options.strand != StrandMode::Ignore {
+                let b_strand = intersection
+                    .interval
+                    .try_lock()
+                    .expect("failed to lock interval")
+                    .strand();
+                if !report_options.strand.accepts(base_strand, b_strand) {
+                    continue;
+                }
+            }
+            if report_options.split {
+                let (query_start, query_stop) = self.query_bounds();
+                let b = intersection
+                    .interval
+                    .try_lock()
+                    .expect("failed to lock interval");
+                let in_span = b.start() < query_stop && b.stop() > query_start;
+                drop(b);
+                // an interval that is only in the gaps between blocks (e.g. an intron) does
+                // not overlap.
+                if in_span && self.query_overlap(intersection.interval.clone(), true) == 0 {
+                    continue;
+                }
+            }
             grouped_intersections[intersection.id as usize].push(intersection.clone());
         }
-        if grouped_intersections.iter().any(|v| !v.is_empty()) {
+        let has_overlaps = grouped_intersections.iter().any(|v| !v.is_empty());
+        if has_overlaps {
             log::trace!("grouped_intersections: {:?}", grouped_intersections);
         }
 
@@ -114,51 +230,37 @@ impl Intersections {
                 // each b_interval must go with the a_piece that it overlaps.
                 let mut b_satisified = vec![];
                 for b_interval in overlaps {
-                    let bases_overlap = self
-                        .calculate_overlap(self.base_interval.clone(), b_interval.interval.clone());
-                    let base = self
-                        .base_interval
-                        .try_lock()
-                        .expect("failed to lock interval");
+                    let bases_overlap =
+                        self.query_overlap(b_interval.interval.clone(), report_options.split);
+                    let query_length = self.query_length(report_options.split);
                     let b = b_interval
                         .interval
                         .try_lock()
                         .expect("failed to lock interval");
                     if Intersections::satisfies_requirements(
                         bases_overlap,
-                        base.stop() - base.start(),
+                        query_length,
                         &report_options.a_requirements,
                         &report_options.a_mode,
                     ) && Intersections::satisfies_requirements(
                         bases_overlap,
-                        b.stop() - b.start(),
+                        blocks_length(&blocks_of(&b, report_options.split)),
                         &report_options.b_requirements,
                         &report_options.b_mode,
                     ) {
                         drop(b);
-                        drop(base);
                         b_satisified.push(b_interval.clone());
                     }
                 }
                 if !b_satisified.is_empty() {
-                    self.push_overlap_fragments(
-                        &mut result,
-                        &b_satisified,
-                        &report_options.a_piece,
-                        &report_options.b_piece,
-                        b_idx,
-                    );
+                    self.push_overlap_fragments(&mut result, &b_satisified, report_options, b_idx);
                 }
             } else {
                 // Calculate cumulative overlap and sum of lengths for this group
                 let total_bases_overlap = self.calculate_total_overlap(overlaps, report_options);
-                let base = self
-                    .base_interval
-                    .try_lock()
-                    .expect("failed to lock interval");
                 if Intersections::satisfies_requirements(
                     total_bases_overlap,
-                    base.stop() - base.start(),
+                    self.query_length(report_options.split),
                     &report_options.a_requirements,
                     &report_options.a_mode,
                 ) && Intersections::satisfies_requirements(
@@ -167,36 +269,23 @@ impl Intersections {
                         .iter()
                         .map(|o| {
                             let ov = o.interval.try_lock().expect("failed to lock interval");
-                            ov.stop() - ov.start()
+                            blocks_length(&blocks_of(&ov, report_options.split))
                         })
                         .sum(),
                     &report_options.b_requirements,
                     &report_options.b_mode,
                 ) {
-                    drop(base);
-                    self.push_overlap_fragments(
-                        &mut result,
-                        overlaps,
-                        &report_options.a_piece,
-                        &report_options.b_piece,
-                        b_idx,
-                    );
+                    self.push_overlap_fragments(&mut result, overlaps, report_options, b_idx);
                 }
             }
         }
-        if report_options.a_mode == IntersectionMode::Not && self.overlapping.is_empty() {
-            self.push_overlap_fragments(
-                &mut result,
-                &[],
-                &report_options.a_piece,
-                &report_options.b_piece,
-                usize::MAX,
-            );
+        if report_options.a_mode == IntersectionMode::Not && !has_overlaps {
+            self.push_overlap_fragments(&mut result, &[], report_options, usize::MAX);
         }
 
         if matches!(report_options.a_piece, IntersectionPart::Piece) {
             // Pass as mutable reference now
-            filter_part_overlaps(&mut result);
+            filter_part_overlaps(&mut result, report_options.split);
         }
 
         let report = Arc::new(Report::new(result));
@@ -204,6 +293,100 @@ impl Intersections {
         report
     }
 
+    /// Coverage of the base interval by all overlapping intervals (from every source).
+    pub fn coverage(&self) -> Coverage {
+        let base = self
+            .base_interval
+            .try_lock()
+            .expect("failed to lock interval");
+        Coverage::new(
+            base.start(),
+            base.stop(),
+            self.overlapping.iter().map(|o| {
+                let o = o.interval.try_lock().expect("failed to lock interval");
+                (o.start(), o.stop())
+            }),
+        )
+    }
+
+    /// Report the pieces of the base interval that are not covered by any overlapping interval.
+    ///
+    /// Unlike `report` with `IntersectionPart::Inverse`, overlaps from all sources are merged
+    /// before subtracting. An overlap is only subtracted if it is accepted by the strand mode and
+    /// the single overlapping interval satisfies both `a_requirements` and `b_requirements`.
+    /// With `split`, overlaps and lengths are measured over blocks and the pieces are the parts
+    /// of each base block not covered by a block of a qualifying overlap.
+    /// If `remove_whole` is true, the base interval is reported unchanged when nothing qualifies
+    /// and not at all otherwise (bedtools subtract -A).
+    pub fn subtract(&self, report_options: &ReportOptions, remove_whole: bool) -> Arc<Report> {
+        let split = report_options.split;
+        let base = self
+            .base_interval
+            .try_lock()
+            .expect("failed to lock interval");
+        let base_blocks = blocks_of(&base, split);
+        let base_length = blocks_length(&base_blocks);
+        let base_strand = base.strand();
+
+        let qualifying: Vec<Intersection> = self
+            .overlapping
+            .iter()
+            .filter(|o| {
+                let b = o.interval.try_lock().expect("failed to lock interval");
+                let b_blocks = blocks_of(&b, split);
+                let bases_overlap = blocks_length(&intersect_blocks(&base_blocks, &b_blocks));
+                report_options.strand.accepts(base_strand, b.strand())
+                    && Intersections::satisfies_requirements(
+                        bases_overlap,
+                        base_length,
+                        &report_options.a_requirements,
+                        &IntersectionMode::Default,
+                    )
+                    && Intersections::satisfies_requirements(
+                        bases_overlap,
+                        blocks_length(&b_blocks),
+                        &report_options.b_requirements,
+                        &IntersectionMode::Default,
+                    )
+            })
+            .cloned()
+            .collect();
+
+        let pieces = if qualifying.is_empty() {
+            vec![Arc::new(Mutex::new(base.clone_box()))]
+        } else if remove_whole {
+            vec![]
+        } else if split {
+            let mut bounds: Vec<(u64, u64)> = qualifying
+                .iter()
+                .flat_map(|o| {
+                    let b = o.interval.try_lock().expect("failed to lock interval");
+                    blocks_of(&b, true)
+                })
+                .collect();
+            bounds.sort_unstable();
+            base_blocks
+                .iter()
+                .flat_map(|&(start, stop)| uncovered(start, stop, &bounds))
+                .map(|(start, stop)| Arc::new(Mutex::new(block_piece(&base, start, stop))))
+                .collect()
+        } else {
+            inverse(&base, &qualifying)
+        };
+        drop(base);
+
+        Arc::new(Report::new(
+            pieces
+                .into_iter()
+                .map(|a| ReportFragment {
+                    a: Some(a),
+                    b: vec![],
+                    id: 0,
+                })
+                .collect(),
+        ))
+    }
+
     fn satisfies_requirements(
         bases_overlap: u64,
         interval_length: u64,
@@ -229,6 +412,71 @@ impl Intersections {
         }
     }
 
+    /// (start, stop) of the query: the base interval, or its window when one was used.
+    #[inline]
+    fn query_bounds(&self) -> (u64, u64) {
+        match self.query_window {
+            Some(bounds) => bounds,
+            None => {
+                let base = self
+                    .base_interval
+                    .try_lock()
+                    .expect("failed to lock interval");
+                (base.start(), base.stop())
+            }
+        }
+    }
+
+    /// Blocks of the query: its window when one was used, otherwise the blocks of the base
+    /// interval (see `blocks_of`).
+    fn query_blocks(&self, split: bool) -> Vec<(u64, u64)> {
+        match self.query_window {
+            Some(bounds) => vec![bounds],
+            None => blocks_of(
+                &self
+                    .base_interval
+                    .try_lock()
+                    .expect("failed to lock interval"),
+                split,
+            ),
+        }
+    }
+
+    /// Length of the query. With `split`, only the bases in blocks are counted.
+    #[inline]
+    fn query_length(&self, split: bool) -> u64 {
+        if split {
+            blocks_length(&self.query_blocks(true))
+        } else {
+            let (query_start, query_stop) = self.query_bounds();
+            query_stop - query_start
+        }
+    }
+
+    /// Bases of `interval` in the blocks of the query that are also in the blocks of `interval`
+    /// (see `ReportOptions::split`). Zero for an interval that is only in an intron of the query.
+    pub fn block_overlap(&self, interval: &Arc<Mutex<Position>>) -> u64 {
+        self.query_overlap(interval.clone(), true)
+    }
+
+    /// Bases of `interval_b` that overlap the query (see `query_bounds`).
+    /// With `split`, only bases in the blocks of both are counted.
+    #[inline]
+    fn query_overlap(&self, interval_b: Arc<Mutex<Position>>, split: bool) -> u64 {
+        if split {
+            let query_blocks = self.query_blocks(true);
+            let b = interval_b.try_lock().expect("failed to lock interval");
+            return blocks_length(&intersect_blocks(&query_blocks, &blocks_of(&b, true)));
+        }
+        match self.query_window {
+            Some((start, stop)) => {
+                let b = interval_b.try_lock().expect("failed to lock interval");
+                (stop.min(b.stop())).saturating_sub(start.max(b.start()))
+            }
+            None => self.calculate_overlap(self.base_interval.clone(), interval_b),
+        }
+    }
+
     #[inline]
     fn calculate_overlap(
         &self,
@@ -251,18 +499,13 @@ impl Intersections {
             .iter()
             .map(|o| {
                 // TODO: what to do here if distance and/or n_closest are > 0?
-                let ovl = self.calculate_overlap(self.base_interval.clone(), o.interval.clone());
+                let ovl = self.query_overlap(o.interval.clone(), report_options.split);
 
                 if report_options.a_mode == IntersectionMode::PerPiece {
                     let a_req = match report_options.a_requirements {
                         OverlapAmount::Bases(bases) => ovl >= bases,
                         OverlapAmount::Fraction(fraction) => {
-                            let iv = self
-                                .base_interval
-                                .try_lock()
-                                .expect("failed to lock interval");
-                            let interval_length = iv.stop() - iv.start();
-                            ovl as f32 >= fraction * interval_length as f32
+                            ovl as f32 >= fraction * self.query_length(report_options.split) as f32
                         }
                     };
                     if !a_req {
@@ -274,7 +517,8 @@ impl Intersections {
                         OverlapAmount::Bases(bases) => ovl >= bases,
                         OverlapAmount::Fraction(fraction) => {
                             let iv = o.interval.try_lock().expect("failed to lock interval");
-                            let interval_length = iv.stop() - iv.start();
+                            let interval_length =
+                                blocks_length(&blocks_of(&iv, report_options.split));
                             ovl as f32 >= fraction * interval_length as f32
                         }
                     };
@@ -290,11 +534,12 @@ impl Intersections {
         &self,
         result: &mut Vec<ReportFragment>,
         overlaps: &[Intersection], // already grouped and only from b_idx.
-        a_piece: &IntersectionPart,
-        b_piece: &IntersectionPart,
+        report_options: &ReportOptions,
         b_idx: usize, // index bs of result.
     ) {
         assert!(overlaps.iter().all(|o| o.id as usize == b_idx));
+        let (a_piece, b_piece) = (&report_options.a_piece, &report_options.b_piece);
+        let split = report_options.split;
 
         if matches!(a_piece, IntersectionPart::Whole) {
             let locked_base = self
@@ -304,8 +549,13 @@ impl Intersections {
             let base = locked_base.clone_box();
             drop(locked_base);
 
-            let base_start = base.start();
-            let base_stop = base.stop();
+            // B pieces are relative to the query window when there is one.
+            let (base_start, base_stop) = self.query_bounds();
+            let query_blocks = if split {
+                self.query_blocks(true)
+            } else {
+                vec![]
+            };
 
             let make_b_positions = |intersection: &Intersection| -> Vec<Arc<Mutex<Position>>> {
                 match b_piece {
@@ -313,6 +563,16 @@ impl Intersections {
                     IntersectionPart::WholeWide | IntersectionPart::Whole => {
                         vec![intersection.interval.clone()]
                     }
+                    IntersectionPart::Piece if split => {
+                        let o = intersection
+                            .interval
+                            .try_lock()
+                            .expect("failed to lock interval");
+                        intersect_blocks(&query_blocks, &blocks_of(&o, true))
+                            .into_iter()
+                            .map(|(start, stop)| Arc::new(Mutex::new(block_piece(&o, start, stop))))
+                            .collect()
+                    }
                     IntersectionPart::Piece => {
                         let o = intersection
                             .interval
@@ -369,6 +629,31 @@ impl Intersections {
             IntersectionPart::None | IntersectionPart::WholeWide => {
                 vec![self.base_interval.clone()]
             }
+            IntersectionPart::Piece if split => {
+                // each piece of A is where one of its blocks overlaps a block of B.
+                let bi = self
+                    .base_interval
+                    .try_lock()
+                    .expect("failed to lock interval");
+                let base_blocks = blocks_of(&bi, true);
+                overlaps
+                    .iter()
+                    .flat_map(|o| {
+                        let oi = o.interval.try_lock().expect("failed to lock interval");
+                        let pieces = intersect_blocks(&base_blocks, &blocks_of(&oi, true));
+                        if pieces.is_empty() {
+                            vec![Arc::new(Mutex::new(bi.clone_box()))]
+                        } else {
+                            pieces
+                                .into_iter()
+                                .map(|(start, stop)| {
+                                    Arc::new(Mutex::new(block_piece(&bi, start, stop)))
+                                })
+                                .collect()
+                        }
+                    })
+                    .collect()
+            }
             IntersectionPart::Piece => {
                 // Create and adjust a_position if a_piece is Part
                 overlaps
@@ -421,21 +706,32 @@ impl Intersections {
                     b: vec![],
                     id: b_idx,
                 },
+                IntersectionPart::Piece if split => {
+                    let query_blocks = self.query_blocks(true);
+                    let mut b_positions = Vec::new();
+                    for o in overlaps {
+                        let o = o.interval.try_lock().expect("failed to lock interval");
+                        for (start, stop) in intersect_blocks(&query_blocks, &blocks_of(&o, true)) {
+                            b_positions.push(Arc::new(Mutex::new(block_piece(&o, start, stop))));
+                        }
+                    }
+                    ReportFragment {
+                        a: a_pos,
+                        b: b_positions,
+                        id: b_idx,
+                    }
+                }
                 IntersectionPart::Piece => {
                     let mut b_positions = Vec::new();
-                    let base = self
-                        .base_interval
-                        .try_lock()
-                        .expect("failed to lock interval");
+                    let (query_start, query_stop) = self.query_bounds();
                     for o in overlaps {
                         let o = o.interval.try_lock().expect("failed to lock interval");
                         let mut b_interval = o.clone_box();
-                        b_interval.set_start(b_interval.start().max(base.start()));
-                        b_interval.set_stop(b_interval.stop().min(base.stop()));
+                        b_interval.set_start(b_interval.start().max(query_start));
+                        b_interval.set_stop(b_interval.stop().min(query_stop));
                         b_positions.push(Arc::new(Mutex::new(b_interval)));
                         drop(o);
                     }
-                    drop(base);
                     ReportFragment {
                         a: a_pos,
                         b: b_positions,
@@ -446,20 +742,17 @@ impl Intersections {
                     // if we have a: 1-10, b: 3-6, 8-12
                     // then we want to report b: [], 10-12
                     let mut b_positions = Vec::new();
-                    let base = self
-                        .base_interval
-                        .try_lock()
-                        .expect("failed to lock interval");
+                    let (query_start, query_stop) = self.query_bounds();
                     for o in overlaps {
                         let o = o.interval.try_lock().expect("failed to lock interval");
-                        if o.start() < base.start() {
+                        if o.start() < query_start {
                             let mut b_interval = o.clone_box();
-                            b_interval.set_stop(base.start());
+                            b_interval.set_stop(query_start);
                             b_positions.push(Arc::new(Mutex::new(b_interval)));
                         }
-                        if o.stop() > base.stop() {
+                        if o.stop() > query_stop {
                             let mut b_interval = o.clone_box();
-                            b_interval.set_start(base.stop());
+                            b_interval.set_start(query_stop);
                             b_positions.push(Arc::new(Mutex::new(b_interval)));
                         }
                         drop(o);
@@ -594,6 +887,35 @@ mod tests {
         assert_eq!(b1.stop(), 12);
     }
 
+    #[test]
+    fn test_query_window() {
+        // 12-15 does not overlap a, but is inside its window.
+        let mut intersections = make_example("a: 5-10\nb: 3-6, 12-15");
+        intersections.query_window = Some((0, 14));
+        let ro = ReportOptions {
+            b_piece: IntersectionPart::Piece,
+            ..Default::default()
+        };
+        let r = intersections.report(&ro);
+        assert_eq!(r.len(), 2);
+        for (fragment, (start, stop)) in r.iter().zip([(3, 6), (12, 14)]) {
+            let a = fragment.a.as_ref().unwrap().lock();
+            assert_eq!((a.start(), a.stop()), (5, 10));
+            let b = fragment.b[0].lock();
+            assert_eq!((b.start(), b.stop()), (start, stop));
+        }
+
+        // requirements are measured against the window: 3 + 2 bases overlap it, but only 1
+        // base overlaps a.
+        let ro = ReportOptions {
+            a_requirements: OverlapAmount::Bases(5),
+            ..Default::default()
+        };
+        assert_eq!(intersections.report(&ro).len(), 2);
+        let intersections = make_example("a: 5-10\nb: 3-6, 12-15");
+        assert_eq!(intersections.report(&ro).len(), 0);
+    }
+
     #[test]
     fn test_inverse() {
         let intersections = make_example("a: 1-10\nb: 3-6, 4-6, 8-12");
@@ -633,6 +955,71 @@ mod tests {
         assert_eq!(inv0.stop(), 10);
     }
 
+    #[test]
+    fn test_inverse_contained_overlap() {
+        let intersections = make_example("a: 1-20\nb: 2-12, 4-6\nc: 14-16");
+        let inv = inverse(
+            &intersections.base_interval.lock(),
+            &intersections.overlapping,
+        );
+        let inv: Vec<_> = inv
+            .iter()
+            .map(|p| (p.lock().start(), p.lock().stop()))
+            .collect();
+        assert_eq!(inv, vec![(1, 2), (12, 14), (16, 20)]);
+    }
+
+    #[test]
+    fn test_uncovered() {
+        assert_eq!(uncovered(10, 20, &[]), vec![(10, 20)]);
+        assert_eq!(
+            uncovered(10, 20, &[(0, 12), (14, 16), (15, 17), (30, 40)]),
+            vec![(12, 14), (17, 20)]
+        );
+        assert_eq!(uncovered(10, 20, &[(5, 25)]), vec![]);
+        assert_eq!(uncovered(10, 20, &[(0, 5), (25, 30)]), vec![(10, 20)]);
+    }
+
+    #[test]
+    fn test_subtract() {
+        // overlaps from all sources are subtracted together.
+        let intersections = make_example("a: 1-20\nb: 2-12, 4-6\nc: 14-16");
+        let r = intersections.subtract(&ReportOptions::default(), false);
+        let pieces: Vec<_> = r
+            .iter()
+            .map(|f| {
+                let a = f.a.as_ref().unwrap().lock();
+                (a.start(), a.stop())
+            })
+            .collect();
+        assert_eq!(pieces, vec![(1, 2), (12, 14), (16, 20)]);
+
+        assert_eq!(
+            intersections
+                .subtract(&ReportOptions::default(), true)
+                .len(),
+            0
+        );
+
+        // only 2-12 covers half of A, so 14-16 is not subtracted.
+        let ro = ReportOptions {
+            a_requirements: OverlapAmount::Fraction(0.5),
+            ..Default::default()
+        };
+        let r = intersections.subtract(&ro, false);
+        assert_eq!(r.len(), 2);
+
+        // no qualifying overlaps reports A unchanged.
+        let ro = ReportOptions {
+            a_requirements: OverlapAmount::Fraction(0.9),
+            ..Default::default()
+        };
+        let r = intersections.subtract(&ro, true);
+        assert_eq!(r.len(), 1);
+        let a = r[0].a.as_ref().unwrap().lock();
+        assert_eq!((a.start(), a.stop()), (1, 20));
+    }
+
     #[test]
     fn test_not() {
         let intersections = make_example("a: 1-10\nb: 3-6, 8-12");
@@ -1169,6 +1556,156 @@ mod tests {
         );
     }
 
+    fn stranded_intersections(a: &str, bs: &[&str]) -> Intersections {
+        use crate::bedder_bed::BedRecord;
+        // each B gets its own id so that every overlap is reported separately.
+        let bed = |start, stop, strand: &str| {
+            Arc::new(Mutex::new(Position::Bed(BedRecord::new(
+                "chr1",
+                start,
+                stop,
+                None,
+                None,
+                vec![strand.into()],
+            ))))
+        };
+        Intersections {
+            base_interval: bed(10, 20, a),
+            overlapping: bs
+                .iter()
+                .enumerate()
+                .map(|(i, s)| Intersection {
+                    interval: bed(12 + i as u64, 18, s),
+                    id: i as u32,
+                })
+                .collect(),
+            query_window: None,
+            cached_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    #[test]
+    fn test_strand_modes() {
+        use crate::position::Strand;
+        let ro = |strand| ReportOptions {
+            b_mode: IntersectionMode::PerPiece,
+            b_piece: IntersectionPart::Whole,
+            strand,
+            ..Default::default()
+        };
+
+        let intersections = stranded_intersections("+", &["+", "-", "."]);
+        assert_eq!(intersections.report(&ro(StrandMode::Ignore)).len(), 3);
+
+        let r = intersections.report(&ro(StrandMode::Same));
+        assert_eq!(r.len(), 1);
+        assert_eq!(r[0].b[0].lock().strand(), Strand::Forward);
+
+        let r = intersections.report(&ro(StrandMode::Opposite));
+        assert_eq!(r.len(), 1);
+        assert_eq!(r[0].b[0].lock().strand(), Strand::Reverse);
+
+        // an A with unknown strand never matches a strand requirement.
+        let intersections = stranded_intersections(".", &["+", "-"]);
+        assert_eq!(intersections.report(&ro(StrandMode::Same)).len(), 0);
+        assert_eq!(intersections.report(&ro(StrandMode::Opposite)).len(), 0);
+    }
+
+    #[test]
+    fn test_strand_not_mode() {
+        // -v with -s should report A when the only overlaps are on the other strand.
+        let intersections = stranded_intersections("+", &["-"]);
+        let ro = ReportOptions {
+            a_mode: IntersectionMode::Not,
+            strand: StrandMode::Same,
+            ..Default::default()
+        };
+        assert_eq!(intersections.report(&ro).len(), 1);
+    }
+
+    // a BED12 transcript with blocks at 100-150 and 300-400.
+    fn bed12_intersections(bs: &[(u64, u64)]) -> Intersections {
+        use crate::bedder_bed::BedRecord;
+        let fields = ["+", "100", "400", "0", "2", "50,100,", "0,200,"];
+        let base = BedRecord::new(
+            "chr1",
+            100,
+            400,
+            Some("tx"),
+            None,
+            fields.iter().map(|f| String::from(*f)).collect(),
+        );
+        Intersections {
+            base_interval: Arc::new(Mutex::new(Position::Bed(base))),
+            overlapping: bs
+                .iter()
+                .enumerate()
+                .map(|(i, &(start, stop))| Intersection {
+                    interval: Arc::new(Mutex::new(Position::Bed(BedRecord::new(
+                        "chr1",
+                        start,
+                        stop,
+                        None,
+                        None,
+                        vec![],
+                    )))),
+                    id: i as u32,
+                })
+                .collect(),
+            query_window: None,
+            cached_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    #[test]
+    fn test_split_bed12() {
+        // 200-250 is only in the intron.
+        let intersections = bed12_intersections(&[(200, 250), (140, 320)]);
+        let ro = |split, a_piece, b_piece| ReportOptions {
+            a_piece,
+            b_piece,
+            split,
+            ..Default::default()
+        };
+        let spans = |positions: Vec<&Arc<Mutex<Position>>>| -> Vec<(u64, u64)> {
+            positions
+                .iter()
+                .map(|p| {
+                    let p = p.lock();
+                    (p.start(), p.stop())
+                })
+                .collect()
+        };
+
+        let r = intersections.report(&ro(false, IntersectionPart::Whole, IntersectionPart::Whole));
+        assert_eq!(r.len(), 2);
+
+        let r = intersections.report(&ro(true, IntersectionPart::Whole, IntersectionPart::Whole));
+        assert_eq!(r.len(), 1);
+        assert_eq!(spans(r[0].b.iter().collect()), vec![(140, 320)]);
+
+        let r = intersections.report(&ro(true, IntersectionPart::Whole, IntersectionPart::Piece));
+        assert_eq!(spans(r[0].b.iter().collect()), vec![(140, 150), (300, 320)]);
+
+        let r = intersections.report(&ro(true, IntersectionPart::Piece, IntersectionPart::Whole));
+        let a_pieces = spans(r.iter().map(|f| f.a.as_ref().unwrap()).collect());
+        assert_eq!(a_pieces, vec![(140, 150), (300, 320)]);
+        // pieces of a BED12 record have a single block.
+        assert_eq!(
+            r[1].a.as_ref().unwrap().lock().blocks(),
+            Some(vec![(300, 320)])
+        );
+
+        // requirements are relative to the 150 bases in blocks; 30 of them overlap.
+        let with_fraction = |fraction| ReportOptions {
+            a_requirements: OverlapAmount::Fraction(fraction),
+            split: true,
+            ..Default::default()
+        };
+        assert_eq!(intersections.report(&with_fraction(0.2)).len(), 1);
+        assert_eq!(intersections.report(&with_fraction(0.25)).len(), 0);
+    }
+
     /*
     #[test]
     fn test_no_overlaps() {
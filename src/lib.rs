@@ -20,6 +20,9 @@ pub mod sniff;
 
 /// Reports from intersections.
 pub mod report;
+
+/// Sweep-based coverage of an interval.
+pub mod coverage;
 pub mod report_options;
 
 pub mod formatting;
@@ -28,9 +31,24 @@ pub mod string;
 
 pub mod chrom_ordering;
 
+/// Merge overlapping intervals from a sorted PositionedIterator.
+pub mod merge;
+
+/// Regions of the genome not covered by a sorted PositionedIterator.
+pub mod complement;
+
+/// Fixed-size or fixed-count windows across a genome or a set of regions.
+pub mod windows;
+
+/// Extend intervals or report their flanks.
+pub mod slop;
+
 // Determines how the output is written--format, compression, etc.
 pub mod writer;
 
+/// Parquet and Arrow IPC output of reports.
+pub mod columnar;
+
 //#[cfg(feature = "bed")]
 /// Bed parser implementing the PositionedIterator trait.
 pub mod bedder_bed;
@@ -41,6 +59,21 @@ pub use rust_htslib::htslib as hts;
 /// Vcf parser implementing the PositionedIterator trait.
 pub mod bedder_vcf;
 
+/// Sam/Bam/Cram parser implementing the PositionedIterator trait.
+pub mod bedder_bam;
+
+/// Gff3/Gtf parser implementing the PositionedIterator trait.
+pub mod bedder_gff;
+
+/// BEDPE/PAF pairs whose ends are intersected separately.
+pub mod bedder_pair;
+
+/// BigWig/BigBed reader implementing the PositionedIterator trait.
+pub mod bedder_bbi;
+
+/// D4 coverage reader implementing the PositionedIterator trait, and a D4 writer.
+pub mod bedder_d4;
+
 pub mod hts_format;
 
 /// Python bindings for bedder
@@ -20,6 +20,26 @@ enum Commands {
     Closest(cli::closest::ClosestCmdArgs),
     /// Map operation — aggregate overlapping B values per A interval
     Map(cli::map::MapCmdArgs),
+    /// Merge overlapping or nearby intervals into a single interval
+    Merge(cli::merge::MergeCmdArgs),
+    /// Subtract mode - report the parts of A not covered by any B
+    Subtract(cli::subtract::SubtractCmdArgs),
+    /// Report regions of the genome not covered by the input
+    Complement(cli::complement::ComplementCmdArgs),
+    /// Coverage mode - depth and breadth of B coverage for each A interval
+    Coverage(cli::coverage::CoverageCmdArgs),
+    /// Genome coverage - per-base depth across the genome as a BedGraph
+    Genomecov(cli::genomecov::GenomecovCmdArgs),
+    /// Make windows across the genome or a set of regions
+    Makewindows(cli::makewindows::MakewindowsCmdArgs),
+    /// Extend intervals by a number of bases or a fraction of their length
+    Slop(cli::slop::SlopCmdArgs),
+    /// Report the regions flanking each interval
+    Flank(cli::flank::FlankCmdArgs),
+    /// Report overlaps between the ends of BEDPE/PAF pairs and intervals (bedtools pairtobed)
+    Pairtobed(cli::pairtobed::PairtobedCmdArgs),
+    /// Report overlaps between two sets of BEDPE/PAF pairs (bedtools pairtopair)
+    Pairtopair(cli::pairtopair::PairtopairCmdArgs),
 }
 
 #[cfg(feature = "mimalloc_allocator")]
@@ -40,5 +60,15 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Intersect(args) => cli::intersect::intersect_command(args),
         Commands::Closest(args) => cli::closest::closest_command(args),
         Commands::Map(args) => cli::map::map_command(args),
+        Commands::Merge(args) => cli::merge::merge_command(args),
+        Commands::Subtract(args) => cli::subtract::subtract_command(args),
+        Commands::Complement(args) => cli::complement::complement_command(args),
+        Commands::Coverage(args) => cli::coverage::coverage_command(args),
+        Commands::Genomecov(args) => cli::genomecov::genomecov_command(args),
+        Commands::Makewindows(args) => cli::makewindows::makewindows_command(args),
+        Commands::Slop(args) => cli::slop::slop_command(args),
+        Commands::Flank(args) => cli::flank::flank_command(args),
+        Commands::Pairtobed(args) => cli::pairtobed::pairtobed_command(args),
+        Commands::Pairtopair(args) => cli::pairtopair::pairtopair_command(args),
     }
 }
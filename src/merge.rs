@@ -0,0 +1,408 @@
+use crate::bedder_bed::BedRecord;
+use crate::chrom_ordering::Chromosome;
+use crate::position::{Position, PositionedIterator, Strand};
+use crate::string::String;
+use hashbrown::HashMap;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::io::{self, Error};
+
+/// A run of overlapping (or nearby) intervals collapsed into a single interval.
+#[derive(Debug)]
+pub struct MergedInterval {
+    pub chrom: String,
+    /// 0-based start of the first member.
+    pub start: u64,
+    /// largest stop of any member.
+    pub stop: u64,
+    /// strand of the members when merging is strand-aware. `Strand::Unknown` otherwise.
+    pub strand: Strand,
+    /// the intervals that were merged, in input order.
+    pub members: Vec<Position>,
+}
+
+impl MergedInterval {
+    /// A BED record with the merged coordinates. When the merge is strand-aware, the strand
+    /// is placed in column 6 so that `Position::strand` reports it.
+    pub fn to_position(&self) -> Position {
+        let record = if self.strand == Strand::Unknown {
+            BedRecord::new(&self.chrom, self.start, self.stop, None, None, vec![])
+        } else {
+            BedRecord::new(
+                &self.chrom,
+                self.start,
+                self.stop,
+                Some("."),
+                Some(0.0),
+                vec![String::from(self.strand.to_string().as_str())],
+            )
+        };
+        Position::Bed(record)
+    }
+}
+
+// closed intervals wait here until no open interval can start before them.
+struct ReadyInterval(MergedInterval);
+
+impl PartialEq for ReadyInterval {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ReadyInterval {}
+
+impl PartialOrd for ReadyInterval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReadyInterval {
+    // reversed so that the BinaryHeap is a min-heap on (start, stop).
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.0.start, other.0.stop).cmp(&(self.0.start, self.0.stop))
+    }
+}
+
+/// MergeIterator collapses overlapping or nearby intervals from a sorted `PositionedIterator`.
+///
+/// Intervals are merged when the gap between them is at most `max_distance`, so the default of 0
+/// merges overlapping and book-ended intervals. When strand-aware, only intervals on the same
+/// strand are merged and intervals with unknown strand are merged with each other.
+pub struct MergeIterator {
+    inner: Box<dyn PositionedIterator>,
+    // owned (rather than borrowed as in IntersectionIterator) so that a MergeIterator can itself
+    // be boxed and used as a PositionedIterator.
+    chromosome_order: HashMap<String, Chromosome>,
+    max_distance: i64,
+    strand_aware: bool,
+
+    // one open interval per strand (only the first is used when not strand-aware).
+    open: [Option<MergedInterval>; 3],
+    ready: BinaryHeap<ReadyInterval>,
+    // a position from the next chromosome held back until the current chromosome is drained.
+    pending: Option<Position>,
+    current_chrom: Option<(String, usize)>,
+    last_start: u64,
+    done: bool,
+}
+
+impl MergeIterator {
+    pub fn new(
+        inner: Box<dyn PositionedIterator>,
+        chromosome_order: &HashMap<String, Chromosome>,
+    ) -> Self {
+        MergeIterator {
+            inner,
+            chromosome_order: chromosome_order.clone(),
+            max_distance: 0,
+            strand_aware: false,
+            open: [None, None, None],
+            ready: BinaryHeap::new(),
+            pending: None,
+            current_chrom: None,
+            last_start: 0,
+            done: false,
+        }
+    }
+
+    /// Merge intervals separated by at most `max_distance` bases.
+    /// A negative value requires that intervals overlap by at least that many bases.
+    pub fn with_max_distance(mut self, max_distance: i64) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Only merge intervals that are on the same strand.
+    pub fn with_strand(mut self, strand_aware: bool) -> Self {
+        self.strand_aware = strand_aware;
+        self
+    }
+
+    fn slot(&self, strand: Strand) -> usize {
+        if !self.strand_aware {
+            return 0;
+        }
+        match strand {
+            Strand::Forward => 0,
+            Strand::Reverse => 1,
+            Strand::Unknown => 2,
+        }
+    }
+
+    fn close_all(&mut self) {
+        for slot in self.open.iter_mut() {
+            if let Some(m) = slot.take() {
+                self.ready.push(ReadyInterval(m));
+            }
+        }
+    }
+
+    // a ready interval can be emitted once no open interval starts before it.
+    fn pop_ready(&mut self) -> Option<MergedInterval> {
+        let top = self.ready.peek()?;
+        let min_open = self.open.iter().flatten().map(|m| m.start).min();
+        if min_open.is_none_or(|s| top.0.start <= s) {
+            self.ready.pop().map(|r| r.0)
+        } else {
+            None
+        }
+    }
+
+    fn add(&mut self, position: Position) -> io::Result<()> {
+        let chrom_index = match self.chromosome_order.get(position.chrom()) {
+            Some(c) => c.index,
+            None => {
+                return Err(Error::other(format!(
+                    "invalid chromosome: {}:{}-{} in iterator {}",
+                    position.chrom(),
+                    position.start() + 1,
+                    position.stop(),
+                    self.inner.name()
+                )))
+            }
+        };
+
+        let same_chrom = matches!(&self.current_chrom, Some((c, _)) if c == position.chrom());
+        if !same_chrom {
+            if let Some((chrom, index)) = &self.current_chrom {
+                if *index > chrom_index {
+                    return Err(Error::other(format!(
+                        "intervals from {} out of order: {} should be before {}",
+                        self.inner.name(),
+                        position.chrom(),
+                        chrom
+                    )));
+                }
+            }
+            self.current_chrom = Some((String::from(position.chrom()), chrom_index));
+        } else if position.start() < self.last_start {
+            return Err(Error::other(format!(
+                "intervals from {} out of order: {}:{} should be before {}:{}",
+                self.inner.name(),
+                position.chrom(),
+                position.start() + 1,
+                position.chrom(),
+                self.last_start + 1
+            )));
+        }
+        self.last_start = position.start();
+
+        let strand = if self.strand_aware {
+            position.strand()
+        } else {
+            Strand::Unknown
+        };
+        let slot = self.slot(strand);
+        match &mut self.open[slot] {
+            Some(m) if position.start() as i64 - m.stop as i64 <= self.max_distance => {
+                m.stop = m.stop.max(position.stop());
+                m.members.push(position);
+            }
+            open => {
+                let previous = open.replace(MergedInterval {
+                    chrom: String::from(position.chrom()),
+                    start: position.start(),
+                    stop: position.stop(),
+                    strand,
+                    members: vec![position],
+                });
+                if let Some(m) = previous {
+                    self.ready.push(ReadyInterval(m));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for MergeIterator {
+    type Item = io::Result<MergedInterval>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.pop_ready() {
+                return Some(Ok(m));
+            }
+            if self.done {
+                return None;
+            }
+
+            let position = match self.pending.take() {
+                Some(p) => p,
+                None => match self.inner.next_position(None) {
+                    Some(Ok(p)) => p,
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => {
+                        self.done = true;
+                        self.close_all();
+                        continue;
+                    }
+                },
+            };
+
+            // drain everything from the current chromosome before starting the next.
+            let new_chrom = matches!(&self.current_chrom, Some((c, _)) if c != position.chrom());
+            if new_chrom && (self.open.iter().any(|m| m.is_some()) || !self.ready.is_empty()) {
+                self.close_all();
+                self.pending = Some(position);
+                continue;
+            }
+
+            if let Err(e) = self.add(position) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+impl PositionedIterator for MergeIterator {
+    fn name(&self) -> String {
+        format!("merged:{}", self.inner.name())
+    }
+
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        self.next().map(|r| r.map(|m| m.to_position()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chrom_ordering::parse_genome;
+
+    struct Records {
+        ivs: std::vec::IntoIter<Position>,
+    }
+
+    impl PositionedIterator for Records {
+        fn name(&self) -> String {
+            String::from("records")
+        }
+
+        fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+            self.ivs.next().map(Ok)
+        }
+    }
+
+    fn records(ivs: &[(&str, u64, u64, &str)]) -> Box<dyn PositionedIterator> {
+        Box::new(Records {
+            ivs: ivs
+                .iter()
+                .map(|(chrom, start, stop, strand)| {
+                    Position::Bed(BedRecord::new(
+                        chrom,
+                        *start,
+                        *stop,
+                        Some("x"),
+                        Some(1.0),
+                        vec![String::from(*strand)],
+                    ))
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    fn coords(merged: MergeIterator) -> Vec<(String, u64, u64, usize)> {
+        merged
+            .map(|m| {
+                let m = m.expect("error merging");
+                (m.chrom, m.start, m.stop, m.members.len())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_overlapping_and_bookended() {
+        let genome = parse_genome("chr1\nchr2\n".as_bytes()).unwrap();
+        let ivs = records(&[
+            ("chr1", 1, 10, "+"),
+            ("chr1", 5, 20, "+"),
+            ("chr1", 20, 30, "-"),
+            ("chr1", 40, 50, "+"),
+            ("chr2", 1, 5, "+"),
+        ]);
+        let merged = coords(MergeIterator::new(ivs, &genome));
+        assert_eq!(
+            merged,
+            vec![
+                (String::from("chr1"), 1, 30, 3),
+                (String::from("chr1"), 40, 50, 1),
+                (String::from("chr2"), 1, 5, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_max_distance() {
+        let genome = parse_genome("chr1\n".as_bytes()).unwrap();
+        let ivs = &[("chr1", 1, 10, "+"), ("chr1", 15, 20, "+")];
+        let merged = coords(MergeIterator::new(records(ivs), &genome).with_max_distance(4));
+        assert_eq!(merged.len(), 2);
+        let merged = coords(MergeIterator::new(records(ivs), &genome).with_max_distance(5));
+        assert_eq!(merged, vec![(String::from("chr1"), 1, 20, 2)]);
+
+        // a negative distance requires overlap.
+        let ivs = &[("chr1", 1, 10, "+"), ("chr1", 8, 20, "+")];
+        let merged = coords(MergeIterator::new(records(ivs), &genome).with_max_distance(-3));
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_strand_aware_stays_sorted() {
+        let genome = parse_genome("chr1\n".as_bytes()).unwrap();
+        let ivs = records(&[
+            ("chr1", 1, 100, "+"),
+            ("chr1", 5, 10, "-"),
+            ("chr1", 20, 30, "-"),
+            ("chr1", 50, 120, "+"),
+            ("chr1", 60, 70, "."),
+        ]);
+        let merged: Vec<_> = MergeIterator::new(ivs, &genome)
+            .with_strand(true)
+            .map(|m| m.expect("error merging"))
+            .map(|m| (m.start, m.stop, m.strand))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                (1, 120, Strand::Forward),
+                (5, 10, Strand::Reverse),
+                (20, 30, Strand::Reverse),
+                (60, 70, Strand::Unknown),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_out_of_order() {
+        let genome = parse_genome("chr1\nchr2\n".as_bytes()).unwrap();
+        let ivs = records(&[("chr1", 10, 20, "+"), ("chr1", 5, 8, "+")]);
+        let mut it = MergeIterator::new(ivs, &genome);
+        assert!(it.next().unwrap().is_err());
+
+        let ivs = records(&[("chr2", 10, 20, "+"), ("chr1", 5, 8, "+")]);
+        let mut it = MergeIterator::new(ivs, &genome);
+        let results: Vec<_> = it.by_ref().collect();
+        assert!(results.iter().any(|r| r
+            .as_ref()
+            .err()
+            .is_some_and(|e| e.to_string().contains("out of order"))));
+
+        let ivs = records(&[("chrX", 10, 20, "+")]);
+        let mut it = MergeIterator::new(ivs, &genome);
+        assert!(it.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_merge_as_positioned_iterator() {
+        let genome = parse_genome("chr1\n".as_bytes()).unwrap();
+        let ivs = records(&[("chr1", 1, 10, "-"), ("chr1", 5, 20, "-")]);
+        let mut it = MergeIterator::new(ivs, &genome).with_strand(true);
+        let p = it.next_position(None).unwrap().unwrap();
+        assert_eq!((p.start(), p.stop(), p.strand()), (1, 20, Strand::Reverse));
+        assert!(it.next_position(None).is_none());
+    }
+}
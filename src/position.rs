@@ -40,6 +40,36 @@ impl fmt::Display for FieldError {
 
 impl std::error::Error for FieldError {}
 
+/// Strand of a Positioned. Formats without strand information report `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strand {
+    Forward,
+    Reverse,
+    #[default]
+    Unknown,
+}
+
+impl Strand {
+    /// Parse a strand from a BED-like strand column. Anything other than `+` or `-` is `Unknown`.
+    pub fn from_str_lossy(s: &str) -> Self {
+        match s {
+            "+" => Strand::Forward,
+            "-" => Strand::Reverse,
+            _ => Strand::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+            Strand::Unknown => write!(f, "."),
+        }
+    }
+}
+
 /// A Positioned has a position in the genome. It is a bed-like (half-open) interval.
 pub trait Positioned: Debug + Sync + Send {
     fn chrom(&self) -> &str;
@@ -56,6 +86,11 @@ pub trait Positioned: Debug + Sync + Send {
     /// set the stop position.
     fn set_stop(&mut self, start: u64);
 
+    /// strand of the interval. Defaults to `Strand::Unknown` for types without strand.
+    fn strand(&self) -> Strand {
+        Strand::Unknown
+    }
+
     // get back the original line?
     //fn line(&self) -> &'a str;
 
@@ -78,6 +113,12 @@ pub enum Position {
     Bed(crate::bedder_bed::BedRecord),
     // Note: we use a Box here because a vcf Record is large.
     Vcf(Box<crate::bedder_vcf::BedderRecord>),
+    // SAM/BAM/CRAM alignments are boxed for the same reason.
+    Alignment(Box<crate::bedder_bam::BedderAlignment>),
+    // GFF3/GTF features.
+    Gff(Box<crate::bedder_gff::GffRecord>),
+    // one end of a BEDPE or PAF pair.
+    Pair(crate::bedder_pair::PairEnd),
     Interval(crate::interval::Interval),
     // catch-all in case we have another interval type.
     // #[cfg(feature = "dyn_positioned")]
@@ -89,6 +130,9 @@ impl Clone for Position {
         match self {
             Position::Bed(b) => Position::Bed(b.to_owned()),
             Position::Vcf(v) => Position::Vcf(Box::new((**v).clone())),
+            Position::Alignment(a) => Position::Alignment(a.clone()),
+            Position::Gff(g) => Position::Gff(g.clone()),
+            Position::Pair(p) => Position::Pair(p.clone()),
             Position::Interval(i) => Position::Interval(i.dup()),
             Position::Other(_) => unimplemented!("Clone not implemented for Position::Other"),
         }
@@ -109,6 +153,9 @@ impl Position {
         match self {
             Position::Bed(b) => b.chrom(),
             Position::Vcf(v) => v.chrom(),
+            Position::Alignment(a) => a.chrom(),
+            Position::Gff(g) => g.chrom(),
+            Position::Pair(p) => p.chrom(),
             Position::Interval(i) => &i.chrom,
             // #[cfg(feature = "dyn_positioned")]
             Position::Other(o) => o.chrom(),
@@ -121,6 +168,9 @@ impl Position {
         match self {
             Position::Bed(b) => b.start(),
             Position::Vcf(v) => v.record.pos() as u64,
+            Position::Alignment(a) => a.start(),
+            Position::Gff(g) => g.start(),
+            Position::Pair(p) => p.start(),
             Position::Interval(i) => i.start,
             // #[cfg(feature = "dyn_positioned")]
             Position::Other(o) => o.start(),
@@ -132,6 +182,9 @@ impl Position {
         match self {
             Position::Bed(b) => b.stop(),
             Position::Vcf(v) => v.record.end() as u64,
+            Position::Alignment(a) => a.stop(),
+            Position::Gff(g) => g.stop(),
+            Position::Pair(p) => p.stop(),
             Position::Interval(i) => i.stop,
             // #[cfg(feature = "dyn_positioned")]
             Position::Other(o) => o.stop(),
@@ -142,6 +195,9 @@ impl Position {
         match self {
             Position::Bed(b) => b.set_start(start),
             Position::Vcf(v) => v.record.set_pos(start as i64),
+            Position::Alignment(a) => a.set_start(start),
+            Position::Gff(g) => g.set_start(start),
+            Position::Pair(p) => p.set_start(start),
             Position::Interval(i) => i.set_start(start),
             // #[cfg(feature = "dyn_positioned")]
             Position::Other(o) => o.set_start(start),
@@ -152,6 +208,9 @@ impl Position {
         match self {
             Position::Bed(b) => b.set_stop(stop),
             Position::Vcf(_v) => {}
+            Position::Alignment(a) => a.set_stop(stop),
+            Position::Gff(g) => g.set_stop(stop),
+            Position::Pair(p) => p.set_stop(stop),
             Position::Interval(i) => i.set_stop(stop),
             // #[cfg(feature = "dyn_positioned")]
             Position::Other(o) => o.set_stop(stop),
@@ -162,16 +221,57 @@ impl Position {
         match self {
             Position::Bed(b) => Position::Bed(b.to_owned()),
             Position::Vcf(v) => Position::Vcf(Box::new((**v).clone())),
+            Position::Alignment(a) => Position::Alignment(a.clone()),
+            Position::Gff(g) => Position::Gff(g.clone()),
+            Position::Pair(p) => Position::Pair(p.clone()),
             Position::Interval(i) => Position::Interval(i.dup()),
             // #[cfg(feature = "dyn_positioned")]
             Position::Other(_o) => unimplemented!("TODO: clone Box<dyn Positioned>"),
         }
     }
 
-    /// Get the BED name field (column 4) if this is a BED record with a name.
+    /// Strand of the position. BED uses column 6, GFF column 7, pair ends the strand of that
+    /// end and alignments use the reverse flag; other types are `Strand::Unknown`.
+    #[inline]
+    pub fn strand(&self) -> Strand {
+        match self {
+            Position::Bed(b) => b.strand(),
+            Position::Vcf(_) => Strand::Unknown,
+            Position::Alignment(a) => a.strand(),
+            Position::Gff(g) => g.strand(),
+            Position::Pair(p) => p.strand(),
+            Position::Interval(_) => Strand::Unknown,
+            // #[cfg(feature = "dyn_positioned")]
+            Position::Other(o) => o.strand(),
+        }
+    }
+
+    /// Get the BED name field (column 4) if this is a BED record with a name,
+    /// the read name of an alignment, the Name, gene_name, ID or gene_id attribute of a
+    /// GFF feature, or the name of the pair for a pair end.
     pub fn name(&self) -> Option<&str> {
         match self {
             Position::Bed(b) => b.0.name(),
+            Position::Alignment(a) => a.qname(),
+            Position::Gff(g) => g.name(),
+            Position::Pair(p) => p.pair.name.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The blocks (e.g. exons) of a BED12 record as sorted (start, stop).
+    /// None for other types and for BED records without valid block columns.
+    pub fn blocks(&self) -> Option<Vec<(u64, u64)>> {
+        match self {
+            Position::Bed(b) => b.blocks(),
+            _ => None,
+        }
+    }
+
+    /// Get a GFF/GTF attribute. Other types have no attributes.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        match self {
+            Position::Gff(g) => g.attribute(key),
             _ => None,
         }
     }
@@ -6,7 +6,7 @@ use pyo3::{prelude::*, IntoPyObjectExt};
 use std::collections::HashMap;
 use std::fmt;
 
-use crate::column::{Number, Type, Value};
+use crate::column::{ColumnError, Number, Type, Value};
 use crate::position::Position;
 use crate::report_options::{IntersectionMode, IntersectionPart, OverlapAmount, ReportOptions};
 use rust_htslib as htslib;
@@ -20,6 +20,7 @@ use rust_htslib as htslib;
 ///     stop (int): The end position (exclusive)
 ///     name (str, optional): The name field if present
 ///     score (float, optional): The score field if present
+///     blocks (list[tuple[int, int]]): The BED12 blocks, or the whole record
 ///
 /// # Example
 /// ```python
@@ -164,6 +165,21 @@ impl PyBedRecord {
         }
     }
 
+    #[getter]
+    /// Get the blocks (e.g. exons) of a BED12 record as (start, stop) tuples.
+    /// Records without blocks have a single block spanning the record.
+    ///
+    /// # Example
+    /// ```python
+    /// exonic = sum(stop - start for start, stop in bed_record.blocks)
+    /// ```
+    fn blocks(&self) -> PyResult<Vec<(u64, u64)>> {
+        let position = self.inner.try_lock().expect("failed to lock interval");
+        Ok(position
+            .blocks()
+            .unwrap_or_else(|| vec![(position.start(), position.stop())]))
+    }
+
     /// Index into the `other_fields` list.
     ///
     /// # Example
@@ -184,6 +200,7 @@ impl PyBedRecord {
 /// Attributes:
 ///     chrom (str): The chromosome name
 ///     pos (int): The position (0-based)
+///     samples (list[str]): The sample names
 ///
 /// # Example
 /// ```python
@@ -239,6 +256,26 @@ impl PyVcfRecord {
             .start())
     }
 
+    #[getter]
+    /// Get the sample names, in the order of FORMAT values.
+    ///
+    /// # Example
+    /// ```python
+    /// samples = vcf_record.samples
+    /// ```
+    fn samples(&self) -> PyResult<Vec<String>> {
+        if let Position::Vcf(v) = &*self.inner.try_lock().expect("failed to lock interval") {
+            Ok(v.record
+                .header()
+                .samples()
+                .iter()
+                .map(|s| String::from_utf8_lossy(s).to_string())
+                .collect())
+        } else {
+            Ok(vec![])
+        }
+    }
+
     /// Get an INFO field by key, returning the best Python representation.
     ///
     /// # Example
@@ -657,6 +694,174 @@ impl PyVcfRecord {
     }
 }
 
+/// A Python wrapper for a GFF3 or GTF feature.
+///
+/// Attributes:
+///     chrom (str): The chromosome name
+///     start (int): The start position (0-based)
+///     stop (int): The end position (exclusive)
+///     source (str): The source column
+///     type (str): The feature type (e.g. "gene" or "exon")
+///     score (float, optional): The score column
+///     strand (str): "+", "-" or "."
+///     phase (int, optional): The phase of a CDS feature
+///     attributes (dict[str, str]): The parsed attributes
+///
+/// # Example
+/// ```python
+/// gff = position.gff()
+/// if gff is not None and gff.type == "exon":
+///     print(gff.attribute("gene_name"))
+/// ```
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct PyGffRecord {
+    inner: Arc<Mutex<Position>>,
+}
+
+impl PyGffRecord {
+    fn with_record<T>(&self, f: impl FnOnce(&crate::bedder_gff::GffRecord) -> T) -> Option<T> {
+        if let Position::Gff(g) = &*self.inner.try_lock().expect("failed to lock interval") {
+            Some(f(g))
+        } else {
+            None
+        }
+    }
+}
+
+#[pymethods]
+impl PyGffRecord {
+    #[getter]
+    /// Get the chromosome name.
+    fn chrom(&self) -> PyResult<String> {
+        Ok(self
+            .inner
+            .try_lock()
+            .expect("failed to lock interval")
+            .chrom()
+            .to_string())
+    }
+
+    #[getter]
+    /// Get the start position (0-based).
+    fn start(&self) -> PyResult<u64> {
+        Ok(self
+            .inner
+            .try_lock()
+            .expect("failed to lock interval")
+            .start())
+    }
+
+    #[getter]
+    /// Get the end position (exclusive).
+    fn stop(&self) -> PyResult<u64> {
+        Ok(self
+            .inner
+            .try_lock()
+            .expect("failed to lock interval")
+            .stop())
+    }
+
+    #[getter]
+    /// Get the source column.
+    ///
+    /// # Example
+    /// ```python
+    /// source = gff.source
+    /// ```
+    fn source(&self) -> PyResult<String> {
+        Ok(self
+            .with_record(|g| g.source.to_string())
+            .unwrap_or_default())
+    }
+
+    #[getter]
+    /// Get the feature type.
+    ///
+    /// # Example
+    /// ```python
+    /// feature_type = gff.type
+    /// ```
+    fn r#type(&self) -> PyResult<String> {
+        Ok(self
+            .with_record(|g| g.feature_type.to_string())
+            .unwrap_or_default())
+    }
+
+    #[getter]
+    /// Get the score if present.
+    fn score(&self) -> PyResult<Option<f64>> {
+        Ok(self.with_record(|g| g.score).flatten())
+    }
+
+    #[getter]
+    /// Get the strand ("+", "-" or ".").
+    fn strand(&self) -> PyResult<String> {
+        Ok(self
+            .inner
+            .try_lock()
+            .expect("failed to lock interval")
+            .strand()
+            .to_string())
+    }
+
+    #[getter]
+    /// Get the phase (0, 1 or 2) if present.
+    fn phase(&self) -> PyResult<Option<u8>> {
+        Ok(self.with_record(|g| g.phase).flatten())
+    }
+
+    #[getter]
+    /// Get all attributes as a dict, in file order.
+    ///
+    /// # Example
+    /// ```python
+    /// for key, value in gff.attributes.items():
+    ///     print(key, value)
+    /// ```
+    fn attributes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, types::PyDict>> {
+        let dict = types::PyDict::new(py);
+        let attributes = self
+            .with_record(|g| g.attributes.clone())
+            .unwrap_or_default();
+        for (key, value) in attributes {
+            dict.set_item(key.to_string(), value.to_string())?;
+        }
+        Ok(dict)
+    }
+
+    /// Get the value of an attribute or None if it is not present.
+    ///
+    /// # Example
+    /// ```python
+    /// gene = gff.attribute("gene_name")
+    /// ```
+    fn attribute(&self, key: &str) -> PyResult<Option<String>> {
+        Ok(self
+            .with_record(|g| g.attribute(key).map(|v| v.to_string()))
+            .flatten())
+    }
+
+    /// Index attributes by name; raises KeyError when the attribute is not present.
+    ///
+    /// # Example
+    /// ```python
+    /// gene_id = gff["gene_id"]
+    /// ```
+    fn __getitem__(&self, key: &str) -> PyResult<String> {
+        self.attribute(key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.inner)
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
+
 // Wrapper for bedder::report::ReportFragment
 /// A fragment of a report containing intersection results.
 ///
@@ -1008,6 +1213,30 @@ impl PyPosition {
         }
     }
 
+    /// get the GFF/GTF feature if this position represents one
+    ///
+    /// # Example
+    /// ```python
+    /// gff = position.gff()
+    /// ```
+    ///
+    fn gff(&self) -> PyResult<Option<PyGffRecord>> {
+        let is_gff = matches!(
+            *self
+                .inner
+                .try_lock()
+                .expect("failed to lock interval in call to .gff()"),
+            Position::Gff(_)
+        );
+        if is_gff {
+            Ok(Some(PyGffRecord {
+                inner: self.inner.clone(),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
     #[getter]
     /// Get the chromosome name
     ///
@@ -1054,6 +1283,22 @@ impl PyPosition {
             .stop())
     }
 
+    #[getter]
+    /// Get the strand ("+", "-" or "." when unknown)
+    ///
+    /// # Example
+    /// ```python
+    /// strand = position.strand
+    /// ```
+    fn strand(&self) -> PyResult<String> {
+        Ok(self
+            .inner
+            .try_lock()
+            .expect("failed to lock interval")
+            .strand()
+            .to_string())
+    }
+
     fn __repr__(&self) -> String {
         format!("{:?}", self.inner)
     }
@@ -1334,6 +1579,19 @@ impl<'py> PythonFunction<'py> {
     pub fn return_type(&self) -> &str {
         &self.return_type
     }
+
+    /// The type of the returned values and whether the function returns a list, e.g.
+    /// `list[int]` is (Integer, true).
+    pub fn value_type(&self) -> Result<(Type, bool), ColumnError> {
+        match self
+            .return_type
+            .strip_prefix("list[")
+            .and_then(|t| t.strip_suffix(']'))
+        {
+            Some(t) => Ok((Type::try_from(t)?, true)),
+            None => Ok((Type::try_from(self.return_type.as_str())?, false)),
+        }
+    }
 }
 
 const BEDDER_PREFIX: &str = "bedder_";
@@ -1358,7 +1616,10 @@ pub fn introspect_python_functions<'py>(
             if let Ok(annotations) = obj.getattr("__annotations__") {
                 if let Ok(dict) = annotations.downcast::<pyo3::types::PyDict>() {
                     if let Some(return_type) = dict.get_item("return")? {
-                        if let Ok(type_name) = return_type.getattr("__name__") {
+                        if return_type.hasattr("__origin__")? {
+                            // generic aliases such as list[int] are named by their repr.
+                            return_type_str = format!("{}", return_type.repr()?);
+                        } else if let Ok(type_name) = return_type.getattr("__name__") {
                             return_type_str = format!("{}", type_name);
                         } else {
                             // Fallback to repr if __name__ is not available
@@ -1376,9 +1637,19 @@ pub fn introspect_python_functions<'py>(
                     }
                 }
             }
-            if !["str", "int", "float", "bool"].contains(&return_type_str.as_str()) {
+            if ![
+                "str",
+                "int",
+                "float",
+                "bool",
+                "list[str]",
+                "list[int]",
+                "list[float]",
+            ]
+            .contains(&return_type_str.as_str())
+            {
                 return Err(PyValueError::new_err(format!(
-                    "Invalid return type '{}'. Expected str, int, float, or bool. Make sure the function has a return annotation.",
+                    "Invalid return type '{}'. Expected str, int, float, bool or a list of str, int or float. Make sure the function has a return annotation.",
                     return_type_str
                 )));
             }
@@ -1420,12 +1691,14 @@ impl<'py> CompiledPython<'py> {
             fname
         )))?;
 
+        let (ftype, is_list) = f
+            .value_type()
+            .map_err(|e| PyValueError::new_err(format!("Invalid type: {}", e)))?;
         Ok(CompiledPython {
             function_name: fname.to_string(),
             f: f.pyfn.clone(),
-            ftype: Type::try_from(f.return_type.as_str())
-                .map_err(|e| PyValueError::new_err(format!("Invalid type: {}", e)))?,
-            number: Number::One,
+            ftype,
+            number: if is_list { Number::Dot } else { Number::One },
         })
     }
 
@@ -1440,8 +1713,15 @@ impl<'py> CompiledPython<'py> {
     #[inline]
     pub fn eval(&self, fragment: PyReportFragment) -> PyResult<Value> {
         let result = self.f.call1((fragment,))?;
-        if self.number != Number::One && self.number != Number::Dot {
-            unimplemented!("Multiple values not supported yet in python eval function");
+        if let Ok(list) = result.downcast_exact::<types::PyList>() {
+            match self.ftype {
+                Type::Integer => Ok(Value::VecInt(list.extract()?)),
+                Type::Float => Ok(Value::VecFloat(list.extract()?)),
+                Type::Character | Type::String => Ok(Value::VecString(list.extract()?)),
+                Type::Flag => Err(PyTypeError::new_err("Result is a list of booleans")),
+            }
+        } else if self.number != Number::One {
+            Err(PyTypeError::new_err("Result is not a list"))
         } else {
             match self.ftype {
                 Type::Integer => result
@@ -1645,6 +1925,7 @@ impl<'py> CompiledExpr<'py> {
 fn bedder_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyBedRecord>()?;
     m.add_class::<PyVcfRecord>()?;
+    m.add_class::<PyGffRecord>()?;
     m.add_class::<PyReportFragment>()?;
     m.add_class::<PyReport>()?;
     m.add_class::<PyPosition>()?;
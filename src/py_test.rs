@@ -2,7 +2,7 @@
 mod tests {
     use crate::bedder_bed::BedRecord;
     use crate::bedder_vcf::BedderRecord;
-    use crate::column::Value;
+    use crate::column::{Column, ColumnReporter, Number, Value, VcfField};
     use crate::hts_format::Format as BedderFormat;
     use crate::intersection::{Intersection, Intersections};
     use crate::position::Position;
@@ -49,6 +49,7 @@ mod tests {
                 interval: Arc::new(Mutex::new(overlap_pos)),
                 id: 0,
             }],
+            query_window: None,
             cached_report: Arc::new(Mutex::new(None)),
         }
     }
@@ -87,6 +88,37 @@ def bedder_test_func(fragment) -> str:
         .expect("Failed to run test");
     }
 
+    #[test]
+    fn test_list_return() {
+        ensure_python_initialized();
+        Python::attach(|py| -> PyResult<()> {
+            let code = r#"
+def bedder_per_sample(fragment) -> list[int]:
+    return [len(fragment.b), 0]
+            "#;
+            py.run(&CString::new(code)?, None, None)?;
+            let globals = py.import("__main__")?.dict();
+            let functions_map = crate::py::introspect_python_functions(py, globals)?;
+            assert_eq!(functions_map["per_sample"].return_type(), "list[int]");
+
+            // a list is one value per sample for FORMAT columns.
+            let info = Column::try_from(("py:per_sample", &functions_map)).unwrap();
+            assert_eq!(info.number(), &Number::Dot);
+            let format = Column::try_from(("FORMAT/py:per_sample", &functions_map)).unwrap();
+            assert_eq!(format.vcf_field(), VcfField::Format);
+            assert_eq!(format.number(), &Number::One);
+
+            let compiled = CompiledPython::new(py, "per_sample", &functions_map)?;
+            let report = create_test_intersection().report(&Arc::new(ReportOptions::default()));
+            for frag in report.iter() {
+                let result = compiled.eval(PyReportFragment::new(frag.clone()))?;
+                assert_eq!(result, Value::VecInt(vec![1, 0]));
+            }
+            Ok(())
+        })
+        .expect("Failed to run test");
+    }
+
     #[test]
     fn test_compiled_map_python_value_conversions() {
         ensure_python_initialized();
@@ -183,6 +215,44 @@ def bedder_map_bad_decl(iv) -> str:
         .expect("map value python conversion test failed");
     }
 
+    #[test]
+    fn test_gff_position() {
+        ensure_python_initialized();
+        Python::attach(|py| -> PyResult<()> {
+            let code = r#"
+def bedder_gff_level(iv) -> float:
+    g = iv.gff()
+    if g is None or g.type != "gene" or g.source != "HAVANA":
+        return None
+    assert g.attributes["gene_name"] == "TP53"
+    assert g.attribute("missing") is None
+    assert g.strand == "-" and g.phase is None and g.score == 5.0
+    return float(g["level"]) + g.start
+
+def bedder_bed_only(iv) -> float:
+    return 1.0 if iv.gff() is None else 0.0
+"#;
+            crate::py::initialize_python(py)?;
+            let c_code = CString::new(code)?;
+            py.run(&c_code, None, None)?;
+            let globals = py.import("__main__")?.dict();
+            let functions_map = crate::py::introspect_python_functions(py, globals)?;
+
+            let level_fn = CompiledMapValuePython::new("gff_level", &functions_map)?;
+            let bed_only_fn = CompiledMapValuePython::new("bed_only", &functions_map)?;
+
+            let record = crate::bedder_gff::GffRecord::parse(
+                "chr17\tHAVANA\tgene\t101\t200\t5\t-\t.\tgene_id \"g1\"; gene_name \"TP53\"; level \"2\";",
+            )?;
+            let pos = Arc::new(Mutex::new(Position::Gff(Box::new(record))));
+            assert_eq!(level_fn.eval_position(&pos)?, Some(102.0));
+            assert_eq!(bed_only_fn.eval_position(&pos)?, Some(0.0));
+
+            Ok(())
+        })
+        .expect("gff python test failed");
+    }
+
     #[test]
     fn test_vcf_info() {
         ensure_python_initialized();
@@ -1,4 +1,6 @@
+use crate::coverage::Coverage;
 use crate::position::Position;
+use crate::report_options::DistanceMode;
 use parking_lot::Mutex;
 use std::sync::Arc;
 #[derive(Debug, Clone)]
@@ -110,6 +112,54 @@ impl Report {
 }
 
 impl ReportFragment {
+    /// Coverage of `a` by the `b` intervals of this fragment. None if there is no `a`.
+    pub fn coverage(&self) -> Option<Coverage> {
+        let a = self.a.as_ref()?;
+        // release a before locking b as they may be the same Arc.
+        let (start, stop) = {
+            let a = a.try_lock().expect("failed to lock a interval in coverage");
+            (a.start(), a.stop())
+        };
+        Some(Coverage::new(
+            start,
+            stop,
+            self.b.iter().map(|b| {
+                let b = b.try_lock().expect("failed to lock b interval in coverage");
+                (b.start(), b.stop())
+            }),
+        ))
+    }
+
+    /// Distance from `a` to the closest `b`, signed by `mode` so that upstream is negative.
+    /// Overlapping intervals have distance 0. None if there is no `a` or no `b`.
+    pub fn signed_distance(&self, mode: DistanceMode) -> Option<i64> {
+        // release a before locking b as they may be the same Arc.
+        let (a_start, a_stop, a_strand) = {
+            let a = self
+                .a
+                .as_ref()?
+                .try_lock()
+                .expect("failed to lock a interval in signed_distance");
+            (a.start(), a.stop(), a.strand())
+        };
+        self.b
+            .iter()
+            .map(|b| {
+                let b = b
+                    .try_lock()
+                    .expect("failed to lock b interval in signed_distance");
+                let distance = if a_start < b.stop() && b.start() < a_stop {
+                    0
+                } else if a_start >= b.stop() {
+                    -((a_start - b.stop()) as i64)
+                } else {
+                    (b.start() - a_stop) as i64
+                };
+                mode.orient(distance, a_strand, b.strand())
+            })
+            .min_by_key(|d| d.unsigned_abs())
+    }
+
     pub fn distance(&self) -> u64 {
         if let Some(a) = &self.a {
             log::info!("a: {:?}", self);
@@ -240,4 +290,48 @@ mod tests {
         };
         assert_eq!(frag.distance(), u64::MAX);
     }
+
+    fn make_bed(start: u64, stop: u64, strand: &str) -> Arc<Mutex<Position>> {
+        Arc::new(Mutex::new(Position::Bed(
+            crate::bedder_bed::BedRecord::new("chr1", start, stop, None, None, vec![strand.into()]),
+        )))
+    }
+
+    #[test]
+    fn test_signed_distance() {
+        // b is 80 bases before a on the reference.
+        let frag = |a_strand, b_strand| ReportFragment {
+            a: Some(make_bed(100, 110, a_strand)),
+            b: vec![make_bed(10, 20, b_strand)],
+            id: 0,
+        };
+        assert_eq!(frag("+", "+").signed_distance(DistanceMode::Ref), Some(-80));
+        assert_eq!(frag("-", "+").signed_distance(DistanceMode::Ref), Some(-80));
+        assert_eq!(frag("+", "+").signed_distance(DistanceMode::A), Some(-80));
+        assert_eq!(frag("-", "+").signed_distance(DistanceMode::A), Some(80));
+        // a is after (downstream of) a forward b.
+        assert_eq!(frag("+", "+").signed_distance(DistanceMode::B), Some(80));
+        assert_eq!(frag("+", "-").signed_distance(DistanceMode::B), Some(-80));
+
+        // the closest b wins, overlaps are 0.
+        let frag = ReportFragment {
+            a: Some(make_bed(100, 110, "+")),
+            b: vec![make_bed(10, 20, "+"), make_bed(115, 120, "+")],
+            id: 0,
+        };
+        assert_eq!(frag.signed_distance(DistanceMode::Ref), Some(5));
+        let frag = ReportFragment {
+            a: Some(make_bed(100, 110, "+")),
+            b: vec![make_bed(105, 120, "+")],
+            id: 0,
+        };
+        assert_eq!(frag.signed_distance(DistanceMode::B), Some(0));
+
+        let frag = ReportFragment {
+            a: Some(make_bed(100, 110, "+")),
+            b: vec![],
+            id: 0,
+        };
+        assert_eq!(frag.signed_distance(DistanceMode::Ref), None);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::position::Strand;
 use clap::ValueEnum;
 use std::{num::ParseFloatError, str::FromStr};
 
@@ -104,6 +105,125 @@ impl Default for &IntersectionMode {
     }
 }
 
+/// StrandMode restricts which B intervals may be reported for an A interval based on strand.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, ValueEnum)]
+pub enum StrandMode {
+    /// Ignore strand.
+    #[value(name = "ignore")]
+    #[default]
+    Ignore,
+    /// Only report B intervals on the same strand as A. Bedtools -s
+    #[value(name = "same")]
+    Same,
+    /// Only report B intervals on the opposite strand from A. Bedtools -S
+    #[value(name = "opposite")]
+    Opposite,
+}
+
+impl StrandMode {
+    /// Can an interval on strand `b` be reported for a query on strand `a`?
+    /// When strand is required, intervals with unknown strand never match.
+    #[inline]
+    pub fn accepts(&self, a: Strand, b: Strand) -> bool {
+        match self {
+            StrandMode::Ignore => true,
+            StrandMode::Same => a != Strand::Unknown && a == b,
+            StrandMode::Opposite => a != Strand::Unknown && b != Strand::Unknown && a != b,
+        }
+    }
+}
+
+impl FromStr for StrandMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+/// DistanceMode determines the sign of distances between A and B intervals. Negative
+/// distances are upstream. Bedtools -D
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, ValueEnum)]
+pub enum DistanceMode {
+    /// Relative to the reference: B before A is negative.
+    #[value(name = "ref")]
+    #[default]
+    Ref,
+    /// Relative to the strand of A: B upstream of A is negative.
+    #[value(name = "a")]
+    A,
+    /// Relative to the strand of B: A upstream of B is negative.
+    #[value(name = "b")]
+    B,
+}
+
+impl DistanceMode {
+    /// Sign `distance`, which is negative when B is before A on the reference, by this mode.
+    /// Unknown strands are treated as forward.
+    #[inline]
+    pub fn orient(&self, distance: i64, a: Strand, b: Strand) -> i64 {
+        match self {
+            DistanceMode::Ref => distance,
+            DistanceMode::A if a == Strand::Reverse => -distance,
+            DistanceMode::A => distance,
+            DistanceMode::B if b == Strand::Reverse => distance,
+            DistanceMode::B => -distance,
+        }
+    }
+}
+
+impl FromStr for DistanceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+/// ClosestDirection limits non-overlapping closest intervals to one side of A, as signed
+/// by a `DistanceMode`. Overlapping intervals are always reported.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ClosestDirection {
+    /// Report intervals on either side.
+    #[value(name = "both")]
+    #[default]
+    Both,
+    /// Only report upstream intervals (negative distance). Bedtools -id
+    #[value(name = "upstream")]
+    Upstream,
+    /// Only report downstream intervals (positive distance). Bedtools -iu
+    #[value(name = "downstream")]
+    Downstream,
+}
+
+impl ClosestDirection {
+    /// Can an interval at signed, non-zero `distance` be reported?
+    #[inline]
+    pub fn accepts(&self, distance: i64) -> bool {
+        match self {
+            ClosestDirection::Both => true,
+            ClosestDirection::Upstream => distance < 0,
+            ClosestDirection::Downstream => distance > 0,
+        }
+    }
+}
+
+/// TieMode determines which intervals are kept when several are equally close and they
+/// do not all fit within n-closest. Bedtools -t
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, ValueEnum)]
+pub enum TieMode {
+    /// Keep the first tied intervals (in genome order).
+    #[value(name = "first")]
+    #[default]
+    First,
+    /// Keep the last tied intervals (in genome order).
+    #[value(name = "last")]
+    Last,
+    /// Keep all tied intervals, even if more than n-closest are reported.
+    #[value(name = "all")]
+    All,
+}
+
 /// OverlapAmount indicates the amount of overlap required.
 /// Either as bases or as a fraction of the total length.
 #[derive(PartialEq, Debug, Clone)]
@@ -181,6 +301,10 @@ pub struct ReportOptions {
     pub b_piece: IntersectionPart,
     pub a_requirements: OverlapAmount,
     pub b_requirements: OverlapAmount,
+    pub strand: StrandMode,
+    /// measure overlaps (and report pieces) against the blocks of BED12 records rather than
+    /// their whole span, so that e.g. intervals in an intron do not overlap a transcript.
+    pub split: bool,
 }
 
 impl ReportOptions {
@@ -208,6 +332,8 @@ pub struct ReportOptionsBuilder {
     b_piece: IntersectionPart,
     a_requirements: OverlapAmount,
     b_requirements: OverlapAmount,
+    strand: StrandMode,
+    split: bool,
 }
 
 impl Default for ReportOptionsBuilder {
@@ -226,6 +352,8 @@ impl ReportOptionsBuilder {
             b_piece: IntersectionPart::WholeWide,
             a_requirements: OverlapAmount::Bases(1),
             b_requirements: OverlapAmount::Bases(1),
+            strand: StrandMode::Ignore,
+            split: false,
         }
     }
 
@@ -265,6 +393,18 @@ impl ReportOptionsBuilder {
         self
     }
 
+    /// Set the strand requirement
+    pub fn strand(mut self, strand: StrandMode) -> Self {
+        self.strand = strand;
+        self
+    }
+
+    /// Set whether overlaps are measured against BED12 blocks
+    pub fn split(mut self, split: bool) -> Self {
+        self.split = split;
+        self
+    }
+
     /// Build the ReportOptions
     pub fn build(self) -> ReportOptions {
         ReportOptions {
@@ -274,6 +414,8 @@ impl ReportOptionsBuilder {
             b_piece: self.b_piece,
             a_requirements: self.a_requirements,
             b_requirements: self.b_requirements,
+            strand: self.strand,
+            split: self.split,
         }
     }
 }
@@ -0,0 +1,353 @@
+use crate::chrom_ordering::Chromosome;
+use crate::position::{Position, PositionedIterator, Strand};
+use crate::report_options::OverlapAmount;
+use crate::string::String;
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::io::{self, Error};
+
+/// SlopIterator wraps a sorted `PositionedIterator` and extends each interval by `left` bases
+/// before its start and `right` bases after its end. With `with_flank(true)` each interval is
+/// instead replaced by its left and right flanks (empty flanks are not reported).
+///
+/// Amounts are either a number of bases or a fraction of each interval's length. With
+/// `with_strand(true)`, `left` and `right` are upstream and downstream of intervals on the
+/// reverse strand. Results are clamped to 0 and to the chromosome length when it is known.
+///
+/// Because the amount can differ between intervals, results are re-sorted by start; this
+/// buffers at most one chromosome of intervals when a fraction is used.
+pub struct SlopIterator {
+    inner: Box<dyn PositionedIterator>,
+    chromosome_order: HashMap<String, Chromosome>,
+    left: OverlapAmount,
+    right: OverlapAmount,
+    strand_aware: bool,
+    flank: bool,
+
+    // transformed intervals on the current chromosome, sorted by start.
+    queue: VecDeque<Position>,
+    // next input interval, not yet transformed.
+    pending: Option<Position>,
+    done: bool,
+}
+
+impl SlopIterator {
+    pub fn new(
+        inner: Box<dyn PositionedIterator>,
+        chromosome_order: &HashMap<String, Chromosome>,
+        left: OverlapAmount,
+        right: OverlapAmount,
+    ) -> Self {
+        SlopIterator {
+            inner,
+            chromosome_order: chromosome_order.clone(),
+            left,
+            right,
+            strand_aware: false,
+            flank: false,
+            queue: VecDeque::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Treat `left` as upstream and `right` as downstream; they are swapped for intervals on
+    /// the reverse strand. Intervals with unknown strand are treated as forward.
+    pub fn with_strand(mut self, strand_aware: bool) -> Self {
+        self.strand_aware = strand_aware;
+        self
+    }
+
+    /// Report the flanks of each interval rather than the extended interval.
+    pub fn with_flank(mut self, flank: bool) -> Self {
+        self.flank = flank;
+        self
+    }
+
+    // the most that any interval can be extended to the left, if bounded.
+    fn max_left(&self) -> Option<u64> {
+        let bases = |a: &OverlapAmount| match a {
+            OverlapAmount::Bases(b) => Some(*b),
+            OverlapAmount::Fraction(_) => None,
+        };
+        if self.strand_aware {
+            Some(bases(&self.left)?.max(bases(&self.right)?))
+        } else {
+            bases(&self.left)
+        }
+    }
+
+    fn amount(a: &OverlapAmount, length: u64) -> u64 {
+        match a {
+            OverlapAmount::Bases(b) => *b,
+            OverlapAmount::Fraction(f) => (*f as f64 * length as f64).round() as u64,
+        }
+    }
+
+    fn insert(&mut self, position: Position) {
+        let i = self
+            .queue
+            .partition_point(|p| p.start() <= position.start());
+        self.queue.insert(i, position);
+    }
+
+    fn add(&mut self, position: Position) -> io::Result<()> {
+        if matches!(position, Position::Vcf(_)) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "slop and flank do not support VCF records (from {})",
+                    self.inner.name()
+                ),
+            ));
+        }
+        let chrom_length = match self.chromosome_order.get(position.chrom()) {
+            Some(c) => c.length.map(|l| l as u64),
+            None => {
+                return Err(Error::other(format!(
+                    "invalid chromosome: {}:{}-{} in iterator {}",
+                    position.chrom(),
+                    position.start() + 1,
+                    position.stop(),
+                    self.inner.name()
+                )))
+            }
+        };
+
+        let (start, stop) = (position.start(), position.stop());
+        let length = stop - start;
+        let (mut left, mut right) = (
+            Self::amount(&self.left, length),
+            Self::amount(&self.right, length),
+        );
+        if self.strand_aware && position.strand() == Strand::Reverse {
+            std::mem::swap(&mut left, &mut right);
+        }
+        let new_start = start.saturating_sub(left);
+        let new_stop = match chrom_length {
+            // don't shrink intervals that are already beyond the end.
+            Some(l) => (stop + right).min(l.max(stop)),
+            None => stop + right,
+        };
+
+        if !self.flank {
+            let mut p = position;
+            p.set_start(new_start);
+            p.set_stop(new_stop);
+            self.insert(p);
+            return Ok(());
+        }
+        if new_start < start {
+            let mut p = position.clone();
+            p.set_start(new_start);
+            p.set_stop(start);
+            self.insert(p);
+        }
+        if stop < new_stop {
+            let mut p = position;
+            p.set_start(stop);
+            p.set_stop(new_stop);
+            self.insert(p);
+        }
+        Ok(())
+    }
+
+    // can the front of the queue be reported given that the next input starts at `start`
+    // on `chrom`?
+    fn ready(&self, chrom: &str, start: u64) -> bool {
+        match self.queue.front() {
+            Some(p) if p.chrom() != chrom => true,
+            Some(p) => self
+                .max_left()
+                .is_some_and(|max_left| p.start() + max_left <= start),
+            None => false,
+        }
+    }
+}
+
+impl Iterator for SlopIterator {
+    type Item = io::Result<Position>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(p) = &self.pending {
+                // report anything that can no longer be preceded by a later interval.
+                if self.ready(p.chrom(), p.start()) {
+                    return self.queue.pop_front().map(Ok);
+                }
+                let p = self.pending.take().expect("pending was checked above");
+                if let Err(e) = self.add(p) {
+                    return Some(Err(e));
+                }
+            }
+            if self.done {
+                return self.queue.pop_front().map(Ok);
+            }
+            match self.inner.next_position(None) {
+                Some(Ok(p)) => self.pending = Some(p),
+                Some(Err(e)) => return Some(Err(e)),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+impl PositionedIterator for SlopIterator {
+    fn name(&self) -> String {
+        if self.flank {
+            format!("flank:{}", self.inner.name())
+        } else {
+            format!("slop:{}", self.inner.name())
+        }
+    }
+
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bedder_bed::BedRecord;
+    use crate::chrom_ordering::parse_genome;
+
+    struct Intervals {
+        ivs: std::vec::IntoIter<Position>,
+    }
+
+    impl PositionedIterator for Intervals {
+        fn name(&self) -> String {
+            String::from("intervals")
+        }
+
+        fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+            self.ivs.next().map(Ok)
+        }
+    }
+
+    fn beds(ivs: &[(&str, u64, u64, &str)]) -> Box<dyn PositionedIterator> {
+        Box::new(Intervals {
+            ivs: ivs
+                .iter()
+                .map(|(chrom, start, stop, strand)| {
+                    Position::Bed(BedRecord::new(
+                        chrom,
+                        *start,
+                        *stop,
+                        None,
+                        None,
+                        vec![(*strand).into()],
+                    ))
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    fn regions(it: SlopIterator) -> Vec<(String, u64, u64)> {
+        it.map(|p| {
+            let p = p.expect("error in slop");
+            (String::from(p.chrom()), p.start(), p.stop())
+        })
+        .collect()
+    }
+
+    fn coords(r: &[(String, u64, u64)]) -> Vec<(u64, u64)> {
+        r.iter().map(|(_, s, e)| (*s, *e)).collect()
+    }
+
+    #[test]
+    fn test_slop_clamps_to_chromosome() {
+        let genome = parse_genome("chr1\t100\nchr2\t50\n".as_bytes()).unwrap();
+        let ivs = beds(&[
+            ("chr1", 5, 10, "+"),
+            ("chr1", 50, 60, "+"),
+            ("chr1", 95, 100, "+"),
+            ("chr2", 45, 48, "+"),
+        ]);
+        let r = regions(SlopIterator::new(
+            ivs,
+            &genome,
+            OverlapAmount::Bases(10),
+            OverlapAmount::Bases(10),
+        ));
+        assert_eq!(
+            r,
+            vec![
+                (String::from("chr1"), 0, 20),
+                (String::from("chr1"), 40, 70),
+                (String::from("chr1"), 85, 100),
+                (String::from("chr2"), 35, 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slop_fraction_resorts() {
+        let genome = parse_genome("chr1\t2000\n".as_bytes()).unwrap();
+        let ivs = beds(&[("chr1", 100, 110, "+"), ("chr1", 105, 1105, "+")]);
+        let r = regions(SlopIterator::new(
+            ivs,
+            &genome,
+            OverlapAmount::Fraction(0.5),
+            OverlapAmount::Bases(0),
+        ));
+        assert_eq!(coords(&r), vec![(0, 1105), (95, 110)]);
+    }
+
+    #[test]
+    fn test_flank_resorts() {
+        let genome = parse_genome("chr1\t2000\n".as_bytes()).unwrap();
+        let ivs = beds(&[("chr1", 100, 1000, "+"), ("chr1", 200, 300, "+")]);
+        let r = regions(
+            SlopIterator::new(
+                ivs,
+                &genome,
+                OverlapAmount::Bases(10),
+                OverlapAmount::Bases(10),
+            )
+            .with_flank(true),
+        );
+        assert_eq!(
+            coords(&r),
+            vec![(90, 100), (190, 200), (300, 310), (1000, 1010)]
+        );
+    }
+
+    #[test]
+    fn test_stranded_flank() {
+        // 20 bases upstream of each interval, e.g. promoters.
+        let genome = parse_genome("chr1\t1000\n".as_bytes()).unwrap();
+        let ivs = beds(&[
+            ("chr1", 10, 200, "+"),
+            ("chr1", 150, 250, "-"),
+            ("chr1", 300, 400, "."),
+        ]);
+        let r = regions(
+            SlopIterator::new(
+                ivs,
+                &genome,
+                OverlapAmount::Bases(20),
+                OverlapAmount::Bases(0),
+            )
+            .with_strand(true)
+            .with_flank(true),
+        );
+        assert_eq!(coords(&r), vec![(0, 10), (250, 270), (280, 300)]);
+    }
+
+    #[test]
+    fn test_slop_invalid_chromosome() {
+        let genome = parse_genome("chr1\t1000\n".as_bytes()).unwrap();
+        let ivs = beds(&[("chrX", 10, 20, "+")]);
+        let r: Vec<_> = SlopIterator::new(
+            ivs,
+            &genome,
+            OverlapAmount::Bases(1),
+            OverlapAmount::Bases(1),
+        )
+        .collect();
+        assert!(r.iter().any(|p| p.is_err()));
+    }
+}
@@ -1,9 +1,16 @@
+use crate::bedder_bam::BedderBam;
+use crate::bedder_bbi::BedderBbi;
 use crate::bedder_bed::BedderBed;
+use crate::bedder_d4::BedderD4;
+use crate::bedder_gff::BedderGff;
 use crate::bedder_vcf::BedderVCF;
+use crate::chrom_ordering::Chromosome;
 use crate::position::PositionedIterator;
-use flate2::bufread::GzDecoder;
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+use hashbrown::HashMap;
 use log::info;
-use std::io::{self, Read};
+use std::fs::File;
+use std::io::{self, BufRead, Read, Seek};
 use std::path::Path;
 
 #[derive(Debug)]
@@ -11,6 +18,13 @@ pub enum FileType {
     Bed,
     Vcf,
     Bcf,
+    Sam,
+    Bam,
+    Cram,
+    Gff,
+    BigWig,
+    BigBed,
+    D4,
 }
 
 #[derive(Debug)]
@@ -21,12 +35,86 @@ pub enum Compression {
     None,
 }
 
+/// A file or a stream (stdin or a pipe such as process substitution) to read from.
+/// Streams can not seek, so they are read without index skipping.
+pub enum Input {
+    File(io::BufReader<File>),
+    Stream(Box<dyn BufRead>),
+}
+
+impl Input {
+    /// `-` is stdin. Files that can not seek (e.g. `<(zcat a.bed.gz)`) are read as streams.
+    pub fn open<P: AsRef<Path>>(p: P) -> io::Result<Self> {
+        if p.as_ref().to_str() == Some("-") {
+            return Ok(Input::Stream(Box::new(io::BufReader::new(io::stdin()))));
+        }
+        let mut file = File::open(p)?;
+        if file.stream_position().is_err() {
+            return Ok(Input::Stream(Box::new(io::BufReader::new(file))));
+        }
+        Ok(Input::File(io::BufReader::new(file)))
+    }
+
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Input::Stream(_))
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(f) => f.read(buf),
+            Input::Stream(s) => s.read(buf),
+        }
+    }
+}
+
+impl BufRead for Input {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Input::File(f) => f.fill_buf(),
+            Input::Stream(s) => s.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Input::File(f) => f.consume(amt),
+            Input::Stream(s) => s.consume(amt),
+        }
+    }
+}
+
+fn not_seekable() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "can not seek in a stream")
+}
+
+impl Seek for Input {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        match self {
+            Input::File(f) => f.seek(pos),
+            Input::Stream(_) => Err(not_seekable()),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        match self {
+            Input::File(f) => f.stream_position(),
+            Input::Stream(_) => Err(not_seekable()),
+        }
+    }
+}
+
 pub enum BedderReader<R>
 where
     R: io::BufRead + io::Seek + 'static,
 {
     BedderBed(Box<BedderBed<'static, R>>),
     BedderVcf(BedderVCF),
+    BedderBam(BedderBam),
+    BedderGff(BedderGff),
+    BedderBbi(BedderBbi),
+    BedderD4(BedderD4),
 }
 
 impl<R> BedderReader<R>
@@ -43,21 +131,103 @@ where
         match self {
             BedderReader::BedderBed(rdr) => Box::new(*rdr),
             BedderReader::BedderVcf(rdr) => Box::new(rdr),
+            BedderReader::BedderBam(rdr) => Box::new(rdr),
+            BedderReader::BedderGff(rdr) => Box::new(rdr),
+            BedderReader::BedderBbi(rdr) => Box::new(rdr),
+            BedderReader::BedderD4(rdr) => Box::new(rdr),
+        }
+    }
+
+    /// bigWig, bigBed and D4 files are read in the chromosome order of the genome file.
+    /// Other readers follow the order of the file and are unchanged.
+    pub fn with_chromosome_order(
+        self,
+        chrom_order: &HashMap<crate::string::String, Chromosome>,
+    ) -> Self {
+        match self {
+            BedderReader::BedderBbi(rdr) => {
+                BedderReader::BedderBbi(rdr.with_chromosome_order(chrom_order))
+            }
+            BedderReader::BedderD4(rdr) => {
+                BedderReader::BedderD4(rdr.with_chromosome_order(chrom_order))
+            }
+            rdr => rdr,
         }
     }
 }
 // TODO: https://github.com/quinlan-lab/bedder-rs/blob/ffddd2b3a2075594a5375fb81b8672f4f5039acf/src/sniff.rs
 pub fn open<P: AsRef<Path>, R: io::BufRead + io::Seek + 'static>(
+    reader: R,
+    p: P,
+) -> io::Result<(BedderReader<R>, FileType)> {
+    open_with_reference(reader, p, None)
+}
+
+/// Open a path that may be `-` for stdin or a pipe. BED and GFF/GTF can be read from streams
+/// (plain, gzip or bgzf); other formats must be files. See `open_with_reference`.
+pub fn open_path<P: AsRef<Path>>(
+    p: P,
+    reference: Option<&Path>,
+) -> io::Result<(BedderReader<Input>, FileType)> {
+    let input = match Input::open(&p)? {
+        Input::Stream(stream) => decompress_stream(stream, p.as_ref())?,
+        input => input,
+    };
+    open_with_reference(input, p, reference)
+}
+
+// streams are decompressed here, as they are read, so the readers see plain text.
+fn decompress_stream(mut stream: Box<dyn BufRead>, p: &Path) -> io::Result<Input> {
+    let (ft, c) = sniff(&mut stream).map_err(|e| io::Error::other(e.to_string()))?;
+    if !matches!(ft, FileType::Bed | FileType::Gff) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{:?} can not be read from stdin or a pipe ({}); use a file",
+                ft,
+                p.display()
+            ),
+        ));
+    }
+    Ok(Input::Stream(match c {
+        Compression::None => stream,
+        _ => Box::new(io::BufReader::new(MultiGzDecoder::new(stream))),
+    }))
+}
+
+/// Like `open`, but `reference` is the FASTA used to decode CRAM files.
+/// Index skipping is disabled for BED readers that can not seek.
+pub fn open_with_reference<P: AsRef<Path>, R: io::BufRead + io::Seek + 'static>(
     mut reader: R,
     p: P,
+    reference: Option<&Path>,
 ) -> io::Result<(BedderReader<R>, FileType)> {
     let (ft, c) = sniff(&mut reader).map_err(|e| io::Error::other(e.to_string()))?;
     info!("sniffed file type: {:?}, compression: {:?}", ft, c);
     let rdr = match ft {
-        FileType::Bed => BedderReader::BedderBed(Box::new(BedderBed::new(reader, Some(p)))),
+        FileType::Bed => {
+            let seekable = reader.stream_position().is_ok();
+            BedderReader::BedderBed(Box::new(
+                BedderBed::new(reader, Some(p)).with_index_skipping(seekable),
+            ))
+        }
         FileType::Vcf | FileType::Bcf => {
             BedderReader::BedderVcf(BedderVCF::from_path(p.as_ref().to_str().unwrap())?)
         }
+        FileType::Sam | FileType::Bam | FileType::Cram => BedderReader::BedderBam(
+            BedderBam::from_path(p.as_ref().to_str().unwrap(), reference)?,
+        ),
+        FileType::Gff => {
+            let reader: Box<dyn io::BufRead> = match c {
+                Compression::None => Box::new(reader),
+                _ => Box::new(io::BufReader::new(MultiGzDecoder::new(reader))),
+            };
+            BedderReader::BedderGff(BedderGff::new(reader, &p.as_ref().to_string_lossy()))
+        }
+        FileType::BigWig | FileType::BigBed => {
+            BedderReader::BedderBbi(BedderBbi::from_path(&p.as_ref().to_string_lossy())?)
+        }
+        FileType::D4 => BedderReader::BedderD4(BedderD4::from_path(&p.as_ref().to_string_lossy())?),
     };
     Ok((rdr, ft))
 }
@@ -66,12 +236,20 @@ pub fn sniff<R: io::BufRead>(
     rdr: &mut R,
 ) -> Result<(FileType, Compression), Box<dyn std::error::Error>> {
     let buf = rdr.fill_buf()?;
+    // bigWig and bigBed magic numbers are little-endian u32s.
+    if buf.starts_with(&[0x26, 0xFC, 0x8F, 0x88]) {
+        return Ok((FileType::BigWig, Compression::None));
+    } else if buf.starts_with(&[0xEB, 0xF2, 0x89, 0x87]) {
+        return Ok((FileType::BigBed, Compression::None));
+    } else if buf.starts_with(b"d4\xdd\xdd") {
+        return Ok((FileType::D4, Compression::None));
+    }
     let mut dec_buf = vec![0u8; buf.len()];
 
-    let is_gzipped = &buf[0..2] == b"\x1f\x8b";
+    let is_gzipped = buf.starts_with(b"\x1f\x8b");
     let mut c = Compression::None;
 
-    if is_gzipped && buf[3] & 4 != 0 && buf.len() >= 18 {
+    if is_gzipped && buf.len() >= 18 && buf[3] & 4 != 0 {
         c = match &buf[12..16] {
             b"BC\x02\x00" => Compression::BGZF,
             b"RAZF" => Compression::RAZF,
@@ -103,6 +281,17 @@ pub fn sniff<R: io::BufRead>(
         FileType::Vcf
     } else if buf.starts_with(b"BCF") && (buf[3] == 0x2 || buf[3] == 0x4) {
         FileType::Bcf
+    } else if buf.starts_with(b"BAM\x01") {
+        FileType::Bam
+    } else if buf.starts_with(b"CRAM") {
+        FileType::Cram
+    } else if [b"@HD\t", b"@SQ\t", b"@RG\t", b"@PG\t", b"@CO\t"]
+        .iter()
+        .any(|h| buf.starts_with(*h))
+    {
+        FileType::Sam
+    } else if buf.starts_with(b"##gff-version") || looks_like_gff(buf) {
+        FileType::Gff
     } else {
         FileType::Bed
     };
@@ -110,6 +299,24 @@ pub fn sniff<R: io::BufRead>(
     Ok((ft, c))
 }
 
+// GTF files have no required header so check the first data line: 9 columns with integer
+// start and end, a strand and a phase.
+fn looks_like_gff(buf: &[u8]) -> bool {
+    let Some(line) = buf
+        .split(|&b| b == b'\n')
+        .find(|l| !l.is_empty() && !l.starts_with(b"#"))
+    else {
+        return false;
+    };
+    let line = String::from_utf8_lossy(line);
+    let fields: Vec<&str> = line.trim_end().split('\t').collect();
+    fields.len() == 9
+        && fields[3].parse::<u64>().is_ok()
+        && fields[4].parse::<u64>().is_ok()
+        && matches!(fields[6], "+" | "-" | "." | "?")
+        && matches!(fields[7], "." | "0" | "1" | "2")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,6 +397,80 @@ mod tests {
         assert_eq!(s, "chr1\t1000\t2000\nchr2\t3000\t4000");
     }
 
+    #[test]
+    fn test_alignments() {
+        for (path, expected) in [("tests/test.sam", "Sam"), ("tests/test.bam", "Bam")] {
+            let mut rdr = io::BufReader::new(std::fs::File::open(path).unwrap());
+            let (ft, _) = sniff(&mut rdr).unwrap();
+            assert_eq!(format!("{:?}", ft), expected);
+        }
+
+        let mut cursor = Cursor::new(b"CRAM\x03\x00");
+        let (ft, c) = sniff(&mut cursor).unwrap();
+        assert!(matches!(ft, FileType::Cram));
+        assert!(matches!(c, Compression::None));
+    }
+
+    #[test]
+    fn test_gzip_bed_stream() {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(b"chr1\t1000\t2000\nchr1\t3000\t4000\n")
+            .unwrap();
+        let stream: Box<dyn BufRead> = Box::new(Cursor::new(encoder.finish().unwrap()));
+        let input = decompress_stream(stream, Path::new("-")).unwrap();
+        assert!(input.is_stream());
+        let (rdr, ft) = open(input, "-").unwrap();
+        assert!(matches!(ft, FileType::Bed));
+
+        // the query would skip past the first record if the stream were indexed.
+        let mut it = rdr.into_positioned_iterator();
+        let q = crate::position::Position::Bed(crate::bedder_bed::BedRecord::new(
+            "chr1",
+            3000,
+            3001,
+            None,
+            None,
+            vec![],
+        ));
+        let p = it.next_position(Some(&q)).unwrap().unwrap();
+        assert_eq!(p.start(), 1000);
+        assert_eq!(it.next_position(None).unwrap().unwrap().start(), 3000);
+        assert!(it.next_position(None).is_none());
+
+        let vcf: Box<dyn BufRead> = Box::new(Cursor::new(b"##fileformat=VCFv4.3\n".to_vec()));
+        assert!(decompress_stream(vcf, Path::new("-")).is_err());
+    }
+
+    #[test]
+    fn test_bbi() {
+        for (path, expected) in [("tests/test.bw", "BigWig"), ("tests/test.bb", "BigBed")] {
+            let mut rdr = io::BufReader::new(std::fs::File::open(path).unwrap());
+            let (ft, c) = sniff(&mut rdr).unwrap();
+            assert_eq!(format!("{:?}", ft), expected);
+            assert!(matches!(c, Compression::None));
+        }
+        let (ft, _) = sniff(&mut Cursor::new(b"d4\xdd\xdd\x00\x00\x00\x00")).unwrap();
+        assert!(matches!(ft, FileType::D4));
+    }
+
+    #[test]
+    fn test_gff() {
+        let gff3 = b"##gff-version 3\nchr1\t.\tgene\t1\t100\t.\t+\t.\tID=g1\n";
+        let (ft, _) = sniff(&mut Cursor::new(gff3)).unwrap();
+        assert!(matches!(ft, FileType::Gff));
+
+        let gtf =
+            b"#!genome-build GRCh38\nchr1\tHAVANA\texon\t11869\t12227\t.\t+\t.\tgene_id \"g1\";\n";
+        let (ft, _) = sniff(&mut Cursor::new(gtf)).unwrap();
+        assert!(matches!(ft, FileType::Gff));
+
+        // BED12 has integer thickStart/thickEnd in the strand and phase columns.
+        let bed12 = b"chr1\t100\t200\tname\t0\t+\t100\t200\t0\t1\t100,\t0,\n";
+        let (ft, _) = sniff(&mut Cursor::new(bed12)).unwrap();
+        assert!(matches!(ft, FileType::Bed));
+    }
+
     #[test]
     fn test_bgzip_vcf() {
         let data = b"##fileformat=VCFv4.3\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\nchr1\t1000\t.\tA\tT\t.\t.\t.";
@@ -72,6 +72,7 @@ pub(crate) fn parse_intersections(input: &str) -> Intersections {
     Intersections {
         base_interval: Arc::new(Mutex::new(Position::Interval(base_interval))),
         overlapping: intersections,
+        query_window: None,
         cached_report: Arc::new(Mutex::new(None)),
     }
 }
@@ -0,0 +1,320 @@
+use crate::bedder_bed::BedRecord;
+use crate::chrom_ordering::Chromosome;
+use crate::position::{Position, PositionedIterator};
+use crate::string::String;
+use hashbrown::HashMap;
+use std::io::{self, Error};
+
+/// How each region is split into windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSpec {
+    /// Windows of `size` bases starting every `step` bases. The last window in a region may be
+    /// shorter. `step == size` gives tiling windows; `step < size` gives sliding windows.
+    Size { size: u64, step: u64 },
+    /// Split each region into this many (nearly) equal windows.
+    Count(u64),
+}
+
+enum WindowSource {
+    // (chromosome, length) in genome order.
+    Genome(std::vec::IntoIter<(String, u64)>),
+    Regions(Box<dyn PositionedIterator>),
+}
+
+struct Region {
+    chrom: String,
+    start: u64,
+    stop: u64,
+    name: Option<std::string::String>,
+    // 0-based index of the next window in this region.
+    window: u64,
+}
+
+/// WindowIterator generates windows across a genome or across a set of regions.
+/// Windows are reported as BED positions so they can be used as the query of an
+/// `IntersectionIterator`. When regions are used they should be sorted and non-overlapping
+/// for the windows to be sorted.
+pub struct WindowIterator {
+    source: WindowSource,
+    spec: WindowSpec,
+    name_windows: bool,
+    region: Option<Region>,
+}
+
+impl WindowIterator {
+    /// Windows across every chromosome of the genome, in genome order.
+    /// Every chromosome must have a length.
+    pub fn from_genome(
+        chromosome_order: &HashMap<String, Chromosome>,
+        spec: WindowSpec,
+    ) -> io::Result<Self> {
+        let mut chromosomes: Vec<(&String, &Chromosome)> = chromosome_order.iter().collect();
+        chromosomes.sort_by_key(|(_, c)| c.index);
+        let chromosomes = chromosomes
+            .into_iter()
+            .map(|(name, c)| match c.length {
+                Some(length) => Ok((name.clone(), length as u64)),
+                None => Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "windows across the genome require a length for chromosome {} in the genome file",
+                        name
+                    ),
+                )),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Self::new(WindowSource::Genome(chromosomes.into_iter()), spec)
+    }
+
+    /// Windows across each interval from `regions`.
+    pub fn from_regions(
+        regions: Box<dyn PositionedIterator>,
+        spec: WindowSpec,
+    ) -> io::Result<Self> {
+        Self::new(WindowSource::Regions(regions), spec)
+    }
+
+    fn new(source: WindowSource, spec: WindowSpec) -> io::Result<Self> {
+        let valid = match spec {
+            WindowSpec::Size { size, step } => size > 0 && step > 0,
+            WindowSpec::Count(n) => n > 0,
+        };
+        if !valid {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("window size, step and count must be > 0; got {:?}", spec),
+            ));
+        }
+        Ok(WindowIterator {
+            source,
+            spec,
+            name_windows: false,
+            region: None,
+        })
+    }
+
+    /// Name each window with its 1-based number within its region, prefixed by the
+    /// region name (as `name_number`) when the region has one.
+    pub fn with_names(mut self, name_windows: bool) -> Self {
+        self.name_windows = name_windows;
+        self
+    }
+
+    fn next_region(&mut self) -> Option<io::Result<Region>> {
+        match &mut self.source {
+            WindowSource::Genome(chromosomes) => chromosomes.next().map(|(chrom, length)| {
+                Ok(Region {
+                    chrom,
+                    start: 0,
+                    stop: length,
+                    name: None,
+                    window: 0,
+                })
+            }),
+            WindowSource::Regions(regions) => regions.next_position(None).map(|p| {
+                p.map(|p| Region {
+                    chrom: String::from(p.chrom()),
+                    start: p.start(),
+                    stop: p.stop(),
+                    name: p.name().map(|n| n.to_string()),
+                    window: 0,
+                })
+            }),
+        }
+    }
+
+    // index and bounds of the next window in region, if any.
+    fn next_window(spec: WindowSpec, region: &Region) -> Option<(u64, u64, u64)> {
+        match spec {
+            WindowSpec::Size { size, step } => {
+                let start = region.start + region.window * step;
+                if start >= region.stop {
+                    return None;
+                }
+                Some((region.window, start, (start + size).min(region.stop)))
+            }
+            WindowSpec::Count(n) => {
+                let length = region.stop - region.start;
+                // skip empty windows when the region is shorter than n.
+                let mut w = region.window;
+                while w < n {
+                    let start = region.start + w * length / n;
+                    let stop = region.start + (w + 1) * length / n;
+                    if start < stop {
+                        return Some((w, start, stop));
+                    }
+                    w += 1;
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Iterator for WindowIterator {
+    type Item = io::Result<Position>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.region.is_none() {
+                match self.next_region()? {
+                    Ok(r) => self.region = Some(r),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            let region = self.region.as_mut().expect("region was set above");
+            let Some((window, start, stop)) = Self::next_window(self.spec, region) else {
+                self.region = None;
+                continue;
+            };
+            region.window = window + 1;
+            let name = if self.name_windows {
+                Some(match &region.name {
+                    Some(n) => format!("{}_{}", n, region.window),
+                    None => region.window.to_string(),
+                })
+            } else {
+                None
+            };
+            return Some(Ok(Position::Bed(BedRecord::new(
+                &region.chrom,
+                start,
+                stop,
+                name.as_deref(),
+                None,
+                vec![],
+            ))));
+        }
+    }
+}
+
+impl PositionedIterator for WindowIterator {
+    fn name(&self) -> String {
+        match &self.source {
+            WindowSource::Genome(_) => String::from("windows:genome"),
+            WindowSource::Regions(r) => format!("windows:{}", r.name()),
+        }
+    }
+
+    fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chrom_ordering::parse_genome;
+    use crate::intersection::IntersectionIterator;
+    use crate::interval::Interval;
+
+    struct Intervals {
+        ivs: std::vec::IntoIter<Position>,
+    }
+
+    impl PositionedIterator for Intervals {
+        fn name(&self) -> String {
+            String::from("intervals")
+        }
+
+        fn next_position(&mut self, _q: Option<&Position>) -> Option<io::Result<Position>> {
+            self.ivs.next().map(Ok)
+        }
+    }
+
+    fn intervals(ivs: &[(&str, u64, u64)]) -> Box<dyn PositionedIterator> {
+        Box::new(Intervals {
+            ivs: ivs
+                .iter()
+                .map(|(chrom, start, stop)| {
+                    Position::Interval(Interval {
+                        chrom: String::from(*chrom),
+                        start: *start,
+                        stop: *stop,
+                        ..Default::default()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    fn windows(mut it: WindowIterator) -> Vec<(String, u64, u64, Option<String>)> {
+        let mut result = vec![];
+        while let Some(p) = it.next_position(None) {
+            let p = p.expect("error generating window");
+            result.push((
+                String::from(p.chrom()),
+                p.start(),
+                p.stop(),
+                p.name().map(String::from),
+            ));
+        }
+        result
+    }
+
+    #[test]
+    fn test_genome_tiling_windows() {
+        let genome = parse_genome("chr1\t25\nchr2\t10\n".as_bytes()).unwrap();
+        let spec = WindowSpec::Size { size: 10, step: 10 };
+        let w = windows(WindowIterator::from_genome(&genome, spec).unwrap());
+        let coords: Vec<_> = w.iter().map(|(c, s, e, _)| (c.as_str(), *s, *e)).collect();
+        assert_eq!(
+            coords,
+            vec![
+                ("chr1", 0, 10),
+                ("chr1", 10, 20),
+                ("chr1", 20, 25),
+                ("chr2", 0, 10)
+            ]
+        );
+
+        let genome = parse_genome("chr1\t25\nchr2\n".as_bytes()).unwrap();
+        assert!(WindowIterator::from_genome(&genome, spec).is_err());
+    }
+
+    #[test]
+    fn test_sliding_windows_with_names() {
+        let regions = intervals(&[("chr1", 100, 120)]);
+        let spec = WindowSpec::Size { size: 10, step: 5 };
+        let w = windows(
+            WindowIterator::from_regions(regions, spec)
+                .unwrap()
+                .with_names(true),
+        );
+        assert_eq!(
+            w,
+            vec![
+                (String::from("chr1"), 100, 110, Some(String::from("1"))),
+                (String::from("chr1"), 105, 115, Some(String::from("2"))),
+                (String::from("chr1"), 110, 120, Some(String::from("3"))),
+                (String::from("chr1"), 115, 120, Some(String::from("4"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_windows() {
+        let regions = intervals(&[("chr1", 0, 10), ("chr1", 20, 22)]);
+        let w = windows(WindowIterator::from_regions(regions, WindowSpec::Count(3)).unwrap());
+        let coords: Vec<_> = w.iter().map(|(_, s, e, _)| (*s, *e)).collect();
+        // the 2-base region can only be split into 2 non-empty windows.
+        assert_eq!(coords, vec![(0, 3), (3, 6), (6, 10), (20, 21), (21, 22)]);
+
+        assert!(WindowIterator::from_regions(intervals(&[]), WindowSpec::Count(0)).is_err());
+    }
+
+    #[test]
+    fn test_windows_as_query() {
+        let genome = parse_genome("chr1\t30\n".as_bytes()).unwrap();
+        let spec = WindowSpec::Size { size: 10, step: 10 };
+        let windows = Box::new(WindowIterator::from_genome(&genome, spec).unwrap());
+        let b = intervals(&[("chr1", 5, 15)]);
+        let counts: Vec<usize> =
+            IntersectionIterator::new(windows, vec![b], &genome, -1, -1, false)
+                .unwrap()
+                .map(|i| i.expect("error intersecting").overlapping.len())
+                .collect();
+        assert_eq!(counts, vec![1, 1, 0]);
+    }
+}
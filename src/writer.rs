@@ -1,4 +1,5 @@
-use crate::column::{Column, ColumnReporter, Value};
+use crate::chrom_ordering::Chromosome;
+use crate::column::{Column, ColumnReporter, Number, Value, VcfField};
 use crate::hts_format::{Compression, Format};
 use crate::intersection::Intersections;
 use crate::position::Position;
@@ -9,8 +10,13 @@ use rust_htslib::bam;
 use rust_htslib::bcf::{self, header::HeaderView};
 use rust_htslib::htslib as hts;
 use simplebed::{self, BedValue};
+use std::cell::RefCell;
+use std::ffi::CString;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Seek, Write};
 use std::mem;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::result::Result;
 use std::string::String;
@@ -54,9 +60,180 @@ impl From<std::io::Error> for FormatConversionError {
 pub enum InputHeader {
     Vcf(HeaderView),
     Sam(bam::Header),
+    /// contig names and lengths in genome file order. Used to create a VCF header when the
+    /// query (e.g. BED) has none.
+    Genome(Vec<(String, Option<usize>)>),
     None,
 }
 
+impl InputHeader {
+    pub fn from_genome(
+        chrom_order: &hashbrown::HashMap<crate::string::String, Chromosome>,
+    ) -> Self {
+        let mut contigs: Vec<_> = chrom_order.iter().collect();
+        contigs.sort_by_key(|(_, c)| c.index);
+        InputHeader::Genome(
+            contigs
+                .into_iter()
+                .map(|(name, c)| (name.to_string(), c.length))
+                .collect(),
+        )
+    }
+}
+
+// a BGZF file written with htslib. Unlike dropping a writer, `close` reports the errors from
+// writing the last block and the EOF marker.
+struct BgzfOutput {
+    inner: *mut hts::BGZF,
+    path: String,
+}
+
+impl BgzfOutput {
+    fn create(path: &str) -> io::Result<Self> {
+        let c_path = CString::new(path).map_err(io::Error::other)?;
+        let inner = unsafe { hts::bgzf_open(c_path.as_ptr(), c"w".as_ptr()) };
+        if inner.is_null() {
+            return Err(io::Error::other(format!(
+                "error opening {} for writing",
+                path
+            )));
+        }
+        Ok(BgzfOutput {
+            inner,
+            path: path.to_string(),
+        })
+    }
+
+    fn close(mut self) -> io::Result<()> {
+        let ret = unsafe { hts::bgzf_close(self.inner) };
+        self.inner = std::ptr::null_mut();
+        if ret != 0 {
+            return Err(io::Error::other(format!("error closing {}", self.path)));
+        }
+        Ok(())
+    }
+}
+
+impl Write for BgzfOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = unsafe { hts::bgzf_write(self.inner, buf.as_ptr() as *const _, buf.len() as _) };
+        if n < 0 {
+            return Err(io::Error::other(format!("error writing {}", self.path)));
+        }
+        Ok(n as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if unsafe { hts::bgzf_flush(self.inner) } != 0 {
+            return Err(io::Error::other(format!("error writing {}", self.path)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BgzfOutput {
+    fn drop(&mut self) {
+        if !self.inner.is_null() {
+            unsafe { hts::bgzf_close(self.inner) };
+        }
+    }
+}
+
+// a plain, bgzipped or gzipped file. `finish` must be called to see errors from closing it.
+enum CompressedOutput {
+    Plain(BufWriter<File>),
+    Bgzf(BgzfOutput),
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+}
+
+impl CompressedOutput {
+    fn create(path: &str, compression: Compression) -> Result<Self, FormatConversionError> {
+        Ok(match compression {
+            Compression::None => CompressedOutput::Plain(BufWriter::new(File::create(path)?)),
+            Compression::Bgzf => CompressedOutput::Bgzf(BgzfOutput::create(path)?),
+            Compression::Gzip => CompressedOutput::Gzip(flate2::write::GzEncoder::new(
+                BufWriter::new(File::create(path)?),
+                flate2::Compression::default(),
+            )),
+            _ => {
+                return Err(FormatConversionError::IoError(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{:?} compression is not supported for {}",
+                        compression, path
+                    ),
+                )))
+            }
+        })
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            CompressedOutput::Plain(mut w) => w.flush(),
+            CompressedOutput::Bgzf(w) => w.close(),
+            CompressedOutput::Gzip(w) => w.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for CompressedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedOutput::Plain(w) => w.write(buf),
+            CompressedOutput::Bgzf(w) => w.write(buf),
+            CompressedOutput::Gzip(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedOutput::Plain(w) => w.flush(),
+            CompressedOutput::Bgzf(w) => w.flush(),
+            CompressedOutput::Gzip(w) => w.flush(),
+        }
+    }
+}
+
+// a CompressedOutput that is shared with the BED writer, which owns its output, so that it
+// can be finished after the BED writer is dropped.
+#[derive(Clone)]
+struct SharedOutput(Rc<RefCell<CompressedOutput>>);
+
+impl SharedOutput {
+    fn finish(self) -> io::Result<()> {
+        Rc::try_unwrap(self.0)
+            .map_err(|_| io::Error::other("output is still in use"))?
+            .into_inner()
+            .finish()
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+// a VCF header with the genome contigs and the END of converted BED records.
+fn genome_header(contigs: &[(String, Option<usize>)]) -> bcf::Header {
+    let mut header = bcf::Header::new();
+    for (name, length) in contigs {
+        let contig = match length {
+            Some(length) => format!("##contig=<ID={},length={}>", name, length),
+            None => format!("##contig=<ID={}>", name),
+        };
+        header.push_record(contig.as_bytes());
+    }
+    header.push_record(
+        br#"##INFO=<ID=END,Number=1,Type=Integer,Description="End position of the interval">"#,
+    );
+    header
+}
+
 /// A writer for the possible genomic formats
 pub enum GenomicWriter {
     Vcf(bcf::Writer),
@@ -65,6 +242,8 @@ pub enum GenomicWriter {
     //Sam(bam::Writer),
     Bed(simplebed::BedWriter),
     //Gff(gff::Writer<HFile>),
+    Table(TableWriter),
+    Columnar(crate::columnar::ColumnarWriter),
 }
 
 impl fmt::Debug for GenomicWriter {
@@ -73,16 +252,58 @@ impl fmt::Debug for GenomicWriter {
             GenomicWriter::Vcf(_) => write!(f, "GenomicWriter::Vcf"),
             GenomicWriter::Bcf(_) => write!(f, "GenomicWriter::Bcf"),
             GenomicWriter::Bed(_) => write!(f, "GenomicWriter::Bed"),
+            GenomicWriter::Table(t) => write!(f, "GenomicWriter::Table({:?})", t.format),
+            GenomicWriter::Columnar(_) => write!(f, "GenomicWriter::Columnar"),
         }
     }
 }
 
+/// Index built for bgzipped output by `Writer::finish`.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, clap::ValueEnum)]
+pub enum IndexType {
+    /// Tabix index (.tbi). Chromosomes must be shorter than 2^29 bases.
+    #[value(name = "tbi")]
+    Tbi,
+    /// Coordinate-sorted index (.csi).
+    #[value(name = "csi")]
+    Csi,
+}
+
+/// How `Writer` lays out reports. The tabular formats have named fields: `a_chrom`,
+/// `a_start`, `a_end`, then `b1_chrom`, `b1_start`, `b1_end` for each B file, then the columns.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// The format of the query, with B intervals and columns as extra BED fields or INFO.
+    #[value(name = "native")]
+    #[default]
+    Native,
+    /// Tab-separated with a header row. Multiple B intervals are comma-separated.
+    #[value(name = "tsv")]
+    Tsv,
+    /// Comma-separated with a header row. Multiple B intervals are comma-separated and quoted.
+    #[value(name = "csv")]
+    Csv,
+    /// One JSON object per line with the B intervals of each file as a list of objects.
+    #[value(name = "jsonl", alias = "json-lines")]
+    Jsonl,
+    /// Parquet with the B intervals of each file as a list of structs and typed columns.
+    #[value(name = "parquet")]
+    Parquet,
+    /// Arrow IPC file with the same schema as Parquet.
+    #[value(name = "arrow", alias = "ipc")]
+    Arrow,
+}
+
 #[allow(dead_code)]
 pub struct Writer {
     format: Format,
     compression: Compression,
     writer: GenomicWriter,
     header: InputHeader,
+    path: String,
+    index: Option<IndexType>,
+    // the output of BED writers, which is finished after the BED writer is dropped.
+    bed_output: Option<SharedOutput>,
 }
 
 #[allow(dead_code)]
@@ -138,14 +359,16 @@ fn update_header(header: &mut bcf::Header, columns: &[Column]) {
         let name = column.name();
         // INFO=<ID=ID,Number=number,Type=type,Description="description",Source="source",Version="version">
         // We'll use Number="." for unknown number of values, Type="String" as a general type for now.
+        // FORMAT lines have the same attributes.
         let info_line = format!(
-            "##INFO=<ID={},Number={},Type={},Description=\"{}\">",
+            "##{}=<ID={},Number={},Type={},Description=\"{}\">",
+            column.vcf_field(),
             name,
             column.number(),
             column.ftype(),
             column.description(),
         );
-        log::info!("Adding INFO field: {}", info_line);
+        log::info!("Adding {} field: {}", column.vcf_field(), info_line);
         header.push_record(info_line.as_bytes());
     }
 }
@@ -173,12 +396,11 @@ impl Writer {
             Some(f) => f,
             None => unimplemented!("format must be specified"),
         };
+        // Use the compression implied by the path if not specified
+        let compression = compression.unwrap_or_else(|| Compression::from_path(path));
         let path = if path == "-" { "/dev/stdout" } else { path };
 
-        // Use default compression if not specified
-        let compression = compression.unwrap_or(Compression::None);
-        // TODO: set compression in htslib.
-
+        let mut bed_output = None;
         let writer = match format {
             Format::Vcf | Format::Bcf => {
                 let mut header = match &input_header {
@@ -186,13 +408,27 @@ impl Writer {
                     InputHeader::Sam(_) => {
                         return Err(FormatConversionError::UnsupportedFormat(format.into()))
                     }
+                    InputHeader::Genome(contigs) => genome_header(contigs),
                     InputHeader::None => {
-                        // TODO: create a minimal header if none is provided.
-                        // For now, error out.
+                        // the contigs are required to write records.
                         return Err(FormatConversionError::UnsupportedFormat(format.into()));
                     }
                 };
-                // TODO: first update the writer header
+                let n_samples = match &input_header {
+                    InputHeader::Vcf(h) => h.sample_count(),
+                    _ => 0,
+                };
+                if let Some(c) = columns.iter().find(|c| c.vcf_field() == VcfField::Format) {
+                    if n_samples == 0 {
+                        return Err(FormatConversionError::IoError(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "FORMAT column {} requires a VCF query with samples",
+                                c.name()
+                            ),
+                        )));
+                    }
+                }
                 update_header(&mut header, columns);
                 /*
                 let mut kstr = rust_htslib::htslib::kstring_t {
@@ -208,6 +444,7 @@ impl Writer {
                 eprintln!("header: {:?}", s);
                 */
 
+                // htslib writes gzip-compressed VCF as BGZF.
                 let writer = bcf::Writer::from_path(
                     path,
                     &header,
@@ -225,7 +462,12 @@ impl Writer {
                 unimplemented!("BAM writing not yet implemented");
             }
             Format::Bed => {
-                let bed_writer = simplebed::BedWriter::new(path)
+                let output = SharedOutput(Rc::new(RefCell::new(CompressedOutput::create(
+                    path,
+                    compression,
+                )?)));
+                bed_output = Some(output.clone());
+                let bed_writer = simplebed::BedWriter::from_writer(Box::new(output))
                     .map_err(|e| FormatConversionError::HtslibError(e.to_string()))?;
                 GenomicWriter::Bed(bed_writer)
             }
@@ -237,9 +479,173 @@ impl Writer {
             compression,
             writer,
             header: input_header.clone(),
+            path: path.to_string(),
+            index: None,
+            bed_output,
+        })
+    }
+
+    /// A writer for the tabular `OutputFormat`s with fields for `n_b` B files and `columns`.
+    /// The TSV and CSV header row is written here. Parquet and Arrow are compressed internally
+    /// so `compression` must be None for them.
+    pub fn init_table(
+        path: &str,
+        format: OutputFormat,
+        compression: Option<Compression>,
+        n_b: usize,
+        columns: &[Column<'_>],
+    ) -> Result<Self, FormatConversionError> {
+        let compression = compression.unwrap_or_else(|| Compression::from_path(path));
+        let path = if path == "-" { "/dev/stdout" } else { path };
+        let writer = match format {
+            OutputFormat::Parquet | OutputFormat::Arrow => {
+                if compression != Compression::None {
+                    return Err(FormatConversionError::IoError(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "{:?} output can not be {:?} compressed",
+                            format, compression
+                        ),
+                    )));
+                }
+                GenomicWriter::Columnar(crate::columnar::ColumnarWriter::new(
+                    path, format, n_b, columns,
+                )?)
+            }
+            _ => {
+                let names = columns.iter().map(|c| c.name().to_string()).collect();
+                let out = CompressedOutput::create(path, compression)?;
+                GenomicWriter::Table(TableWriter::new(out, format, n_b, names)?)
+            }
+        };
+        Ok(Self {
+            format: Format::Text,
+            compression,
+            writer,
+            header: InputHeader::None,
+            path: path.to_string(),
+            index: None,
+            bed_output: None,
         })
     }
 
+    /// Build an index of the output when it is finished. The output must be a BGZF file,
+    /// e.g. with a `.gz`, `.bgz` or `.bcf` suffix. BCF can only have a CSI index.
+    pub fn with_index(mut self, index: IndexType) -> Result<Self, FormatConversionError> {
+        let invalid = |msg: &str| {
+            FormatConversionError::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can not index {}: {}", self.path, msg),
+            ))
+        };
+        if self.path == "/dev/stdout" || self.compression != Compression::Bgzf {
+            return Err(invalid(
+                "indexing requires bgzipped output to a file (.gz, .bgz or .bcf)",
+            ));
+        }
+        if matches!(
+            self.writer,
+            GenomicWriter::Table(_) | GenomicWriter::Columnar(_)
+        ) {
+            return Err(invalid("only BED, VCF and BCF output can be indexed"));
+        }
+        if self.format == Format::Bcf && index == IndexType::Tbi {
+            return Err(invalid("BCF requires a CSI index"));
+        }
+        self.index = Some(index);
+        Ok(self)
+    }
+
+    /// Close the output and build the index requested with `with_index`, if any.
+    pub fn finish(self) -> Result<(), FormatConversionError> {
+        let Writer {
+            format,
+            writer,
+            path,
+            index,
+            bed_output,
+            ..
+        } = self;
+        // the file must be closed (with the BGZF EOF block) before it is indexed, and errors
+        // from closing it must be seen before an index is built over a truncated file.
+        // bcf::Writer closes its file on drop and has no fallible close, so errors closing
+        // VCF/BCF output can not be reported here.
+        match writer {
+            GenomicWriter::Table(table) => table.finish()?,
+            GenomicWriter::Columnar(columnar) => columnar.finish()?,
+            GenomicWriter::Vcf(w) | GenomicWriter::Bcf(w) => drop(w),
+            GenomicWriter::Bed(mut w) => {
+                w.flush()
+                    .map_err(|e| FormatConversionError::HtslibError(e.to_string()))?;
+                drop(w);
+                if let Some(output) = bed_output {
+                    output.finish()?;
+                }
+            }
+        }
+        let Some(index) = index else {
+            return Ok(());
+        };
+        let min_shift = match index {
+            IndexType::Tbi => 0,
+            IndexType::Csi => 14,
+        };
+        let c_path = CString::new(path.as_str())
+            .map_err(|e| FormatConversionError::HtslibError(e.to_string()))?;
+        let ret = unsafe {
+            match format {
+                Format::Bed => hts::tbx_index_build(c_path.as_ptr(), min_shift, &hts::tbx_conf_bed),
+                _ => hts::bcf_index_build(c_path.as_ptr(), min_shift),
+            }
+        };
+        if ret != 0 {
+            return Err(FormatConversionError::HtslibError(format!(
+                "error building {:?} index for {} (code {})",
+                index, path, ret
+            )));
+        }
+        Ok(())
+    }
+
+    /// Convert a BED record to VCF for output with a header from `InputHeader::Genome`.
+    /// REF is `N`, ALT is missing and INFO/END is the BED end. The BED name is the ID and the
+    /// score is the QUAL.
+    fn bed_to_vcf_record(
+        &self,
+        bed: &crate::bedder_bed::BedRecord,
+    ) -> Result<bcf::Record, std::io::Error> {
+        use rust_htslib::bcf::record::Numeric;
+        let vcf_writer = match &self.writer {
+            GenomicWriter::Vcf(w) | GenomicWriter::Bcf(w) => w,
+            _ => unreachable!("BED records are converted only for VCF output"),
+        };
+        let bed = bed.inner();
+        let rid = vcf_writer
+            .header()
+            .name2rid(bed.chrom().as_bytes())
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("chromosome {} is not in the genome file", bed.chrom()),
+                )
+            })?;
+        let to_io = |e: rust_htslib::errors::Error| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        };
+        let mut record = vcf_writer.empty_record();
+        record.set_rid(Some(rid));
+        record.set_pos(bed.start() as i64);
+        record.set_alleles(&[b"N".as_slice()]).map_err(to_io)?;
+        if let Some(name) = bed.name() {
+            record.set_id(name.as_bytes()).map_err(to_io)?;
+        }
+        record.set_qual(bed.score().map_or(f32::missing(), |s| s as f32));
+        record
+            .push_info_integer(b"END", &[bed.end() as i32])
+            .map_err(to_io)?;
+        Ok(record)
+    }
+
     #[allow(dead_code)]
     fn add_info_field_to_vcf_record(
         record: &mut bcf::Record,
@@ -277,15 +683,113 @@ impl Writer {
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
     }
 
+    /// The number of values of each sample for a FORMAT field of `number` at a record with
+    /// `n_allele` alleles, or `None` if any number is allowed. `G` assumes diploid genotypes.
+    fn format_values_per_sample(number: &Number, n_allele: usize) -> Option<usize> {
+        match number {
+            Number::One => Some(1),
+            Number::R => Some(n_allele),
+            Number::A => Some(n_allele.saturating_sub(1)),
+            Number::G => Some(n_allele * (n_allele + 1) / 2),
+            Number::Not | Number::Dot => None,
+        }
+    }
+
+    /// Values of FORMAT fields are per sample, in sample order, with the same number of values
+    /// for each sample. A single value is used for every sample. Strings are a single
+    /// comma-separated string per sample. The number of values of each sample must agree with
+    /// the `Number` of the field and the alleles of the record.
+    fn add_format_field_to_vcf_record(
+        record: &mut bcf::Record,
+        key: &str,
+        number: &Number,
+        value: &Value,
+    ) -> Result<(), std::io::Error> {
+        let n_samples = record.sample_count() as usize;
+        let (n_values, is_string) = match value {
+            Value::VecInt(v) => (v.len(), false),
+            Value::VecFloat(v) => (v.len(), false),
+            Value::VecString(v) => (v.len(), true),
+            Value::Flag(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("FORMAT field {} can not be a Flag", key),
+                ))
+            }
+            _ => (n_samples, false),
+        };
+        // htslib stores one string per sample.
+        let per_sample = if is_string {
+            n_values == n_samples
+        } else {
+            n_samples > 0 && n_values > 0 && n_values % n_samples == 0
+        };
+        if !per_sample {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "FORMAT field {} has {} values for {} samples",
+                    key, n_values, n_samples
+                ),
+            ));
+        }
+        let n_allele = record.allele_count() as usize;
+        if let Some(expected) = Self::format_values_per_sample(number, n_allele) {
+            // a sample string has comma-separated values; missing (".") strings are allowed.
+            let count = |s: &str| (s != ".").then(|| s.split(',').count());
+            let per_sample: Vec<Option<usize>> = match value {
+                Value::String(s) => vec![count(s)],
+                Value::VecString(v) => v.iter().map(|s| count(s)).collect(),
+                _ => vec![Some(n_values / n_samples)],
+            };
+            let bad = per_sample.into_iter().flatten().find(|&n| n != expected);
+            if let Some(n) = bad {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "FORMAT field {} with Number={} has {} values per sample but a record with {} alleles needs {}",
+                        key, number, n, n_allele, expected
+                    ),
+                ));
+            }
+        }
+        let key_bytes = key.as_bytes();
+        match value {
+            Value::Int(i) => record.push_format_integer(key_bytes, &vec![*i; n_samples]),
+            Value::Float(f) => record.push_format_float(key_bytes, &vec![*f; n_samples]),
+            Value::String(s) => {
+                record.push_format_string(key_bytes, &vec![s.as_bytes(); n_samples])
+            }
+            Value::VecInt(v) => record.push_format_integer(key_bytes, v),
+            Value::VecFloat(v) => record.push_format_float(key_bytes, v),
+            Value::VecString(v) => {
+                let byte_slices: Vec<&[u8]> = v.iter().map(|s| s.as_bytes()).collect();
+                record.push_format_string(key_bytes, &byte_slices)
+            }
+            Value::Flag(_) => unreachable!("checked above"),
+        }
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    fn add_column_to_vcf_record(
+        record: &mut bcf::Record,
+        key: &str,
+        field: VcfField,
+        number: &Number,
+        value: &Value,
+    ) -> Result<(), std::io::Error> {
+        match field {
+            VcfField::Info => Self::add_info_field_to_vcf_record(record, key, value),
+            VcfField::Format => Self::add_format_field_to_vcf_record(record, key, number, value),
+        }
+    }
+
     fn apply_report<T: ColumnReporter>(
         &mut self,
         format: Format,
-        intersections: &mut Intersections,
-        report_options: Arc<ReportOptions>,
+        report: &Report,
         crs: &[T],
-    ) -> Result<Arc<Report>, std::io::Error> {
-        let report = intersections.report(&report_options);
-
+    ) -> Result<(), std::io::Error> {
         match format {
             Format::Vcf | Format::Bcf => {
                 for frag in report.iter() {
@@ -293,7 +797,9 @@ impl Writer {
                     let mut column_values = Vec::new();
                     for cr in crs.iter() {
                         match cr.value(frag) {
-                            Ok(value) => column_values.push((cr.name(), value)),
+                            Ok(value) => {
+                                column_values.push((cr.name(), cr.vcf_field(), cr.number(), value))
+                            }
                             Err(e) => {
                                 return Err(std::io::Error::new(
                                     std::io::ErrorKind::InvalidData,
@@ -317,13 +823,31 @@ impl Writer {
                     match *record {
                         Position::Vcf(ref mut record) => {
                             self.translate(&mut record.record)?;
-                            for (name, value) in column_values {
-                                Self::add_info_field_to_vcf_record(
+                            for (name, field, number, value) in column_values {
+                                Self::add_column_to_vcf_record(
                                     &mut record.record,
                                     name,
+                                    field,
+                                    number,
+                                    &value,
+                                )?;
+                            }
+                        }
+                        Position::Bed(ref bed) if matches!(self.header, InputHeader::Genome(_)) => {
+                            let mut vcf_record = self.bed_to_vcf_record(bed)?;
+                            let chrom = Arc::<str>::from(bed.inner().chrom());
+                            for (name, field, number, value) in column_values {
+                                Self::add_column_to_vcf_record(
+                                    &mut vcf_record,
+                                    name,
+                                    field,
+                                    number,
                                     &value,
                                 )?;
                             }
+                            *record = Position::Vcf(Box::new(
+                                crate::bedder_vcf::BedderRecord::new_with_chrom(vcf_record, chrom),
+                            ));
                         }
                         _ => {
                             return Err(std::io::Error::new(
@@ -393,7 +917,7 @@ impl Writer {
                 ))
             }
         }
-        Ok(report)
+        Ok(())
     }
 
     pub fn write<T: ColumnReporter>(
@@ -403,10 +927,27 @@ impl Writer {
         crs: &[T],
         filter: Option<&crate::py::CompiledExpr<'_>>,
     ) -> Result<(), std::io::Error> {
+        let report = intersections.report(&report_options);
+        self.write_report(&report, crs, filter)
+    }
+
+    /// Write a report that was not generated by `Intersections::report`,
+    /// e.g. from `Intersections::subtract`.
+    pub fn write_report<T: ColumnReporter>(
+        &mut self,
+        report: &Report,
+        crs: &[T],
+        filter: Option<&crate::py::CompiledExpr<'_>>,
+    ) -> Result<(), std::io::Error> {
+        match &mut self.writer {
+            GenomicWriter::Table(table) => return table.write_report(report, crs, filter),
+            GenomicWriter::Columnar(columnar) => return columnar.write_report(report, crs, filter),
+            _ => {}
+        }
         let format = self.format;
         match format {
             Format::Vcf | Format::Bcf => {
-                let report = self.apply_report(format, intersections, report_options, crs)?;
+                self.apply_report(format, report, crs)?;
                 let vcf_writer = match &mut self.writer {
                     GenomicWriter::Vcf(writer) | GenomicWriter::Bcf(writer) => writer,
                     _ => {
@@ -444,7 +985,7 @@ impl Writer {
                 }
             }
             Format::Bed => {
-                let report = self.apply_report(format, intersections, report_options, crs)?;
+                self.apply_report(format, report, crs)?;
                 let bed_writer = match &mut self.writer {
                     GenomicWriter::Bed(writer) => writer,
                     _ => {
@@ -496,26 +1037,762 @@ impl Writer {
         }
         Ok(())
     }
+}
 
-    // TODO: use serde?
-    #[allow(unused)]
-    fn format_value(&self, value: &Value) -> String {
-        match value {
-            Value::Int(i) => i.to_string(),
-            Value::Float(f) => f.to_string(),
-            Value::String(s) => s.clone(),
-            Value::Flag(b) => if *b { "1" } else { "0" }.to_string(),
-            Value::VecInt(v) => v
-                .iter()
-                .map(|i| i.to_string())
-                .collect::<Vec<String>>()
-                .join(","),
-            Value::VecFloat(v) => v
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<String>>()
-                .join(","),
-            Value::VecString(v) => v.join(","),
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Flag(b) => if *b { "1" } else { "0" }.to_string(),
+        Value::VecInt(v) => v
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        Value::VecFloat(v) => v
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        Value::VecString(v) => v.join(","),
+    }
+}
+
+// a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
         }
     }
+    out.push('"');
+    out
+}
+
+// JSON has no NaN or infinity.
+fn json_float(f: f32) -> String {
+    if f.is_finite() {
+        f.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    let list = |items: Vec<String>| format!("[{}]", items.join(","));
+    match value {
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => json_float(*f),
+        Value::String(s) => json_string(s),
+        Value::Flag(b) => b.to_string(),
+        Value::VecInt(v) => list(v.iter().map(|i| i.to_string()).collect()),
+        Value::VecFloat(v) => list(v.iter().map(|f| json_float(*f)).collect()),
+        Value::VecString(v) => list(v.iter().map(|s| json_string(s)).collect()),
+    }
+}
+
+// CSV fields are quoted if they contain a delimiter, quote or line break.
+fn csv_field(s: &str) -> std::borrow::Cow<'_, str> {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\"")).into()
+    } else {
+        s.into()
+    }
+}
+
+/// The named fields of a report fragment for TSV, CSV, JSON-lines and columnar output.
+pub(crate) struct TableRow {
+    /// chrom, start and end of A.
+    pub a: Option<(String, u64, u64)>,
+    /// the index of the B file of `b`.
+    pub id: usize,
+    /// chrom, start and end of each B interval.
+    pub b: Vec<(String, u64, u64)>,
+    /// a value for each column.
+    pub values: Vec<Value>,
+}
+
+impl TableRow {
+    /// None if the fragment does not pass the filter.
+    pub(crate) fn from_fragment<T: ColumnReporter>(
+        frag: &crate::report::ReportFragment,
+        crs: &[T],
+        filter: Option<&crate::py::CompiledExpr<'_>>,
+    ) -> io::Result<Option<Self>> {
+        if let Some(filt) = filter {
+            let ok = filt
+                .eval_bool(PyReportFragment::new(frag.clone()))
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Error evaluating filter: {}", e),
+                    )
+                })?;
+            if !ok {
+                return Ok(None);
+            }
+        }
+        let values = crs
+            .iter()
+            .map(|cr| {
+                cr.value(frag).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Error getting value for column: {}. Error: {}",
+                            cr.name(),
+                            e
+                        ),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        // a and b may share a lock, so each is released before the next is taken.
+        let a = frag.a.as_ref().map(|a| {
+            let a = a.try_lock().expect("Failed to lock a-Position");
+            (a.chrom().to_string(), a.start(), a.stop())
+        });
+        let b = frag
+            .b
+            .iter()
+            .map(|b| {
+                let b = b.try_lock().expect("Failed to lock b-Position");
+                (b.chrom().to_string(), b.start(), b.stop())
+            })
+            .collect();
+        Ok(Some(TableRow {
+            a,
+            id: frag.id,
+            b,
+            values,
+        }))
+    }
+}
+
+/// Writes each report fragment as a row of a TSV, CSV or JSON-lines table. The B intervals of
+/// a fragment all come from one file; the fields of the other B files are empty.
+pub struct TableWriter {
+    out: CompressedOutput,
+    format: OutputFormat,
+    n_b: usize,
+    /// column names from `ColumnReporter::name`.
+    names: Vec<String>,
+}
+
+impl TableWriter {
+    fn new(
+        mut out: CompressedOutput,
+        format: OutputFormat,
+        n_b: usize,
+        names: Vec<String>,
+    ) -> io::Result<Self> {
+        let delimiter = match format {
+            OutputFormat::Tsv => "\t",
+            OutputFormat::Csv => ",",
+            OutputFormat::Jsonl => "",
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} output is not a text table", format),
+                ))
+            }
+        };
+        if format != OutputFormat::Jsonl {
+            let mut header = vec!["a_chrom".to_string(), "a_start".into(), "a_end".into()];
+            for i in 1..=n_b {
+                header.extend(["chrom", "start", "end"].map(|f| format!("b{}_{}", i, f)));
+            }
+            header.extend(names.iter().cloned());
+            if format == OutputFormat::Csv {
+                header = header.iter().map(|h| csv_field(h).into_owned()).collect();
+            }
+            writeln!(out, "{}", header.join(delimiter))?;
+        }
+        Ok(TableWriter {
+            out,
+            format,
+            n_b,
+            names,
+        })
+    }
+
+    fn write_report<T: ColumnReporter>(
+        &mut self,
+        report: &Report,
+        crs: &[T],
+        filter: Option<&crate::py::CompiledExpr<'_>>,
+    ) -> io::Result<()> {
+        for frag in report.iter() {
+            if let Some(row) = TableRow::from_fragment(frag, crs, filter)? {
+                match self.format {
+                    OutputFormat::Jsonl => self.write_json(&row)?,
+                    _ => self.write_delimited(&row)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_delimited(&mut self, row: &TableRow) -> io::Result<()> {
+        let mut fields = match &row.a {
+            Some((chrom, start, stop)) => vec![chrom.clone(), start.to_string(), stop.to_string()],
+            None => vec![String::new(); 3],
+        };
+        for i in 0..self.n_b {
+            if i == row.id {
+                let b = &row.b;
+                let chroms: Vec<&str> = b.iter().map(|(chrom, _, _)| chrom.as_str()).collect();
+                let starts: Vec<String> = b.iter().map(|(_, start, _)| start.to_string()).collect();
+                let stops: Vec<String> = b.iter().map(|(_, _, stop)| stop.to_string()).collect();
+                fields.extend([chroms.join(","), starts.join(","), stops.join(",")]);
+            } else {
+                fields.extend([String::new(), String::new(), String::new()]);
+            }
+        }
+        fields.extend(row.values.iter().map(format_value));
+        if self.format == OutputFormat::Csv {
+            let fields: Vec<_> = fields.iter().map(|f| csv_field(f)).collect();
+            writeln!(self.out, "{}", fields.join(","))
+        } else {
+            writeln!(self.out, "{}", fields.join("\t"))
+        }
+    }
+
+    fn write_json(&mut self, row: &TableRow) -> io::Result<()> {
+        let mut fields = match &row.a {
+            Some((chrom, start, stop)) => vec![
+                format!("\"a_chrom\":{}", json_string(chrom)),
+                format!("\"a_start\":{}", start),
+                format!("\"a_end\":{}", stop),
+            ],
+            None => vec![
+                "\"a_chrom\":null".to_string(),
+                "\"a_start\":null".into(),
+                "\"a_end\":null".into(),
+            ],
+        };
+        for i in 0..self.n_b {
+            let intervals: Vec<String> = if i == row.id {
+                row.b
+                    .iter()
+                    .map(|(chrom, start, stop)| {
+                        format!(
+                            "{{\"chrom\":{},\"start\":{},\"end\":{}}}",
+                            json_string(chrom),
+                            start,
+                            stop
+                        )
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+            fields.push(format!("\"b{}\":[{}]", i + 1, intervals.join(",")));
+        }
+        for (name, value) in self.names.iter().zip(&row.values) {
+            fields.push(format!("{}:{}", json_string(name), json_value(value)));
+        }
+        writeln!(self.out, "{{{}}}", fields.join(","))
+    }
+
+    fn finish(self) -> io::Result<()> {
+        self.out.finish()
+    }
+}
+
+/// Formats with a single numeric value per interval that can be loaded into genome browsers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalFormat {
+    BedGraph,
+    BigWig,
+}
+
+impl SignalFormat {
+    /// The format implied by the output path: `.bedgraph`, `.bdg` or `.bg` for BedGraph and
+    /// `.bw` or `.bigwig` for bigWig (case-insensitive).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "bedgraph" | "bdg" | "bg" => Some(SignalFormat::BedGraph),
+            "bw" | "bigwig" => Some(SignalFormat::BigWig),
+            _ => None,
+        }
+    }
+}
+
+enum SignalOutput {
+    BedGraph(Box<dyn Write>),
+    /// bigWig values are staged as BedGraph in a temporary file and converted on `finish`,
+    /// because the bigWig data and zoom levels are written after all values are known.
+    BigWig {
+        path: PathBuf,
+        chrom_sizes: std::collections::HashMap<String, u32>,
+        staged: BufWriter<File>,
+    },
+}
+
+/// Writes a value for each interval as BedGraph or bigWig. Intervals must be written in
+/// the order of the genome file and, for bigWig, must not overlap; `write` returns an error
+/// for an interval that overlaps the previous one.
+pub struct SignalWriter {
+    output: SignalOutput,
+    max_zooms: u32,
+    // chrom and end of the last bigWig interval.
+    last: Option<(String, u64)>,
+}
+
+impl SignalWriter {
+    /// bigWig output requires a length for every chromosome in `chrom_order`.
+    pub fn new(
+        path: &Path,
+        format: SignalFormat,
+        chrom_order: &hashbrown::HashMap<crate::string::String, Chromosome>,
+    ) -> io::Result<Self> {
+        let output = match format {
+            SignalFormat::BedGraph => {
+                SignalOutput::BedGraph(Box::new(BufWriter::new(File::create(path)?)))
+            }
+            SignalFormat::BigWig => {
+                let chrom_sizes = chrom_order
+                    .iter()
+                    .map(|(name, c)| match c.length {
+                        Some(length) => Ok((name.to_string(), length as u32)),
+                        None => Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "bigWig output requires chromosome lengths in the genome file; none for {}",
+                                name
+                            ),
+                        )),
+                    })
+                    .collect::<io::Result<_>>()?;
+                SignalOutput::BigWig {
+                    path: path.to_path_buf(),
+                    chrom_sizes,
+                    staged: BufWriter::new(tempfile::tempfile()?),
+                }
+            }
+        };
+        Ok(SignalWriter {
+            output,
+            max_zooms: 10,
+            last: None,
+        })
+    }
+
+    /// The maximum number of bigWig zoom levels (default 10). Ignored for BedGraph.
+    pub fn with_max_zooms(mut self, max_zooms: u32) -> Self {
+        self.max_zooms = max_zooms;
+        self
+    }
+
+    pub fn write(&mut self, chrom: &str, start: u64, stop: u64, value: f64) -> io::Result<()> {
+        match &mut self.output {
+            SignalOutput::BedGraph(w) => writeln!(w, "{}\t{}\t{}\t{}", chrom, start, stop, value),
+            SignalOutput::BigWig { staged, .. } => {
+                match &mut self.last {
+                    Some((last_chrom, last_end)) if last_chrom.as_str() == chrom => {
+                        if start < *last_end {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!(
+                                    "bigWig intervals can not overlap: {}:{}-{} starts before the end ({}) of the previous interval; write BedGraph instead",
+                                    chrom, start, stop, last_end
+                                ),
+                            ));
+                        }
+                        *last_end = stop;
+                    }
+                    _ => self.last = Some((chrom.to_string(), stop)),
+                }
+                writeln!(staged, "{}\t{}\t{}\t{}", chrom, start, stop, value)
+            }
+        }
+    }
+
+    /// Flush BedGraph output or build the bigWig (with its zoom levels) from the staged values.
+    pub fn finish(self) -> io::Result<()> {
+        match self.output {
+            SignalOutput::BedGraph(mut w) => w.flush(),
+            SignalOutput::BigWig {
+                path,
+                chrom_sizes,
+                staged,
+            } => {
+                let mut staged = staged.into_inner().map_err(|e| e.into_error())?;
+                staged.rewind()?;
+                let invalid = |line: &str| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid staged bigWig value: {}", line),
+                    )
+                };
+                let values = io::BufReader::new(staged).lines().map(move |line| {
+                    let line = line?;
+                    let fields: Vec<&str> = line.split('\t').collect();
+                    let [chrom, start, end, value] = fields[..] else {
+                        return Err(invalid(&line));
+                    };
+                    let value = bigtools::Value {
+                        start: start.parse().map_err(|_| invalid(&line))?,
+                        end: end.parse().map_err(|_| invalid(&line))?,
+                        value: value.parse().map_err(|_| invalid(&line))?,
+                    };
+                    Ok((chrom.to_string(), value))
+                });
+                let mut writer = bigtools::BigWigWrite::create_file(
+                    path.to_string_lossy().to_string(),
+                    chrom_sizes,
+                )?;
+                writer.options.max_zooms = self.max_zooms;
+                let runtime = tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(2)
+                    .build()?;
+                // values follow the genome file, which need not be sorted by chromosome name.
+                let data = bigtools::beddata::BedParserStreamingIterator::wrap_iter(values, true);
+                writer.write(data, runtime).map_err(|e| {
+                    io::Error::other(format!("error writing bigWig {}: {}", path.display(), e))
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bedder_bbi::BedderBbi;
+    use crate::position::PositionedIterator;
+
+    fn genome() -> hashbrown::HashMap<crate::string::String, Chromosome> {
+        crate::chrom_ordering::parse_genome("chr2\t1000\nchr1\t2000\n".as_bytes())
+            .expect("error parsing genome")
+    }
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(Compression::from_path("out.bed.gz"), Compression::Bgzf);
+        assert_eq!(Compression::from_path("out.BGZ"), Compression::Bgzf);
+        assert_eq!(Compression::from_path("out.bcf"), Compression::Bgzf);
+        assert_eq!(Compression::from_path("out.vcf"), Compression::None);
+        assert_eq!(Compression::from_path("-"), Compression::None);
+    }
+
+    #[test]
+    fn test_write_indexed_bed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.bed.gz");
+        let path = path.to_str().unwrap();
+        let mut w = Writer::init(path, Some(Format::Bed), None, InputHeader::None, &[])
+            .unwrap()
+            .with_index(IndexType::Tbi)
+            .unwrap();
+        let GenomicWriter::Bed(bed) = &mut w.writer else {
+            panic!("expected a BED writer");
+        };
+        for (start, end) in [(10, 20), (30, 40)] {
+            let r = crate::bedder_bed::BedRecord::new("chr1", start, end, None, None, vec![]);
+            bed.write_record(r.inner()).unwrap();
+        }
+        w.finish().unwrap();
+        assert!(Path::new(&format!("{}.tbi", path)).exists());
+
+        let mut s = String::new();
+        let mut gz = flate2::read::MultiGzDecoder::new(File::open(path).unwrap());
+        io::Read::read_to_string(&mut gz, &mut s).unwrap();
+        assert_eq!(s, "chr1\t10\t20\nchr1\t30\t40\n");
+
+        // plain output can not be indexed.
+        let plain = dir.path().join("out.bed");
+        let w = Writer::init(
+            plain.to_str().unwrap(),
+            Some(Format::Bed),
+            None,
+            InputHeader::None,
+            &[],
+        )
+        .unwrap();
+        assert!(w.with_index(IndexType::Csi).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_finish_reports_write_errors() {
+        // writes to /dev/full fail with ENOSPC. the gzip and BGZF blocks are written on finish.
+        for compression in [Compression::Gzip, Compression::Bgzf] {
+            let mut w = Writer::init(
+                "/dev/full",
+                Some(Format::Bed),
+                Some(compression),
+                InputHeader::None,
+                &[],
+            )
+            .unwrap();
+            let GenomicWriter::Bed(bed) = &mut w.writer else {
+                panic!("expected a BED writer");
+            };
+            let r = crate::bedder_bed::BedRecord::new("chr1", 10, 20, None, None, vec![]);
+            bed.write_record(r.inner()).unwrap();
+            assert!(w.finish().is_err(), "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_write_bed_as_vcf() {
+        assert_eq!(Format::vcf_from_path("out.VCF.gz"), Some(Format::Vcf));
+        assert_eq!(Format::vcf_from_path("out.bcf"), Some(Format::Bcf));
+        assert_eq!(Format::vcf_from_path("out.bed.gz"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.vcf");
+        let path = path.to_str().unwrap();
+        let header = InputHeader::from_genome(&genome());
+        let mut w = Writer::init(path, Some(Format::Vcf), None, header, &[]).unwrap();
+        let bed = crate::bedder_bed::BedRecord::new("chr1", 10, 20, Some("r1"), Some(5.0), vec![]);
+        let record = w.bed_to_vcf_record(&bed).unwrap();
+        let GenomicWriter::Bcf(vcf) = &mut w.writer else {
+            panic!("expected a VCF writer");
+        };
+        vcf.write(&record).unwrap();
+        w.finish().unwrap();
+
+        let s = std::fs::read_to_string(path).unwrap();
+        let chr2 = s.find("##contig=<ID=chr2,length=1000>").unwrap();
+        assert!(chr2 < s.find("##contig=<ID=chr1,length=2000>").unwrap());
+        assert_eq!(s.lines().last(), Some("chr1\t11\tr1\tN\t.\t5\t.\tEND=20"));
+
+        let bed = crate::bedder_bed::BedRecord::new("chrX", 10, 20, None, None, vec![]);
+        let w = Writer::init(
+            path,
+            Some(Format::Vcf),
+            None,
+            InputHeader::from_genome(&genome()),
+            &[],
+        )
+        .unwrap();
+        assert!(w.bed_to_vcf_record(&bed).is_err());
+    }
+
+    #[test]
+    fn test_format_values_per_sample() {
+        assert_eq!(Writer::format_values_per_sample(&Number::One, 3), Some(1));
+        assert_eq!(Writer::format_values_per_sample(&Number::R, 3), Some(3));
+        assert_eq!(Writer::format_values_per_sample(&Number::A, 3), Some(2));
+        // diploid genotypes of 3 alleles: 0/0 0/1 1/1 0/2 1/2 2/2
+        assert_eq!(Writer::format_values_per_sample(&Number::G, 3), Some(6));
+        assert_eq!(Writer::format_values_per_sample(&Number::Dot, 3), None);
+    }
+
+    #[test]
+    fn test_write_format_field() {
+        use rust_htslib::bcf::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("template.vcf");
+        let mut header = bcf::Header::new();
+        header.push_record(b"##contig=<ID=chr1,length=2000>");
+        header.push_sample(b"s1").push_sample(b"s2");
+        drop(bcf::Writer::from_path(&template, &header, true, bcf::Format::Vcf).unwrap());
+        let view = bcf::Reader::from_path(&template).unwrap().header().clone();
+
+        let hm = std::collections::HashMap::new();
+        let columns = vec![Column::try_from(("FORMAT/hit:Integer:B overlap:1", &hm)).unwrap()];
+        let path = dir.path().join("out.vcf");
+        let path = path.to_str().unwrap();
+        let header = InputHeader::Vcf(view);
+        let mut w = Writer::init(path, Some(Format::Vcf), None, header, &columns).unwrap();
+        let GenomicWriter::Bcf(vcf) = &mut w.writer else {
+            panic!("expected a VCF writer");
+        };
+        let mut record = vcf.empty_record();
+        record.set_rid(Some(0));
+        record.set_pos(100);
+        record.set_alleles(&[b"A", b"T"]).unwrap();
+        Writer::add_column_to_vcf_record(
+            &mut record,
+            "hit",
+            VcfField::Format,
+            &Number::One,
+            &Value::VecInt(vec![1, 0]),
+        )
+        .unwrap();
+        let add = |record: &mut bcf::Record, number: &Number, value: &Value| {
+            Writer::add_format_field_to_vcf_record(record, "hit", number, value)
+        };
+        // one value for every sample, or a list with values for each sample.
+        assert!(add(&mut record, &Number::One, &Value::Int(1)).is_ok());
+        let three = Value::VecInt(vec![1, 0, 1]);
+        assert!(add(&mut record, &Number::One, &three).is_err());
+        // the values of each sample must agree with the Number and the 2 alleles of the record.
+        let four = Value::VecInt(vec![1, 0, 1, 0]);
+        assert!(add(&mut record, &Number::One, &four).is_err());
+        assert!(add(&mut record, &Number::G, &four).is_err());
+        assert!(add(&mut record, &Number::A, &four).is_err());
+        assert!(add(&mut record, &Number::Dot, &four).is_ok());
+        let strings = |v: &[&str]| Value::VecString(v.iter().map(|s| s.to_string()).collect());
+        assert!(add(&mut record, &Number::R, &strings(&["1,2", "."])).is_ok());
+        assert!(add(&mut record, &Number::R, &strings(&["1,2", "3"])).is_err());
+        add(&mut record, &Number::One, &Value::VecInt(vec![1, 0])).unwrap();
+        vcf.write(&record).unwrap();
+        w.finish().unwrap();
+
+        let s = std::fs::read_to_string(path).unwrap();
+        assert!(s.contains("##FORMAT=<ID=hit,Number=1,Type=Integer,Description=\"B overlap\">"));
+        assert_eq!(
+            s.lines().last(),
+            Some("chr1\t101\t.\tA\tT\t.\t.\t.\thit\t1\t0")
+        );
+
+        // BED queries have no samples.
+        let header = InputHeader::from_genome(&genome());
+        assert!(Writer::init(path, Some(Format::Vcf), None, header, &columns).is_err());
+    }
+
+    #[test]
+    fn test_write_table() {
+        use crate::report::ReportFragment;
+        use std::sync::Mutex;
+
+        let position = |chrom: &str, start: u64, end: u64| {
+            Arc::new(Mutex::new(Position::Bed(
+                crate::bedder_bed::BedRecord::new(chrom, start, end, None, None, vec![]),
+            )))
+        };
+        let report = Report::new(vec![ReportFragment {
+            a: Some(position("chr1", 10, 20)),
+            b: vec![position("chr1", 5, 12), position("chr1", 15, 30)],
+            id: 1,
+        }]);
+        let hm = std::collections::HashMap::new();
+        let columns = vec![Column::try_from(("count", &hm)).unwrap()];
+
+        let dir = tempfile::tempdir().unwrap();
+        let write = |format: OutputFormat| {
+            let path = dir.path().join("out.txt");
+            let path = path.to_str().unwrap();
+            let mut w = Writer::init_table(path, format, None, 2, &columns).unwrap();
+            w.write_report(&report, &columns, None).unwrap();
+            w.finish().unwrap();
+            std::fs::read_to_string(path).unwrap()
+        };
+
+        assert_eq!(
+            write(OutputFormat::Tsv),
+            "a_chrom\ta_start\ta_end\tb1_chrom\tb1_start\tb1_end\tb2_chrom\tb2_start\tb2_end\tcount\n\
+             chr1\t10\t20\t\t\t\tchr1,chr1\t5,15\t12,30\t2\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Csv).lines().nth(1),
+            Some("chr1,10,20,,,,\"chr1,chr1\",\"5,15\",\"12,30\",2")
+        );
+
+        let path = dir.path().join("out.tsv.gz");
+        let w = Writer::init_table(path.to_str().unwrap(), OutputFormat::Tsv, None, 2, &columns);
+        assert!(w.unwrap().with_index(IndexType::Tbi).is_err());
+        assert_eq!(
+            write(OutputFormat::Jsonl),
+            "{\"a_chrom\":\"chr1\",\"a_start\":10,\"a_end\":20,\"b1\":[],\"b2\":[\
+             {\"chrom\":\"chr1\",\"start\":5,\"end\":12},\
+             {\"chrom\":\"chr1\",\"start\":15,\"end\":30}],\"count\":2}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_value() {
+        assert_eq!(json_string("a\"b\\c\td\u{1}"), r#""a\"b\\c\td\u0001""#);
+        assert_eq!(json_value(&Value::Float(f32::NAN)), "null");
+        assert_eq!(json_value(&Value::Flag(true)), "true");
+        assert_eq!(
+            json_value(&Value::VecString(vec!["x".into(), "y".into()])),
+            r#"["x","y"]"#
+        );
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn test_signal_format_from_path() {
+        assert_eq!(
+            SignalFormat::from_path(Path::new("out.bedGraph")),
+            Some(SignalFormat::BedGraph)
+        );
+        assert_eq!(
+            SignalFormat::from_path(Path::new("out.bw")),
+            Some(SignalFormat::BigWig)
+        );
+        assert_eq!(SignalFormat::from_path(Path::new("out.bed")), None);
+        assert_eq!(SignalFormat::from_path(Path::new("-")), None);
+    }
+
+    #[test]
+    fn test_write_bigwig() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.bw");
+        let mut w = SignalWriter::new(&path, SignalFormat::BigWig, &genome()).unwrap();
+        w.write("chr2", 10, 20, 1.5).unwrap();
+        w.write("chr1", 0, 100, 3.0).unwrap();
+        w.write("chr1", 100, 200, 4.0).unwrap();
+        w.finish().unwrap();
+
+        let mut r = BedderBbi::from_path(path.to_str().unwrap())
+            .unwrap()
+            .with_chromosome_order(&genome());
+        let mut values = Vec::new();
+        while let Some(p) = r.next_position(None) {
+            let p = p.unwrap();
+            values.push((
+                p.chrom().to_string(),
+                p.start(),
+                p.stop(),
+                p.column_as_f64(5),
+            ));
+        }
+        assert_eq!(
+            values,
+            vec![
+                ("chr2".to_string(), 10, 20, Some(1.5)),
+                ("chr1".to_string(), 0, 100, Some(3.0)),
+                ("chr1".to_string(), 100, 200, Some(4.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_bigwig_rejects_overlaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut w =
+            SignalWriter::new(&dir.path().join("out.bw"), SignalFormat::BigWig, &genome()).unwrap();
+        w.write("chr1", 0, 100, 3.0).unwrap();
+        let e = w.write("chr1", 50, 150, 4.0).unwrap_err();
+        assert!(e.to_string().contains("can not overlap"), "{}", e);
+        // the same interval on another chromosome is fine.
+        w.write("chr2", 50, 150, 4.0).unwrap();
+
+        // BedGraph output may overlap.
+        let mut w = SignalWriter::new(
+            &dir.path().join("out.bedgraph"),
+            SignalFormat::BedGraph,
+            &genome(),
+        )
+        .unwrap();
+        w.write("chr1", 0, 100, 3.0).unwrap();
+        w.write("chr1", 50, 150, 4.0).unwrap();
+        w.finish().unwrap();
+    }
+
+    #[test]
+    fn test_write_bigwig_requires_lengths() {
+        let genome = crate::chrom_ordering::parse_genome("chr1\n".as_bytes()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let r = SignalWriter::new(&dir.path().join("out.bw"), SignalFormat::BigWig, &genome);
+        assert!(r.is_err());
+    }
 }
@@ -0,0 +1,56 @@
+// helpers shared by the integration tests; not every test file uses all of them.
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+/// Run `cargo run -- <args>` and return the non-empty, non-header lines of stdout. Panics with
+/// the stderr of bedder if it fails.
+pub fn run_bedder(args: &[&str]) -> Vec<String> {
+    stdout_lines(args, bedder_output(args))
+}
+
+/// `run_bedder` with `stdin` piped to bedder.
+pub fn run_bedder_with_stdin(args: &[&str], stdin: &[u8]) -> Vec<String> {
+    let mut child = Command::new("cargo")
+        .args(["run", "--"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to execute bedder");
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    stdout_lines(args, child.wait_with_output().unwrap())
+}
+
+fn stdout_lines(args: &[&str], output: Output) -> Vec<String> {
+    assert!(
+        output.status.success(),
+        "bedder {:?} failed:\n{}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Run `cargo run -- <args>` and return its output, whether or not bedder succeeds.
+pub fn bedder_output(args: &[&str]) -> Output {
+    Command::new("cargo")
+        .args(["run", "--"])
+        .args(args)
+        .output()
+        .expect("failed to execute bedder")
+}
+
+/// Write `contents` to `name` in `dir` and return its path.
+pub fn write_temp(dir: &TempDir, name: &str, contents: &str) -> String {
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
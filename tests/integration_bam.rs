@@ -0,0 +1,133 @@
+mod common;
+
+use common::{bedder_output, run_bedder, write_temp};
+
+// tests/test.bam and tests/test.sam have 2 reads on chr1: A at 999900-1000000 (reverse)
+// and B at 999913-1000013 (forward).
+const TARGETS: &str = "chr1\t999850\t999905\tleft\nchr1\t999950\t999960\tboth\nchr1\t1000005\t1000100\tright\nchr1\t2000000\t2000100\tnone\n";
+
+#[test]
+fn test_map_counts_reads_per_region() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", TARGETS);
+    for reads in ["tests/test.bam", "tests/test.sam"] {
+        let lines = run_bedder(&[
+            "map",
+            "-a",
+            &a_path,
+            "-b",
+            reads,
+            "-g",
+            "tests/hg38.small.fai",
+            "-O",
+            "count",
+        ]);
+        let counts: Vec<&str> = lines
+            .iter()
+            .map(|l| l.rsplit('\t').next().unwrap())
+            .collect();
+        assert_eq!(counts, vec!["1", "2", "1", "0"], "{}: {:?}", reads, lines);
+    }
+}
+
+#[test]
+fn test_intersect_reads_with_targets() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", TARGETS);
+    let lines = run_bedder(&[
+        "intersect",
+        "-a",
+        &a_path,
+        "-b",
+        "tests/test.bam",
+        "-g",
+        "tests/hg38.small.fai",
+    ]);
+    for name in ["left", "both", "right"] {
+        assert!(
+            lines.iter().any(|l| l.contains(&format!("\t{}", name))),
+            "expected {} in {:?}",
+            name,
+            lines
+        );
+    }
+    assert!(!lines.iter().any(|l| l.contains("\tnone")), "{:?}", lines);
+}
+
+#[test]
+fn test_alignments_are_rejected_as_query() {
+    let output = bedder_output(&[
+        "intersect",
+        "-a",
+        "tests/test.bam",
+        "-b",
+        "tests/test.bed",
+        "-g",
+        "tests/hg38.small.fai",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not supported for -a"));
+}
+
+fn map_counts(extra: &[&str]) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", TARGETS);
+    let mut args = vec![
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        "tests/test.bam",
+        "-g",
+        "tests/hg38.small.fai",
+        "-O",
+        "count",
+    ];
+    args.extend_from_slice(extra);
+    let lines = run_bedder(&args);
+    lines
+        .iter()
+        .map(|l| l.rsplit('\t').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_map_respects_alignment_filters() {
+    // read A is on the reverse strand (flag 16); both reads have MAPQ 42.
+    let counts = map_counts(&["--exclude-flags", "0x10"]);
+    assert_eq!(counts, vec!["0", "1", "1", "0"]);
+
+    let counts = map_counts(&["--require-flags", "16"]);
+    assert_eq!(counts, vec!["1", "1", "0", "0"]);
+
+    let counts = map_counts(&["--min-mapq", "43"]);
+    assert_eq!(counts, vec!["0", "0", "0", "0"]);
+}
+
+#[test]
+fn test_map_does_not_repeat_reads_after_index_skip() {
+    // tests/test.bam.bai lets the reader skip. the first target is far before both reads so the
+    // reader is asked to skip to it after it has already read A.
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(
+        &dir,
+        "a.bed",
+        "chr1\t800000\t800100\tbefore\nchr1\t999950\t999960\tboth\nchr1\t1000005\t1000100\tright\n",
+    );
+    let lines = run_bedder(&[
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        "tests/test.bam",
+        "-g",
+        "tests/hg38.small.fai",
+        "-O",
+        "count",
+    ]);
+    let counts: Vec<&str> = lines
+        .iter()
+        .map(|l| l.rsplit('\t').next().unwrap())
+        .collect();
+    assert_eq!(counts, vec!["0", "2", "1"], "{:?}", lines);
+}
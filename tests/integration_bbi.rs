@@ -0,0 +1,54 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+// tests/test.bw has chr1 values 100-200:1, 200-300:3, 500-600:10 and 2000000-2000100:4,
+// chr2 0-50:2 and chr10 10-20:5.
+#[test]
+fn test_map_bigwig_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(
+        &dir,
+        "a.bed",
+        "chr1\t150\t550\tr1\nchr1\t2000050\t2000060\tr2\nchr2\t1000\t2000\tr3\nchr10\t0\t100\tr4\n",
+    );
+    let lines = run_bedder(&[
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        "tests/test.bw",
+        "-g",
+        "tests/hg38.small.fai",
+        "-O",
+        "sum,max,count",
+    ]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t150\t550\tr1\t14\t10\t3",
+            "chr1\t2000050\t2000060\tr2\t4\t4\t1",
+            "chr2\t1000\t2000\tr3\t.\t.\t0",
+            "chr10\t0\t100\tr4\t5\t5\t1",
+        ]
+    );
+}
+
+#[test]
+fn test_intersect_bigbed() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", "chr1\t280\t290\tq1\n");
+    let lines = run_bedder(&[
+        "intersect",
+        "-a",
+        &a_path,
+        "-b",
+        "tests/test.bb",
+        "-g",
+        "tests/hg38.small.fai",
+    ]);
+    let out = lines.join("\n");
+    assert!(out.contains("gene1"), "{:?}", lines);
+    assert!(out.contains("gene2"), "{:?}", lines);
+    assert!(!out.contains("gene3"), "{:?}", lines);
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+fn run_intersect(extra: &[&str]) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    // a transcript with exons at 100-150 and 300-400.
+    let a_path = write_temp(
+        &dir,
+        "a.bed",
+        "chr1\t100\t400\ttx\t0\t+\t100\t400\t0\t2\t50,100,\t0,200,\n",
+    );
+    let b_path = write_temp(
+        &dir,
+        "b.bed",
+        "chr1\t140\t160\texon1\nchr1\t200\t250\tintron\nchr1\t390\t420\texon2\n",
+    );
+    let mut args = vec![
+        "intersect",
+        "-a",
+        a_path.as_str(),
+        "-b",
+        b_path.as_str(),
+        "-g",
+        "tests/hg38.small.fai",
+    ];
+    args.extend_from_slice(extra);
+    run_bedder(&args)
+}
+
+#[test]
+fn test_split_ignores_introns() {
+    let lines = run_intersect(&[]);
+    assert!(lines.iter().any(|l| l.contains("intron")), "{:?}", lines);
+
+    let lines = run_intersect(&["--split"]);
+    assert!(!lines.iter().any(|l| l.contains("intron")), "{:?}", lines);
+    assert!(lines.iter().any(|l| l.contains("exon1")), "{:?}", lines);
+    assert!(lines.iter().any(|l| l.contains("exon2")), "{:?}", lines);
+}
+
+#[test]
+fn test_split_a_pieces_are_blocks() {
+    let lines = run_intersect(&["--split", "-p", "piece", "-P", "none"]);
+    let spans: Vec<(&str, &str)> = lines
+        .iter()
+        .map(|l| {
+            let f: Vec<&str> = l.split('\t').collect();
+            (f[1], f[2])
+        })
+        .collect();
+    assert_eq!(spans, vec![("140", "150"), ("390", "400")], "{:?}", lines);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+fn run_closest(a: &str, b: &str, extra: &[&str]) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", a);
+    let b_path = write_temp(&dir, "b.bed", b);
+    let mut args = vec![
+        "closest",
+        "-a",
+        a_path.as_str(),
+        "-b",
+        b_path.as_str(),
+        "-g",
+        "tests/hg38.small.fai",
+    ];
+    args.extend_from_slice(extra);
+    run_bedder(&args)
+}
+
+#[test]
+fn test_signed_distance_relative_to_a_strand() {
+    let a = "chr1\t100\t110\tplus\t0\t+\nchr1\t300\t310\tminus\t0\t-\n";
+    let b = "chr1\t80\t90\tb1\t0\t+\nchr1\t330\t340\tb2\t0\t+\n";
+
+    let lines = run_closest(a, b, &["-n", "1", "-D", "ref"]);
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(lines[0].contains("\tb1") && lines[0].ends_with("\t-10"));
+    assert!(lines[1].contains("\tb2") && lines[1].ends_with("\t20"));
+
+    // b2 is upstream of the reverse-strand A interval.
+    let lines = run_closest(a, b, &["-n", "1", "-D", "a"]);
+    assert!(lines[0].ends_with("\t-10"), "{:?}", lines);
+    assert!(lines[1].ends_with("\t-20"), "{:?}", lines);
+}
+
+#[test]
+fn test_closest_upstream_only() {
+    let lines = run_closest(
+        "chr1\t100\t110\ta1\n",
+        "chr1\t50\t60\tfar_up\nchr1\t112\t115\tnear_down\n",
+        &["-n", "1", "--direction", "upstream"],
+    );
+    assert_eq!(lines.len(), 1, "{:?}", lines);
+    assert!(lines[0].contains("far_up") && !lines[0].contains("near_down"));
+}
+
+#[test]
+fn test_closest_ties_all() {
+    let a = "chr1\t100\t110\ta1\n";
+    let b = "chr1\t80\t90\tleft\nchr1\t120\t130\tright\n";
+
+    let lines = run_closest(a, b, &["-n", "1"]);
+    assert!(lines[0].contains("left") && !lines[0].contains("right"));
+
+    let lines = run_closest(a, b, &["-n", "1", "-t", "last"]);
+    assert!(!lines[0].contains("left") && lines[0].contains("right"));
+
+    let lines = run_closest(a, b, &["-n", "1", "-t", "all"]);
+    assert!(lines[0].contains("left") && lines[0].contains("right"));
+}
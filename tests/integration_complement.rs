@@ -0,0 +1,42 @@
+mod common;
+
+use common::{bedder_output, write_temp};
+
+fn run_complement(genome: &str) -> std::process::Output {
+    let dir = tempfile::tempdir().unwrap();
+    let genome_path = write_temp(&dir, "genome.txt", genome);
+    bedder_output(&["complement", "-a", "tests/map_b.bed", "-g", &genome_path])
+}
+
+#[test]
+fn test_complement_reports_uncovered_and_absent_chromosomes() {
+    let output = run_complement("chr1\t500\nchr2\t100\n");
+    assert!(
+        output.status.success(),
+        "bedder complement failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().filter(|l| !l.is_empty()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t0\t120",
+            "chr1\t190\t350",
+            "chr1\t380\t500",
+            "chr2\t0\t100",
+        ]
+    );
+}
+
+#[test]
+fn test_complement_missing_length_errors() {
+    let output = run_complement("chr1\t500\nchr2\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("chr2"),
+        "expected missing-length error naming chr2, got: {}",
+        stderr
+    );
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::run_bedder;
+
+/// Run `bedder coverage` with map_a/map_b and extra args, return stdout lines.
+fn run_coverage(args: &[&str]) -> Vec<String> {
+    let mut cmd_args = vec![
+        "coverage",
+        "-a",
+        "tests/map_a.bed",
+        "-b",
+        "tests/map_b.bed",
+        "-g",
+        "tests/hg38.small.fai",
+    ];
+    cmd_args.extend_from_slice(args);
+    run_bedder(&cmd_args)
+}
+
+#[test]
+fn test_coverage_does_not_double_count() {
+    // geneA is covered by 120-180, 130-170 and 150-190 => 70 bases, not 60+40+40.
+    let lines = run_coverage(&[]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t100\t200\tgeneA\t10\t3\t70\t100\t0.7000000",
+            "chr1\t300\t400\tgeneB\t20\t1\t30\t100\t0.3000000",
+        ]
+    );
+}
+
+#[test]
+fn test_coverage_mean() {
+    let lines = run_coverage(&["--mean"]);
+    // geneA: (60 + 40 + 40) / 100
+    assert_eq!(
+        lines[0],
+        "chr1\t100\t200\tgeneA\t10\t3\t70\t100\t0.7000000\t1.4000000"
+    );
+}
+
+#[test]
+fn test_coverage_hist() {
+    let lines = run_coverage(&["--hist"]);
+    // geneA depths: 0 over 100-120 and 190-200, 1 over 120-130 and 180-190,
+    // 2 over 130-150 and 170-180, 3 over 150-170.
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t100\t200\tgeneA\t10\t0\t30\t100\t0.3000000",
+            "chr1\t100\t200\tgeneA\t10\t1\t20\t100\t0.2000000",
+            "chr1\t100\t200\tgeneA\t10\t2\t30\t100\t0.3000000",
+            "chr1\t100\t200\tgeneA\t10\t3\t20\t100\t0.2000000",
+            "chr1\t300\t400\tgeneB\t20\t0\t70\t100\t0.7000000",
+            "chr1\t300\t400\tgeneB\t20\t1\t30\t100\t0.3000000",
+        ]
+    );
+}
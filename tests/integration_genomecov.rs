@@ -0,0 +1,81 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+#[test]
+fn test_genomecov_bedgraph() {
+    let dir = tempfile::tempdir().unwrap();
+    let genome_path = write_temp(&dir, "genome.txt", "chr1\t500\nchr2\t100\n");
+    let lines = run_bedder(&["genomecov", "-a", "tests/map_b.bed", "-g", &genome_path]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t0\t120\t0",
+            "chr1\t120\t130\t1",
+            "chr1\t130\t150\t2",
+            "chr1\t150\t170\t3",
+            "chr1\t170\t180\t2",
+            "chr1\t180\t190\t1",
+            "chr1\t190\t350\t0",
+            "chr1\t350\t380\t1",
+            "chr1\t380\t500\t0",
+            "chr2\t0\t100\t0",
+        ]
+    );
+}
+
+#[test]
+fn test_genomecov_skip_zero() {
+    let lines = run_bedder(&[
+        "genomecov",
+        "-a",
+        "tests/map_b.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "--skip-zero",
+    ]);
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[5], "chr1\t350\t380\t1");
+}
+
+// write genomecov output to `ext` and read the depths back with map.
+fn genomecov_round_trip(ext: &str) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    let genome_path = write_temp(&dir, "genome.txt", "chr1\t500\nchr2\t100\n");
+    let a_path = write_temp(&dir, "a.bed", "chr1\t140\t160\nchr1\t400\t450\n");
+    let out_path = dir.path().join(format!("depth.{}", ext));
+    let out_path = out_path.to_str().unwrap();
+
+    run_bedder(&[
+        "genomecov",
+        "-a",
+        "tests/map_b.bed",
+        "-g",
+        &genome_path,
+        "-o",
+        out_path,
+    ]);
+    run_bedder(&[
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        out_path,
+        "-g",
+        &genome_path,
+        "-O",
+        "max",
+    ])
+}
+
+#[test]
+fn test_genomecov_bigwig() {
+    let lines = genomecov_round_trip("bw");
+    assert_eq!(lines, vec!["chr1\t140\t160\t3", "chr1\t400\t450\t0"]);
+}
+
+#[test]
+fn test_genomecov_d4() {
+    let lines = genomecov_round_trip("d4");
+    assert_eq!(lines, vec!["chr1\t140\t160\t3", "chr1\t400\t450\t0"]);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::{bedder_output, run_bedder, write_temp};
+use tempfile::TempDir;
+
+const TARGETS: &str = "chr1\t100\t200\tt1\nchr1\t1000\t1100\tt2\n";
+
+const GTF: &str = "#!genome-build test
+chr1\ttest\tgene\t51\t150\t.\t+\t.\tgene_id \"g1\"; gene_name \"ABC\"; level \"2\";
+chr1\ttest\texon\t121\t180\t.\t+\t.\tgene_id \"g1\"; gene_name \"ABC\"; level \"3\";
+chr1\ttest\tgene\t181\t300\t.\t-\t.\tgene_id \"g2\"; gene_name \"XYZ\"; level \"1\";
+";
+
+fn write_inputs() -> (TempDir, String, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", TARGETS);
+    let b_path = write_temp(&dir, "b.gtf", GTF);
+    (dir, a_path, b_path)
+}
+
+#[test]
+fn test_map_gtf_attribute() {
+    let (_dir, a_path, b_path) = write_inputs();
+    let lines = run_bedder(&[
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        &b_path,
+        "-g",
+        "tests/hg38.small.fai",
+        "-c",
+        "attr:level",
+        "-O",
+        "sum,count",
+    ]);
+    let grouped = run_bedder(&[
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        &b_path,
+        "-g",
+        "tests/hg38.small.fai",
+        "-G",
+        "-O",
+        "count",
+    ]);
+
+    assert_eq!(
+        lines,
+        vec!["chr1\t100\t200\tt1\t6\t3", "chr1\t1000\t1100\tt2\t.\t0"]
+    );
+    // B features are grouped by their gene_name attribute.
+    assert!(
+        grouped.contains(&"chr1\t100\t200\tt1\tABC\t2".to_string()),
+        "{:?}",
+        grouped
+    );
+    assert!(
+        grouped.contains(&"chr1\t100\t200\tt1\tXYZ\t1".to_string()),
+        "{:?}",
+        grouped
+    );
+}
+
+#[test]
+fn test_attr_selector_requires_gff() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", TARGETS);
+    let output = bedder_output(&[
+        "map",
+        "-a",
+        &a_path,
+        "-b",
+        &a_path,
+        "-g",
+        "tests/hg38.small.fai",
+        "-c",
+        "attr:level",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires a GFF/GTF -b input"));
+}
+
+#[test]
+fn test_map_filters_gtf_features() {
+    let (_dir, a_path, b_path) = write_inputs();
+    let run = |extra: &[&str]| {
+        let mut args = vec![
+            "map",
+            "-a",
+            a_path.as_str(),
+            "-b",
+            b_path.as_str(),
+            "-g",
+            "tests/hg38.small.fai",
+            "-O",
+            "count",
+        ];
+        args.extend_from_slice(extra);
+        run_bedder(&args)
+    };
+    let genes = run(&["--feature-type", "gene"]);
+    let abc = run(&["--attribute", "gene_name=ABC"]);
+    let abc_genes = run(&["--feature-type", "gene", "--attribute", "gene_id~^g1$"]);
+
+    assert_eq!(genes[0], "chr1\t100\t200\tt1\t2");
+    assert_eq!(abc[0], "chr1\t100\t200\tt1\t2");
+    assert_eq!(abc_genes[0], "chr1\t100\t200\tt1\t1");
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+#[test]
+fn test_makewindows_sliding_over_regions() {
+    let lines = run_bedder(&[
+        "makewindows",
+        "-a",
+        "tests/map_a.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "-w",
+        "50",
+        "-s",
+        "25",
+    ]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t100\t150",
+            "chr1\t125\t175",
+            "chr1\t150\t200",
+            "chr1\t175\t200",
+            "chr1\t300\t350",
+            "chr1\t325\t375",
+            "chr1\t350\t400",
+            "chr1\t375\t400",
+        ]
+    );
+}
+
+#[test]
+fn test_makewindows_n_windows_with_names() {
+    let lines = run_bedder(&[
+        "makewindows",
+        "-a",
+        "tests/map_a.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "-n",
+        "2",
+        "--name",
+    ]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t100\t150\tgeneA_1",
+            "chr1\t150\t200\tgeneA_2",
+            "chr1\t300\t350\tgeneB_1",
+            "chr1\t350\t400\tgeneB_2",
+        ]
+    );
+}
+
+#[test]
+fn test_makewindows_genome() {
+    let dir = tempfile::tempdir().unwrap();
+    let genome_path = write_temp(&dir, "genome.txt", "chr1\t250\nchr2\t100\n");
+    let lines = run_bedder(&["makewindows", "-g", &genome_path, "-w", "100"]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t0\t100",
+            "chr1\t100\t200",
+            "chr1\t200\t250",
+            "chr2\t0\t100",
+        ]
+    );
+}
+
+#[test]
+fn test_map_over_windows() {
+    let lines = run_bedder(&[
+        "map",
+        "-a",
+        "tests/map_a.bed",
+        "-b",
+        "tests/map_b.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "--window-size",
+        "50",
+    ]);
+    assert_eq!(lines.len(), 4, "expected one row per window: {:?}", lines);
+    let expected = [
+        ("chr1\t100\t150\t", "\t12"),
+        ("chr1\t150\t200\t", "\t15"),
+        ("chr1\t300\t350\t", "\t."),
+        ("chr1\t350\t400\t", "\t4"),
+    ];
+    for (line, (prefix, suffix)) in lines.iter().zip(expected) {
+        assert!(
+            line.starts_with(prefix) && line.ends_with(suffix),
+            "unexpected map row: {}",
+            line
+        );
+    }
+}
@@ -587,3 +587,46 @@ fn test_map_column_zero_error() {
         "bedder map should have failed with -c 0"
     );
 }
+
+#[test]
+fn test_map_bedgraph_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_path = dir.path().join("out.bedgraph");
+    let out_path = out_path.to_str().unwrap();
+    let output = run_map_output(
+        "tests/map_a.bed",
+        "tests/map_b.bed",
+        &["-O", "mean", "-o", out_path],
+    );
+    let bedgraph = std::fs::read_to_string(out_path).unwrap_or_default();
+    assert!(
+        output.status.success(),
+        "bedder map failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(bedgraph, "chr1\t100\t200\t5\nchr1\t300\t400\t4\n");
+
+    // a bedGraph has one value per interval.
+    let output = run_map_output(
+        "tests/map_a.bed",
+        "tests/map_b.bed",
+        &["-O", "sum,count", "-o", out_path],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_map_bigwig_output_rejects_overlaps() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = dir.path().join("a.bed");
+    std::fs::write(&a_path, "chr1\t100\t200\tr1\nchr1\t150\t250\tr2\n").unwrap();
+    let out_path = dir.path().join("out.bw");
+    let output = run_map_output(
+        a_path.to_str().unwrap(),
+        "tests/map_b.bed",
+        &["-O", "sum", "-o", out_path.to_str().unwrap()],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("can not overlap"), "{}", stderr);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::run_bedder;
+
+/// Run `bedder merge` on the given input with extra args, return stdout lines.
+fn run_merge(input: &str, args: &[&str]) -> Vec<String> {
+    let mut cmd_args = vec!["merge", "-a", input, "-g", "tests/hg38.small.fai"];
+    cmd_args.extend_from_slice(args);
+    run_bedder(&cmd_args)
+}
+
+#[test]
+fn test_merge_default() {
+    let lines = run_merge("tests/map_b.bed", &[]);
+    assert_eq!(lines, vec!["chr1\t120\t190", "chr1\t350\t380"]);
+}
+
+#[test]
+fn test_merge_distance_and_ops() {
+    let lines = run_merge(
+        "tests/map_b.bed",
+        &["-d", "200", "-c", "5", "-O", "sum,count"],
+    );
+    assert_eq!(lines, vec!["chr1\t120\t380\t19\t4"]);
+}
+
+#[test]
+fn test_merge_same_strand() {
+    let lines = run_merge("tests/merge_stranded.bed", &["-s", "-c", "5", "-O", "max"]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t100\t300\t+\t3",
+            "chr1\t150\t250\t-\t2",
+            "chr1\t400\t500\t-\t4",
+        ]
+    );
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use common::{bedder_output, write_temp};
+use std::fs;
+use std::io::Read;
+
+fn intersect_to(output_path: &str, extra: &[&str]) -> std::process::Output {
+    let mut args = vec![
+        "intersect",
+        "-a",
+        "tests/map_a.bed",
+        "-b",
+        "tests/map_b.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "-o",
+        output_path,
+    ];
+    args.extend_from_slice(extra);
+    bedder_output(&args)
+}
+
+#[test]
+fn test_bgzipped_output_with_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.bed.gz");
+    let path = path.to_str().unwrap();
+    let output = intersect_to(path, &["--index", "tbi"]);
+    assert!(
+        output.status.success(),
+        "bedder intersect failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(fs::metadata(format!("{}.tbi", path)).is_ok());
+
+    let mut s = String::new();
+    flate2::read::MultiGzDecoder::new(fs::File::open(path).unwrap())
+        .read_to_string(&mut s)
+        .unwrap();
+    assert!(s.starts_with("chr1\t100\t200"), "unexpected output: {}", s);
+}
+
+#[test]
+fn test_index_requires_bgzipped_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.bed");
+    let output = intersect_to(path.to_str().unwrap(), &["--index", "csi"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("bgzipped"));
+}
+
+#[test]
+fn test_bed_query_written_as_vcf() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.vcf");
+    let path = path.to_str().unwrap();
+    let output = intersect_to(path, &["-c", "count"]);
+    assert!(
+        output.status.success(),
+        "bedder intersect failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let s = fs::read_to_string(path).unwrap();
+    assert!(s.contains("##contig=<ID=chr1,length=248956422>"));
+    assert!(s.contains("##INFO=<ID=count,"));
+    assert!(
+        s.lines()
+            .any(|l| l.starts_with("chr1\t101\tgeneA\tN\t.\t10\t.\tEND=200;count=")),
+        "unexpected output: {}",
+        s
+    );
+}
+
+#[test]
+fn test_tabular_output_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.tsv");
+    let path = path.to_str().unwrap();
+    let table = |format: &str| {
+        let output = intersect_to(
+            path,
+            &[
+                "--a-piece",
+                "whole-wide",
+                "-c",
+                "count",
+                "--output-format",
+                format,
+            ],
+        );
+        assert!(
+            output.status.success(),
+            "bedder intersect failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        fs::read_to_string(path).unwrap()
+    };
+
+    let tsv = table("tsv");
+    let lines: Vec<&str> = tsv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "a_chrom\ta_start\ta_end\tb1_chrom\tb1_start\tb1_end\tcount"
+    );
+    assert_eq!(
+        lines[1],
+        "chr1\t100\t200\tchr1,chr1,chr1\t120,130,150\t180,170,190\t3"
+    );
+    assert_eq!(lines[2], "chr1\t300\t400\tchr1\t350\t380\t1");
+
+    let csv = table("csv");
+    assert_eq!(
+        csv.lines().nth(1),
+        Some("chr1,100,200,\"chr1,chr1,chr1\",\"120,130,150\",\"180,170,190\",3")
+    );
+
+    let jsonl = table("json-lines");
+    assert_eq!(
+        jsonl.lines().nth(1),
+        Some(
+            "{\"a_chrom\":\"chr1\",\"a_start\":300,\"a_end\":400,\
+             \"b1\":[{\"chrom\":\"chr1\",\"start\":350,\"end\":380}],\"count\":1}"
+        )
+    );
+}
+
+#[test]
+fn test_parquet_output() {
+    use arrow::array::AsArray;
+    use arrow::datatypes::{Int32Type, UInt64Type};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.parquet");
+    let path = path.to_str().unwrap();
+    let output = intersect_to(
+        path,
+        &[
+            "--a-piece",
+            "whole-wide",
+            "-c",
+            "count",
+            "--output-format",
+            "parquet",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "bedder intersect failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let reader = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(
+        fs::File::open(path).unwrap(),
+    )
+    .unwrap()
+    .build()
+    .unwrap();
+    let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+    let a_start = batch
+        .column_by_name("a_start")
+        .unwrap()
+        .as_primitive::<UInt64Type>();
+    assert_eq!(a_start.values().to_vec(), vec![100, 300]);
+    let b1 = batch.column_by_name("b1").unwrap().as_list::<i32>();
+    assert_eq!(b1.value_length(0), 3);
+    let count = batch
+        .column_by_name("count")
+        .unwrap()
+        .as_primitive::<Int32Type>();
+    assert_eq!(count.values().to_vec(), vec![3, 1]);
+}
+
+#[test]
+fn test_format_columns_on_vcf_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(
+        &dir,
+        "a.vcf",
+        "##fileformat=VCFv4.3\n##contig=<ID=chr1,length=1000000>\n\
+         #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ts1\ts2\n\
+         chr1\t121\t.\tA\tT\t.\tPASS\t.\t.\t.\t.\n",
+    );
+    let path = dir.path().join("out.vcf");
+    let path = path.to_str().unwrap();
+    let run = |column: &str| {
+        bedder_output(&[
+            "intersect",
+            "-a",
+            &a_path,
+            "-b",
+            "tests/map_b.bed",
+            "-g",
+            "tests/hg38.small.fai",
+            "-o",
+            path,
+            "-c",
+            column,
+        ])
+    };
+
+    // the count of B intervals (1) is written for each sample.
+    let output = run("FORMAT/hit:Integer:B overlaps:1:count");
+    assert!(
+        output.status.success(),
+        "bedder intersect failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let s = fs::read_to_string(path).unwrap();
+    assert!(s.contains("##FORMAT=<ID=hit,Number=1,Type=Integer,Description=\"B overlaps\">"));
+    let last = s.lines().last().unwrap();
+    assert!(last.ends_with("\thit\t1\t1"), "unexpected output: {}", s);
+
+    // a biallelic record needs 3 values per sample for Number=G.
+    let output = run("FORMAT/hit:Integer:B overlaps:G:count");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Number=G has 1 values"), "{}", stderr);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::run_bedder;
+
+fn pairtobed(overlap_type: &str) -> Vec<String> {
+    run_bedder(&[
+        "pairtobed",
+        "-a",
+        "tests/pairs_a.bedpe",
+        "-b",
+        "tests/map_b.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "--type",
+        overlap_type,
+    ])
+}
+
+fn pairtopair(overlap_type: &str) -> Vec<String> {
+    run_bedder(&[
+        "pairtopair",
+        "-a",
+        "tests/pairs_a.bedpe",
+        "-b",
+        "tests/pairs_b.bedpe",
+        "-g",
+        "tests/hg38.small.fai",
+        "--type",
+        overlap_type,
+    ])
+}
+
+fn names(lines: &[String]) -> Vec<&str> {
+    lines
+        .iter()
+        .map(|l| l.split('\t').nth(6).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_pairtobed_types() {
+    let either = pairtobed("either");
+    assert_eq!(
+        names(&either),
+        vec!["p1", "p1", "p1", "p2", "p2", "p2", "p2"]
+    );
+    assert!(either[6].ends_with("\tchr1\t350\t380"));
+
+    assert_eq!(names(&pairtobed("both")), vec!["p2"; 4]);
+    assert_eq!(names(&pairtobed("xor")), vec!["p1"; 3]);
+    assert_eq!(
+        pairtobed("neither"),
+        vec!["chr2\t50\t60\tchr1\t600\t700\tp3\t30\t-\t+"]
+    );
+    assert_eq!(names(&pairtobed("notboth")), vec!["p1", "p3"]);
+}
+
+#[test]
+fn test_pairtopair_types() {
+    let both = pairtopair("both");
+    assert_eq!(names(&both), vec!["p1", "p2"]);
+    // p1 overlaps q2 with the ends swapped.
+    assert!(both[0].contains("\tq2\t"));
+    assert!(both[1].contains("\tq1\t"));
+
+    let either = pairtopair("either");
+    assert_eq!(names(&either), vec!["p1", "p1", "p2"]);
+    assert_eq!(names(&pairtopair("neither")), vec!["p3"]);
+    assert_eq!(names(&pairtopair("notboth")), vec!["p3"]);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+#[test]
+fn test_slop_both_sides_clamped() {
+    let dir = tempfile::tempdir().unwrap();
+    let genome_path = write_temp(&dir, "genome.txt", "chr1\t420\n");
+    let lines = run_bedder(&[
+        "slop",
+        "-a",
+        "tests/map_a.bed",
+        "-g",
+        &genome_path,
+        "-b",
+        "50",
+    ]);
+    assert_eq!(
+        lines,
+        vec!["chr1\t50\t250\tgeneA\t10", "chr1\t250\t420\tgeneB\t20"]
+    );
+}
+
+#[test]
+fn test_slop_fraction() {
+    let lines = run_bedder(&[
+        "slop",
+        "-a",
+        "tests/map_a.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "-r",
+        "50%",
+    ]);
+    assert_eq!(
+        lines,
+        vec!["chr1\t100\t250\tgeneA\t10", "chr1\t300\t450\tgeneB\t20"]
+    );
+}
+
+#[test]
+fn test_flank_both_sides() {
+    let lines = run_bedder(&[
+        "flank",
+        "-a",
+        "tests/map_a.bed",
+        "-g",
+        "tests/hg38.small.fai",
+        "-b",
+        "20",
+    ]);
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t80\t100\tgeneA\t10",
+            "chr1\t200\t220\tgeneA\t10",
+            "chr1\t280\t300\tgeneB\t20",
+            "chr1\t400\t420\tgeneB\t20",
+        ]
+    );
+}
+
+#[test]
+fn test_intersect_stranded_promoters() {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(
+        &dir,
+        "genes.bed",
+        "chr1\t100\t200\tg1\t0\t+\nchr1\t300\t400\tg2\t0\t-\n",
+    );
+    let b_path = write_temp(
+        &dir,
+        "peaks.bed",
+        "chr1\t85\t90\tp1\nchr1\t150\t160\tbody\nchr1\t410\t415\tp2\n",
+    );
+
+    let lines = run_bedder(&[
+        "intersect",
+        "-a",
+        &a_path,
+        "-b",
+        &b_path,
+        "-g",
+        "tests/hg38.small.fai",
+        "--a-left",
+        "20",
+        "--a-flank",
+        "--a-stranded",
+    ]);
+
+    assert_eq!(lines.len(), 2, "expected 2 promoter overlaps: {:?}", lines);
+    assert!(
+        lines[0].starts_with("chr1\t80\t100\tg1") && lines[0].contains("\tp1"),
+        "unexpected row: {}",
+        lines[0]
+    );
+    assert!(
+        lines[1].starts_with("chr1\t400\t420\tg2") && lines[1].contains("\tp2"),
+        "unexpected row: {}",
+        lines[1]
+    );
+}
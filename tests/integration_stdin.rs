@@ -0,0 +1,42 @@
+mod common;
+
+use common::run_bedder_with_stdin;
+use flate2::write::GzEncoder;
+use std::io::Write;
+
+fn merge_stdin(stdin: &[u8]) -> Vec<String> {
+    run_bedder_with_stdin(&["merge", "-a", "-", "-g", "tests/hg38.small.fai"], stdin)
+}
+
+#[test]
+fn test_merge_plain_and_gzip_stdin() {
+    let bed = std::fs::read("tests/map_b.bed").unwrap();
+    let expected = vec!["chr1\t120\t190", "chr1\t350\t380"];
+    assert_eq!(merge_stdin(&bed), expected);
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&bed).unwrap();
+    assert_eq!(merge_stdin(&encoder.finish().unwrap()), expected);
+}
+
+#[test]
+fn test_intersect_query_from_stdin() {
+    let a = std::fs::read("tests/map_a.bed").unwrap();
+    let lines = run_bedder_with_stdin(
+        &[
+            "intersect",
+            "-a",
+            "-",
+            "-b",
+            "tests/map_b.bed",
+            "-g",
+            "tests/hg38.small.fai",
+        ],
+        &a,
+    );
+    assert!(
+        lines.iter().any(|l| l.starts_with("chr1\t100\t200")),
+        "{:?}",
+        lines
+    );
+}
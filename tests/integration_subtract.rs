@@ -0,0 +1,97 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+/// Run `bedder subtract` against temp A/B files, return stdout data lines.
+fn run_subtract(a: &str, bs: &[&str], args: &[&str]) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", a);
+    let b_paths: Vec<String> = bs
+        .iter()
+        .enumerate()
+        .map(|(i, b)| write_temp(&dir, &format!("b{}.bed", i), b))
+        .collect();
+
+    let mut cmd_args = vec!["subtract", "-a", a_path.as_str()];
+    for p in &b_paths {
+        cmd_args.extend_from_slice(&["-b", p.as_str()]);
+    }
+    cmd_args.extend_from_slice(&["-g", "tests/hg38.small.fai"]);
+    cmd_args.extend_from_slice(args);
+    run_bedder(&cmd_args)
+}
+
+#[test]
+fn test_subtract_multiple_b() {
+    let lines = run_subtract(
+        "chr1\t10\t100\tA1\nchr1\t200\t300\tA2\n",
+        &["chr1\t20\t50\tB1\nchr1\t30\t40\tB2\n", "chr1\t60\t70\tC1\n"],
+        &[],
+    );
+    assert_eq!(
+        lines,
+        vec![
+            "chr1\t10\t20\tA1",
+            "chr1\t50\t60\tA1",
+            "chr1\t70\t100\tA1",
+            "chr1\t200\t300\tA2",
+        ]
+    );
+}
+
+#[test]
+fn test_subtract_remove_whole() {
+    let lines = run_subtract(
+        "chr1\t10\t100\tA1\nchr1\t200\t300\tA2\n",
+        &["chr1\t20\t50\tB1\n"],
+        &["-A"],
+    );
+    assert_eq!(lines, vec!["chr1\t200\t300\tA2"]);
+}
+
+#[test]
+fn test_subtract_fraction_of_a() {
+    // B1 covers 30% of A1 and is kept; B2 covers 60% and is subtracted.
+    let lines = run_subtract(
+        "chr1\t0\t100\tA1\n",
+        &["chr1\t0\t30\tB1\nchr1\t40\t100\tB2\n"],
+        &["-r", "0.5"],
+    );
+    assert_eq!(lines, vec!["chr1\t0\t40\tA1"]);
+}
+
+#[test]
+fn test_subtract_split_bed12() {
+    // exons at 100-150 and 300-400; the intron-only B is not subtracted with --split.
+    let a = "chr1\t100\t400\ttx\t0\t+\t100\t400\t0\t2\t50,100,\t0,200,\n";
+    let bs = ["chr1\t200\t250\tintron\nchr1\t320\t340\texon2\n"];
+    let spans = |lines: Vec<String>| -> Vec<(String, String)> {
+        lines
+            .iter()
+            .map(|l| {
+                let f: Vec<&str> = l.split('\t').collect();
+                (f[1].to_string(), f[2].to_string())
+            })
+            .collect()
+    };
+
+    let lines = run_subtract(a, &bs, &[]);
+    assert_eq!(
+        spans(lines),
+        vec![
+            ("100".to_string(), "200".to_string()),
+            ("250".to_string(), "320".to_string()),
+            ("340".to_string(), "400".to_string()),
+        ]
+    );
+
+    let lines = run_subtract(a, &bs, &["--split"]);
+    assert_eq!(
+        spans(lines),
+        vec![
+            ("100".to_string(), "150".to_string()),
+            ("300".to_string(), "320".to_string()),
+            ("340".to_string(), "400".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{run_bedder, write_temp};
+
+fn run_intersect(a: &str, b: &str, extra: &[&str]) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    let a_path = write_temp(&dir, "a.bed", a);
+    let b_path = write_temp(&dir, "b.bed", b);
+    let mut args = vec![
+        "intersect",
+        "-a",
+        a_path.as_str(),
+        "-b",
+        b_path.as_str(),
+        "-g",
+        "tests/hg38.small.fai",
+    ];
+    args.extend_from_slice(extra);
+    run_bedder(&args)
+}
+
+#[test]
+fn test_window_reports_nearby_b_with_original_a() {
+    let lines = run_intersect(
+        "chr1\t100\t110\ta1\n",
+        "chr1\t50\t60\tb1\nchr1\t85\t95\tb2\nchr1\t115\t120\tb3\nchr1\t200\t210\tb4\n",
+        &["-w", "20"],
+    );
+    assert_eq!(lines.len(), 2, "expected 2 B within 20 bases: {:?}", lines);
+    assert!(lines[0].starts_with("chr1\t100\t110\ta1") && lines[0].ends_with("\tb2"));
+    assert!(lines[1].starts_with("chr1\t100\t110\ta1") && lines[1].ends_with("\tb3"));
+}
+
+#[test]
+fn test_window_stranded_upstream() {
+    let lines = run_intersect(
+        "chr1\t100\t110\tplus\t0\t+\nchr1\t300\t310\tminus\t0\t-\n",
+        "chr1\t85\t95\tup_plus\nchr1\t115\t120\tdown_plus\nchr1\t285\t290\tdown_minus\nchr1\t315\t318\tup_minus\n",
+        &["--window-left", "20", "--window-right", "0", "--window-stranded"],
+    );
+    assert_eq!(lines.len(), 2, "expected 2 upstream B: {:?}", lines);
+    assert!(lines[0].starts_with("chr1\t100\t110\tplus") && lines[0].ends_with("\tup_plus"));
+    assert!(lines[1].starts_with("chr1\t300\t310\tminus") && lines[1].ends_with("\tup_minus"));
+}